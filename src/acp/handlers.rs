@@ -265,6 +265,43 @@ pub(super) async fn handle_trace_artifacts(
     Json(crate::trace::get_artifacts(&run_id))
 }
 
+#[cfg(feature = "acp")]
+#[derive(Debug, Deserialize)]
+pub(super) struct PromptSnapshotQuery {
+    iteration: Option<u32>,
+    session_id: Option<String>,
+}
+
+#[cfg(feature = "acp")]
+#[derive(Debug, Deserialize)]
+pub(super) struct PromptDiffQuery {
+    from: String,
+    to: String,
+    session_id: Option<String>,
+}
+
+#[cfg(feature = "acp")]
+pub(super) async fn handle_prompt_snapshot(
+    Path(turn_id): Path<String>,
+    Query(query): Query<PromptSnapshotQuery>,
+) -> Result<Json<crate::trace::prompt::PromptSnapshot>, (StatusCode, String)> {
+    crate::trace::prompt::prompt_snapshot(&turn_id, query.iteration, query.session_id.as_deref())
+        .map(Json)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))
+}
+
+#[cfg(feature = "acp")]
+pub(super) async fn handle_prompt_diff(
+    Query(query): Query<PromptDiffQuery>,
+) -> Result<Json<crate::trace::prompt::PromptDiff>, (StatusCode, String)> {
+    let session_id = query.session_id.as_deref();
+    let from = crate::trace::prompt::prompt_snapshot(&query.from, None, session_id)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))?;
+    let to = crate::trace::prompt::prompt_snapshot(&query.to, None, session_id)
+        .map_err(|e| (StatusCode::NOT_FOUND, e))?;
+    Ok(Json(crate::trace::prompt::diff_prompts(&from, &to)))
+}
+
 #[cfg(feature = "acp")]
 pub(super) async fn handle_live_trace(
     Path(session_id): Path<String>,
@@ -290,3 +327,19 @@ pub(super) async fn handle_live_trace(
 
     Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default())
 }
+
+#[cfg(feature = "acp")]
+pub(super) async fn handle_sandbox_report(
+    State(server): State<Arc<AcpServer>>,
+    Path(session_id): Path<String>,
+) -> Json<crate::tools::sandbox::SandboxReport> {
+    let sandbox = match server.session_manager.get_session(&session_id).await {
+        Some(agent) => agent.lock().await.session_sandbox(),
+        None => None,
+    };
+    Json(crate::tools::sandbox::SandboxReport::build(
+        &session_id,
+        sandbox.as_deref(),
+        crate::app::commands::SANDBOX_REPORT_VIOLATION_LIMIT,
+    ))
+}
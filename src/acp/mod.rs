@@ -20,8 +20,9 @@ mod output;
 
 #[cfg(feature = "acp")]
 use handlers::{
-    handle_capabilities, handle_live_trace, handle_run, handle_trace_artifacts,
-    handle_trace_records, handle_trace_run, handle_trace_runs, handle_trace_tree,
+    handle_capabilities, handle_live_trace, handle_prompt_diff, handle_prompt_snapshot, handle_run,
+    handle_sandbox_report, handle_trace_artifacts, handle_trace_records, handle_trace_run,
+    handle_trace_runs, handle_trace_tree,
 };
 
 #[cfg(feature = "acp")]
@@ -71,6 +72,9 @@ impl AcpServer {
             .route("/trace/run/:run_id/tree", get(handle_trace_tree))
             .route("/trace/run/:run_id/artifacts", get(handle_trace_artifacts))
             .route("/trace/live/:session_id", get(handle_live_trace))
+            .route("/trace/prompt/:turn_id", get(handle_prompt_snapshot))
+            .route("/trace/prompt-diff", get(handle_prompt_diff))
+            .route("/sandbox/:session_id", get(handle_sandbox_report))
             .with_state(Arc::new(self));
 
         tracing::info!("ACP Server listening on {}", addr);
@@ -1,19 +1,43 @@
 use std::sync::Arc;
 
+use crate::memory::extraction::MemoryExtractor;
 use crate::memory::WorkspaceMemory;
+use crate::rag::indexer::CodeIndexer;
 use crate::rag::VectorStore;
 use crate::tools::{
-    BashTool, ExecTool, PatchFileTool, RagInsertTool, RagSearchTool, ReadFileTool, ReadMemoryTool,
-    SendFileTool, TavilySearchTool, Tool, WaitTool, WebFetchTool, WriteFileTool, WriteMemoryTool,
+    BashTool, ExecTool, IngestTool, PatchFileTool, RagDeleteTool, RagInsertTool, RagListTool,
+    RagSearchTool, ReadFileTool, ReadMemoryTool, SendFileTool, TavilySearchTool, Tool, WaitTool,
+    WebFetchTool, WriteFileTool, WriteMemoryTool,
 };
 
 pub struct AppBootstrap {
     pub tools: Vec<Arc<dyn Tool>>,
+    pub code_indexer: Arc<CodeIndexer>,
+    pub memory_extractor: Arc<MemoryExtractor>,
 }
 
 pub fn build_app_bootstrap() -> Result<AppBootstrap, Box<dyn std::error::Error>> {
     let vector_store = Arc::new(VectorStore::new()?);
+    configure_injection_detection(&vector_store);
+    let code_indexer = Arc::new(
+        CodeIndexer::new(
+            vector_store.clone(),
+            crate::context::instructions::project_root(),
+        )
+        .with_hidden_paths(
+            crate::config::AppConfig::load()
+                .sandbox
+                .unwrap_or_default()
+                .resolved_hidden_paths(),
+        ),
+    );
     let workspace_memory = Arc::new(WorkspaceMemory::new("."));
+    let memory_extractor = Arc::new(MemoryExtractor::open(
+        crate::schema::StoragePaths::memory_candidates_file(),
+        Some(vector_store.clone()),
+        ".",
+        &crate::config::AppConfig::load().memory.unwrap_or_default(),
+    ));
     let tavily_key = std::env::var("TAVILY_API_KEY").unwrap_or_default();
 
     let mut tools: Vec<Arc<dyn Tool>> = vec![
@@ -27,6 +51,9 @@ pub fn build_app_bootstrap() -> Result<AppBootstrap, Box<dyn std::error::Error>>
         Arc::new(WebFetchTool::new()),
         Arc::new(RagSearchTool::new(vector_store.clone())),
         Arc::new(RagInsertTool::new(vector_store.clone())),
+        Arc::new(RagDeleteTool::new(vector_store.clone())),
+        Arc::new(RagListTool::new(vector_store.clone())),
+        Arc::new(IngestTool::new(vector_store.clone())),
         Arc::new(ReadMemoryTool::new(workspace_memory.clone())),
         Arc::new(WriteMemoryTool::new(workspace_memory.clone())),
         Arc::new(SendFileTool),
@@ -56,5 +83,26 @@ pub fn build_app_bootstrap() -> Result<AppBootstrap, Box<dyn std::error::Error>>
         tools.push(Arc::new(crate::tools::SendTelegramMessageTool::new(token)));
     }
 
-    Ok(AppBootstrap { tools })
+    Ok(AppBootstrap {
+        tools,
+        code_indexer,
+        memory_extractor,
+    })
+}
+
+fn configure_injection_detection(vector_store: &VectorStore) {
+    let config = crate::config::AppConfig::load()
+        .security
+        .and_then(|security| security.injection)
+        .unwrap_or_default();
+    crate::security::set_injection_bands(config.bands());
+    if config.uses_embedding_detector() {
+        match crate::rag::EmbeddingInjectionDetector::new(vector_store.embedder()) {
+            Ok(detector) => crate::security::set_injection_detector(Arc::new(detector)),
+            Err(e) => tracing::warn!(
+                "Embedding injection detector unavailable, using heuristic: {}",
+                e
+            ),
+        }
+    }
 }
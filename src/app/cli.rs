@@ -178,7 +178,7 @@ pub async fn run_headless_command(
     let mut agent_guard = agent.lock().await;
     let _ = output.on_waiting("Processing headless command...").await;
 
-    match agent_guard.step(command).await {
+    match agent_guard.step_with_mentions(command).await {
         Ok(exit) => match exit {
             RunExit::Finished(_) => {}
             RunExit::RecoverableFailed(msg) => {
@@ -382,10 +382,84 @@ fn print_help() {
     println!("  {} - List all sessions", style("/session").white());
     println!("  {} - Manage scheduled tasks", style("/cron").yellow());
     println!("  {} - Inspect context", style("/context").blue());
+    println!(
+        "  {} - Token budget, redundancy and per-turn timeline",
+        style("/context audit [min_tokens]").blue()
+    );
+    println!(
+        "  {} - Rebuild the exact prompt of a past turn",
+        style("/context show [turn_id] [iteration]").blue()
+    );
+    println!(
+        "  {} - Diff the prompts of two turns",
+        style("/context diff <turn_a> <turn_b>").blue()
+    );
+    println!(
+        "  {} - Show, set, dry-run or reset the budget policy",
+        style("/context policy [set|dry-run|reset] [key=value...]").blue()
+    );
     println!(
         "  {} - Trace subagent execution",
         style("/trace <job_id>").magenta()
     );
+    println!(
+        "  {} - Show sandbox policy and recent violations",
+        style("/sandbox").red()
+    );
+    println!(
+        "  {} - Review content withheld as prompt injection",
+        style("/quarantine").red()
+    );
+    println!(
+        "  {} - Branch the conversation into a new session",
+        style("/fork [name]").cyan()
+    );
+    println!(
+        "  {} - List forks or switch between them",
+        style("/branches [checkout <name>]").cyan()
+    );
+    println!(
+        "  {} - Pin a fork's final reply into its parent",
+        style("/merge-notes [name]").cyan()
+    );
+    println!(
+        "  {} - Bundle this session into a portable archive",
+        style("/export [path]").cyan()
+    );
+    println!(
+        "  {} - Restore an exported session as a branch",
+        style("/import <archive> [name]").cyan()
+    );
+    println!(
+        "  {} - Keep a file, directory or note in every prompt",
+        style("/pin [path|note]").cyan()
+    );
+    println!(
+        "  {} - Drop pinned context",
+        style("/unpin <id|path|all>").cyan()
+    );
+    println!(
+        "  {} - Show, refresh or re-embed the knowledge index",
+        style("/index [status|refresh|reindex]").blue()
+    );
+    println!(
+        "  {} - Read PDF, HTML, Markdown or text documents into the knowledge base",
+        style("/ingest <path|glob>").blue()
+    );
+    println!(
+        "  {} - Review extracted memories or show knowledge base usage",
+        style("/memory [review|approve|reject|stats]").blue()
+    );
+    println!(
+        "  {} - Show the secret behind a redaction placeholder",
+        style("/reveal <placeholder>").red()
+    );
+    println!(
+        "  {} - Mention {} or {} in a message to attach it",
+        style("@").cyan(),
+        style("@path/to/file").cyan(),
+        style("@dir/").cyan()
+    );
 
     let mut registry = crate::skills::registry::SkillRegistry::new();
     registry.discover(std::path::Path::new("skills"));
@@ -437,7 +511,7 @@ async fn run_cli_agent_step(
     let mut agent_guard = agent.lock().await;
     let _ = output.on_waiting("Processing...").await;
 
-    match agent_guard.step(line).await {
+    match agent_guard.step_with_mentions(line).await {
         Ok(exit) => match exit {
             RunExit::YieldedToUser => {
                 println!();
@@ -358,6 +358,7 @@ async fn run_invocation(
                     is_autopilot: config.is_autopilot,
                     todos_path: config.todos_path.clone(),
                     execution_guard_state: config.execution_guard_state.clone(),
+                    output: Some(config.output.clone()),
                 },
             )));
 
@@ -389,6 +389,24 @@ impl CodeModeService {
         self.read_cell_summary(session_id, &cell_id, true).await
     }
 
+    /// Values cells in `session_id` have persisted via `store(...)`.
+    pub async fn stored_values(&self, session_id: &str) -> HashMap<String, Value> {
+        let sessions = self.sessions.lock().await;
+        sessions
+            .get(session_id)
+            .map(|session| session.stored_values.clone())
+            .unwrap_or_default()
+    }
+
+    pub async fn restore_stored_values(&self, session_id: &str, values: HashMap<String, Value>) {
+        let mut sessions = self.sessions.lock().await;
+        sessions
+            .entry(session_id.to_string())
+            .or_default()
+            .stored_values
+            .extend(values);
+    }
+
     pub async fn abort_active_cell(&self, session_id: &str, reason: &str) -> bool {
         let host_handle = {
             let mut sessions = self.sessions.lock().await;
@@ -914,6 +932,7 @@ mod tests {
                 execution_guard_state: Arc::new(std::sync::Mutex::new(
                     crate::core::ExecutionGuardState::default(),
                 )),
+                output: None,
             },
         );
         crate::code_mode::host::create_executor_host_builder(
@@ -9,6 +9,29 @@ pub struct AppConfig {
     pub providers: HashMap<String, ProviderConfig>,
     #[serde(default)]
     pub sandbox: Option<crate::tools::sandbox::SandboxConfig>,
+    #[serde(default)]
+    pub security: Option<crate::security::SecurityConfig>,
+    /// Context budget policy; see [`crate::context::ContextPolicy`].
+    #[serde(default)]
+    pub context: Option<crate::context::ContextPolicyOverride>,
+    /// Embedding backend for the knowledge base; see [`crate::rag::embedder`].
+    #[serde(default)]
+    pub embedding: Option<crate::rag::embedder::EmbeddingConfig>,
+    /// Long-term memory extraction; see [`crate::memory::extraction`].
+    #[serde(default)]
+    pub memory: Option<crate::memory::MemoryConfig>,
+    /// Search fusion, reranking and diversity; see [`crate::rag::ranking`].
+    #[serde(default)]
+    pub retrieval: Option<crate::rag::ranking::RetrievalConfig>,
+    /// Knowledge base ageing and garbage collection; see [`crate::rag::retention`].
+    #[serde(default)]
+    pub retention: Option<crate::rag::retention::RetentionConfig>,
+    /// Trace run and prompt blob retention; see [`crate::trace::retention`].
+    #[serde(default)]
+    pub trace: Option<crate::trace::retention::TraceConfig>,
+    /// Pinned tokenizer downloads by family; see [`crate::context::token`].
+    #[serde(default)]
+    pub tokenizers: HashMap<String, crate::context::token::TokenizerSource>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -22,6 +45,14 @@ pub struct ProviderConfig {
     pub platform: Option<String>, // "gen" (default), "vertex"
     pub context_window: Option<usize>,
     pub reasoning_effort: Option<String>,
+    /// Ask `openai_compat` servers for token usage in streamed replies
+    /// (`stream_options.include_usage`). Defaults to on for OpenAI,
+    /// DeepSeek, DashScope and OpenRouter; set it to false for servers that
+    /// reject unknown fields.
+    pub stream_usage: Option<bool>,
+    /// Per-provider context policy, layered over the top-level `[context]`.
+    #[serde(default)]
+    pub context: Option<crate::context::ContextPolicyOverride>,
 }
 
 impl AppConfig {
@@ -0,0 +1,513 @@
+//! Static analysis of the dialogue history: oversized tool outputs, repeated
+//! tool calls, files read more than once, error oscillation, and where the
+//! tokens went per turn and per file. Redundancy findings carry
+//! [`DedupeAction`]s that [`super::sanitize::apply_dedupe_actions`] applies.
+
+use std::collections::HashMap;
+
+use super::agent_context::AgentContext;
+use super::model::Turn;
+use super::token::TokenCounter;
+use crate::tools::protocol::ToolExecutionEnvelope;
+
+/// Tool results above this many tokens are flagged.
+pub const DEFAULT_LARGE_OUTPUT_TOKENS: usize = 2_000;
+/// Prefix of outputs already replaced by a dedupe action.
+pub const DEDUPED_MARKER: &str = "[deduplicated:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PartRef {
+    pub turn: usize,
+    pub message: usize,
+    pub part: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FindingKind {
+    LargeToolOutput,
+    RepeatedToolCall,
+    RepeatedFileRead,
+    ErrorOscillation,
+}
+
+impl FindingKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FindingKind::LargeToolOutput => "LARGE_OUTPUT",
+            FindingKind::RepeatedToolCall => "REPEATED_CALL",
+            FindingKind::RepeatedFileRead => "REPEATED_READ",
+            FindingKind::ErrorOscillation => "OSCILLATION",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditFinding {
+    pub kind: FindingKind,
+    pub turn: usize,
+    pub tool_name: String,
+    pub tokens: usize,
+    pub detail: String,
+}
+
+/// Replace the tool output at `target` with `replacement`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DedupeAction {
+    pub target: PartRef,
+    pub replacement: String,
+    pub tokens_saved: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TurnTokens {
+    pub turn: usize,
+    pub preview: String,
+    pub tokens: usize,
+    pub tool_calls: usize,
+    pub tool_result_tokens: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FileAttribution {
+    pub path: String,
+    pub reads: usize,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ContextAudit {
+    pub findings: Vec<AuditFinding>,
+    pub dedupe_actions: Vec<DedupeAction>,
+    pub timeline: Vec<TurnTokens>,
+    pub files: Vec<FileAttribution>,
+    /// Tool outputs already replaced by earlier dedupe passes.
+    pub already_deduped: usize,
+}
+
+struct ToolResult {
+    at: PartRef,
+    name: String,
+    call_key: Option<String>,
+    file_path: Option<String>,
+    /// Line range of a partial read; only reads of the same range repeat.
+    file_range: Option<String>,
+    ok: bool,
+    output: String,
+    tokens: usize,
+}
+
+pub fn audit_context(ctx: &AgentContext, large_output_tokens: usize) -> ContextAudit {
+    let mut turns: Vec<&Turn> = ctx.dialogue_history.iter().collect();
+    if let Some(current) = &ctx.current_turn {
+        turns.push(current);
+    }
+    audit_turns(&turns, ctx.token_counter(), large_output_tokens)
+}
+
+pub fn audit_turns(
+    turns: &[&Turn],
+    counter: &dyn TokenCounter,
+    large_output_tokens: usize,
+) -> ContextAudit {
+    let mut audit = ContextAudit::default();
+    let mut results = Vec::new();
+
+    for (turn_index, turn) in turns.iter().enumerate() {
+        let mut row = TurnTokens {
+            turn: turn_index,
+            preview: AgentContext::truncate_chars(&turn.user_message, 50),
+            tokens: super::token::turn_token_estimate(turn, counter),
+            ..TurnTokens::default()
+        };
+        let mut calls_by_id: HashMap<String, String> = HashMap::new();
+        let mut pending_calls: Vec<(String, String)> = Vec::new();
+
+        for (message_index, message) in turn.messages.iter().enumerate() {
+            for (part_index, part) in message.parts.iter().enumerate() {
+                if let Some(call) = &part.function_call {
+                    row.tool_calls += 1;
+                    let key = format!("{}({})", call.name, call.args);
+                    match &call.id {
+                        Some(id) => {
+                            calls_by_id.insert(id.clone(), key);
+                        }
+                        None => pending_calls.push((call.name.clone(), key)),
+                    }
+                }
+                let Some(response) = &part.function_response else {
+                    continue;
+                };
+                let Some(raw) = response.response.get("result").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let envelope = ToolExecutionEnvelope::from_json_str(raw);
+                let (ok, output, file_path, file_range) = match &envelope {
+                    Some(envelope) => (
+                        envelope.result.ok,
+                        envelope.result.output.clone(),
+                        (envelope.effects.evidence_kind.as_deref() == Some("file"))
+                            .then(|| envelope.effects.evidence_source_path.clone())
+                            .flatten(),
+                        envelope.effects.evidence_range.clone(),
+                    ),
+                    None => (true, raw.to_string(), None, None),
+                };
+                let call_key = response
+                    .id
+                    .as_ref()
+                    .and_then(|id| calls_by_id.get(id).cloned())
+                    .or_else(|| {
+                        let index = pending_calls
+                            .iter()
+                            .position(|(name, _)| *name == response.name)?;
+                        Some(pending_calls.remove(index).1)
+                    });
+                let tokens = counter.count(raw);
+                row.tool_result_tokens += tokens;
+                results.push(ToolResult {
+                    at: PartRef {
+                        turn: turn_index,
+                        message: message_index,
+                        part: part_index,
+                    },
+                    name: response.name.clone(),
+                    call_key,
+                    file_path,
+                    file_range,
+                    ok,
+                    output,
+                    tokens,
+                });
+            }
+        }
+        audit.timeline.push(row);
+    }
+
+    let mut files: HashMap<String, FileAttribution> = HashMap::new();
+    for result in &results {
+        if result.output.starts_with(DEDUPED_MARKER) {
+            audit.already_deduped += 1;
+            continue;
+        }
+        if result.tokens > large_output_tokens {
+            audit.findings.push(AuditFinding {
+                kind: FindingKind::LargeToolOutput,
+                turn: result.at.turn,
+                tool_name: result.name.clone(),
+                tokens: result.tokens,
+                detail: format!(
+                    "{} tokens (threshold {}); consider narrowing the call",
+                    result.tokens, large_output_tokens
+                ),
+            });
+        }
+        if let Some(path) = &result.file_path {
+            let entry = files
+                .entry(path.clone())
+                .or_insert_with(|| FileAttribution {
+                    path: path.clone(),
+                    ..FileAttribution::default()
+                });
+            entry.reads += 1;
+            entry.tokens += result.tokens;
+        }
+    }
+    audit.files = files.into_values().collect();
+    audit
+        .files
+        .sort_by(|a, b| b.tokens.cmp(&a.tokens).then(a.path.cmp(&b.path)));
+
+    find_repeats(&results, &mut audit);
+    find_oscillation(&results, &mut audit);
+    audit
+}
+
+/// Earlier copies of a repeated call (same call, same output) or of a file
+/// (or line range) that was read again later are redundant: the latest one
+/// stays.
+fn find_repeats(results: &[ToolResult], audit: &mut ContextAudit) {
+    let mut latest_call: HashMap<(String, &str), usize> = HashMap::new();
+    let mut latest_read: HashMap<(&str, Option<&str>), usize> = HashMap::new();
+    for (index, result) in results.iter().enumerate() {
+        if result.output.starts_with(DEDUPED_MARKER) {
+            continue;
+        }
+        if let Some(path) = &result.file_path {
+            latest_read.insert((path.as_str(), result.file_range.as_deref()), index);
+        } else if let Some(key) = &result.call_key {
+            latest_call.insert((key.clone(), result.output.as_str()), index);
+        }
+    }
+
+    for (index, result) in results.iter().enumerate() {
+        if result.output.starts_with(DEDUPED_MARKER) {
+            continue;
+        }
+        let (kind, latest, detail, replacement) = if let Some(path) = &result.file_path {
+            let latest = latest_read[&(path.as_str(), result.file_range.as_deref())];
+            (
+                FindingKind::RepeatedFileRead,
+                latest,
+                format!(
+                    "{} read again in turn {}",
+                    path,
+                    results[latest].at.turn + 1
+                ),
+                format!(
+                    "{} {} was re-read in turn {}; see the later result]",
+                    DEDUPED_MARKER,
+                    path,
+                    results[latest].at.turn + 1
+                ),
+            )
+        } else if let Some(key) = &result.call_key {
+            let latest = latest_call[&(key.clone(), result.output.as_str())];
+            (
+                FindingKind::RepeatedToolCall,
+                latest,
+                format!(
+                    "identical call and output repeated in turn {}",
+                    results[latest].at.turn + 1
+                ),
+                format!(
+                    "{} identical to the {} result in turn {}]",
+                    DEDUPED_MARKER,
+                    result.name,
+                    results[latest].at.turn + 1
+                ),
+            )
+        } else {
+            continue;
+        };
+        if latest == index {
+            continue;
+        }
+        audit.findings.push(AuditFinding {
+            kind,
+            turn: result.at.turn,
+            tool_name: result.name.clone(),
+            tokens: result.tokens,
+            detail,
+        });
+        audit.dedupe_actions.push(DedupeAction {
+            target: result.at,
+            tokens_saved: result.tokens,
+            replacement,
+        });
+    }
+}
+
+/// The same call failing with the same error again and again means the agent
+/// is retrying without changing anything.
+fn find_oscillation(results: &[ToolResult], audit: &mut ContextAudit) {
+    let mut streak = 1;
+    for pair in results.windows(2) {
+        let (prev, next) = (&pair[0], &pair[1]);
+        let same_failure = !prev.ok
+            && !next.ok
+            && prev.name == next.name
+            && prev.output.trim() == next.output.trim();
+        if same_failure {
+            streak += 1;
+            if streak == 2 {
+                audit.findings.push(AuditFinding {
+                    kind: FindingKind::ErrorOscillation,
+                    turn: next.at.turn,
+                    tool_name: next.name.clone(),
+                    tokens: next.tokens,
+                    detail: format!(
+                        "failed twice in a row with the same error: {}",
+                        AgentContext::truncate_chars(next.output.trim(), 80)
+                    ),
+                });
+            }
+        } else {
+            streak = 1;
+        }
+    }
+}
+
+pub fn format_context_audit(audit: &ContextAudit) -> String {
+    let mut out = String::new();
+    out.push_str("\n\x1b[1;36m=== Redundancy Audit ===\x1b[0m\n");
+
+    if audit.findings.is_empty() {
+        out.push_str("  No redundancy or oscillation found.\n");
+    }
+    for finding in &audit.findings {
+        out.push_str(&format!(
+            "  \x1b[33m[{}]\x1b[0m turn {} {} ({} tokens): {}\n",
+            finding.kind.label(),
+            finding.turn + 1,
+            finding.tool_name,
+            finding.tokens,
+            finding.detail
+        ));
+    }
+    let pending: usize = audit.dedupe_actions.iter().map(|a| a.tokens_saved).sum();
+    if !audit.dedupe_actions.is_empty() {
+        out.push_str(&format!(
+            "  {} redundant output(s), ~{} tokens, will be deduplicated when the turn ends.\n",
+            audit.dedupe_actions.len(),
+            pending
+        ));
+    }
+    if audit.already_deduped > 0 {
+        out.push_str(&format!(
+            "  {} output(s) already deduplicated.\n",
+            audit.already_deduped
+        ));
+    }
+
+    if !audit.files.is_empty() {
+        out.push_str("\n\x1b[1;33m[Tokens by File]\x1b[0m\n");
+        for file in audit.files.iter().take(10) {
+            out.push_str(&format!(
+                "  - {:<40} {:>6} tokens ({} read{})\n",
+                file.path,
+                file.tokens,
+                file.reads,
+                if file.reads == 1 { "" } else { "s" }
+            ));
+        }
+    }
+
+    if !audit.timeline.is_empty() {
+        out.push_str("\n\x1b[1;33m[Token Timeline]\x1b[0m\n");
+        let peak = audit
+            .timeline
+            .iter()
+            .map(|row| row.tokens)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for row in &audit.timeline {
+            let bar = "█".repeat((row.tokens * 20).div_ceil(peak));
+            out.push_str(&format!(
+                "  {:>3}. {:<20} {:>6} tokens ({} tools, {} in results) {}\n",
+                row.turn + 1,
+                bar,
+                row.tokens,
+                row.tool_calls,
+                row.tool_result_tokens,
+                row.preview
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::model::{FunctionCall, FunctionResponse, Message, Part};
+
+    fn part(call: Option<FunctionCall>, response: Option<FunctionResponse>) -> Part {
+        Part {
+            text: None,
+            function_call: call,
+            function_response: response,
+            thought_signature: None,
+            file_data: None,
+        }
+    }
+
+    fn tool_turn(
+        id: &str,
+        name: &str,
+        args: serde_json::Value,
+        envelope: serde_json::Value,
+    ) -> Turn {
+        Turn {
+            turn_id: id.to_string(),
+            user_message: format!("ask {}", id),
+            messages: vec![
+                Message {
+                    role: "model".to_string(),
+                    parts: vec![part(
+                        Some(FunctionCall {
+                            name: name.to_string(),
+                            args,
+                            id: Some(format!("call-{}", id)),
+                        }),
+                        None,
+                    )],
+                },
+                Message {
+                    role: "function".to_string(),
+                    parts: vec![part(
+                        None,
+                        Some(FunctionResponse {
+                            name: name.to_string(),
+                            response: serde_json::json!({ "result": envelope.to_string() }),
+                            id: Some(format!("call-{}", id)),
+                        }),
+                    )],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_audit_flags_repeats_and_oscillation() {
+        let read = |id: &str, body: &str| {
+            tool_turn(
+                id,
+                "read_file",
+                serde_json::json!({ "path": "src/lib.rs" }),
+                serde_json::json!({
+                    "ok": true, "tool_name": "read_file", "output": body,
+                    "evidence_kind": "file", "evidence_source_path": "src/lib.rs"
+                }),
+            )
+        };
+        let build = |id: &str| {
+            tool_turn(
+                id,
+                "execute_bash",
+                serde_json::json!({ "command": "cargo build" }),
+                serde_json::json!({ "ok": false, "tool_name": "execute_bash", "output": "error[E0425]" }),
+            )
+        };
+        let turns = [read("t1", "v1"), build("t2"), build("t3"), read("t4", "v2")];
+        let refs: Vec<&Turn> = turns.iter().collect();
+        let counter = crate::context::token::default_counter();
+
+        let audit = audit_turns(&refs, counter.as_ref(), 10_000);
+
+        let kinds: Vec<_> = audit.findings.iter().map(|f| f.kind.clone()).collect();
+        assert!(kinds.contains(&FindingKind::RepeatedFileRead));
+        assert!(kinds.contains(&FindingKind::RepeatedToolCall));
+        assert!(kinds.contains(&FindingKind::ErrorOscillation));
+        let targets: Vec<usize> = audit.dedupe_actions.iter().map(|a| a.target.turn).collect();
+        assert_eq!(targets, vec![0, 1]);
+        assert_eq!(audit.files[0].reads, 2);
+        assert_eq!(audit.timeline.len(), 4);
+    }
+
+    #[test]
+    fn test_reads_of_different_ranges_are_not_repeats() {
+        let read = |id: &str, range: &str| {
+            let (start, end) = range.split_once('-').unwrap();
+            tool_turn(
+                id,
+                "read_file",
+                serde_json::json!({ "path": "src/lib.rs", "start_line": start, "end_line": end }),
+                serde_json::json!({
+                    "ok": true, "tool_name": "read_file", "output": format!("lines {}", range),
+                    "evidence_kind": "file", "evidence_source_path": "src/lib.rs",
+                    "evidence_range": range
+                }),
+            )
+        };
+        let turns = [read("t1", "1-40"), read("t2", "41-80"), read("t3", "1-40")];
+        let refs: Vec<&Turn> = turns.iter().collect();
+        let counter = crate::context::token::default_counter();
+
+        let audit = audit_turns(&refs, counter.as_ref(), 10_000);
+
+        let targets: Vec<usize> = audit.dedupe_actions.iter().map(|a| a.target.turn).collect();
+        assert_eq!(targets, vec![0]);
+        assert_eq!(audit.files[0].reads, 3);
+    }
+}
@@ -1,5 +1,6 @@
 use super::agent_context::AgentContext;
 pub use super::model::Turn;
+use super::policy::{ContextPolicy, ToolOutputStrategy};
 use super::prompt::DetailedContextStats;
 use serde::{Deserialize, Serialize};
 
@@ -26,28 +27,32 @@ pub struct ContextDiff {
 }
 
 pub(crate) fn dialogue_history_token_estimate(ctx: &AgentContext) -> usize {
-    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let counter = ctx.token_counter();
     ctx.dialogue_history
         .iter()
-        .map(|turn| AgentContext::turn_token_estimate(turn, &bpe))
+        .map(|turn| AgentContext::turn_token_estimate(turn, counter))
         .sum()
 }
 
 pub(crate) fn get_context_status(ctx: &AgentContext) -> (usize, usize, usize, usize, usize) {
-    let bpe = AgentContext::get_bpe();
+    let counter = ctx.token_counter();
 
     let current_turn_tokens = if let Some(turn) = &ctx.current_turn {
-        AgentContext::turn_token_estimate(turn, &bpe)
+        AgentContext::turn_token_estimate(turn, counter)
     } else if let Some(last) = ctx.dialogue_history.last() {
-        AgentContext::turn_token_estimate(last, &bpe)
+        AgentContext::turn_token_estimate(last, counter)
     } else {
         0
     };
 
     let prompt_text = ctx.build_system_prompt();
-    let system_tokens = bpe.encode_with_special_tokens(&prompt_text).len();
-    let history_budget =
-        effective_history_budget(ctx.max_history_tokens, system_tokens, current_turn_tokens);
+    let system_tokens = counter.count(&prompt_text);
+    let history_budget = effective_history_budget(
+        &ctx.policy,
+        ctx.max_history_tokens,
+        system_tokens,
+        current_turn_tokens,
+    );
     let (_, history_tokens, _, _) = build_history_with_token_budget(ctx, history_budget);
     let total_tokens = history_tokens + current_turn_tokens + system_tokens;
 
@@ -60,26 +65,19 @@ pub(crate) fn get_context_status(ctx: &AgentContext) -> (usize, usize, usize, us
     )
 }
 
-pub(crate) fn response_token_reserve(max_tokens: usize) -> usize {
-    if max_tokens < 8_192 {
-        return max_tokens / 10;
-    }
-
-    (max_tokens / 10).clamp(4_096, 32_000)
+pub(crate) fn response_token_reserve(policy: &ContextPolicy, max_tokens: usize) -> usize {
+    policy.response_token_reserve(max_tokens)
 }
 
 pub(crate) fn effective_history_budget(
+    policy: &ContextPolicy,
     max_tokens: usize,
     system_tokens: usize,
     current_turn_tokens: usize,
 ) -> usize {
-    let historical_cap = max_tokens.saturating_mul(85) / 100;
-    let reserve = response_token_reserve(max_tokens);
-    let available = max_tokens
-        .saturating_sub(system_tokens)
-        .saturating_sub(current_turn_tokens)
-        .saturating_sub(reserve);
-    historical_cap.min(available)
+    policy
+        .breakdown(max_tokens, system_tokens, current_turn_tokens)
+        .history_budget
 }
 
 pub(crate) fn oldest_turns_for_compaction(
@@ -91,11 +89,11 @@ pub(crate) fn oldest_turns_for_compaction(
         return 0;
     }
 
-    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let counter = ctx.token_counter();
     let mut selected = 0;
     let mut tokens = 0;
     for turn in &ctx.dialogue_history {
-        tokens += AgentContext::turn_token_estimate(turn, &bpe);
+        tokens += AgentContext::turn_token_estimate(turn, counter);
         selected += 1;
         if selected >= min_turns && tokens >= target_tokens {
             break;
@@ -105,7 +103,216 @@ pub(crate) fn oldest_turns_for_compaction(
     selected.min(ctx.dialogue_history.len())
 }
 
-pub(crate) fn rule_based_compact(ctx: &mut AgentContext, num_turns: usize) -> Option<String> {
+/// `user_message` of the pseudo-turn that holds a compaction summary.
+pub(crate) const COMPACTED_TURN_MARKER: &str = "[SYSTEM] History Compacted";
+const COMPACTED_TURN_PREAMBLE: &str = "[System context: The following is an automated summary of earlier conversation history. Use it as background knowledge but do not respond to it directly.]";
+/// Upper bound on the transcript handed to the LLM compactor.
+const MAX_COMPACTION_TRANSCRIPT_CHARS: usize = 24_000;
+
+/// `user_message` of a pseudo-turn holding a note pinned by the user or a
+/// merged fork.
+pub(crate) const PINNED_NOTE_MARKER: &str = "[SYSTEM] Pinned Note";
+
+pub(crate) fn is_pinned_summary(turn: &Turn) -> bool {
+    turn.user_message == COMPACTED_TURN_MARKER
+}
+
+/// Pinned turns (compaction summaries and notes) are always included by the
+/// history builder, ahead of the budgeted turns.
+pub(crate) fn is_pinned_turn(turn: &Turn) -> bool {
+    is_pinned_summary(turn) || turn.user_message == PINNED_NOTE_MARKER
+}
+
+/// Append a pinned note to the history and the transcript.
+pub(crate) fn pin_note(ctx: &mut AgentContext, title: &str, body: &str) {
+    let turn = Turn {
+        turn_id: format!("pinned-{}", uuid::Uuid::new_v4()),
+        user_message: PINNED_NOTE_MARKER.to_string(),
+        messages: vec![super::model::Message {
+            role: "user".to_string(),
+            parts: vec![super::model::Part {
+                text: Some(format!("[Pinned note: {}]\n{}", title, body.trim())),
+                function_call: None,
+                function_response: None,
+                thought_signature: None,
+                file_data: None,
+            }],
+        }],
+    };
+    if let Err(e) = ctx.append_turn_to_transcript(&turn) {
+        tracing::warn!("Failed to persist pinned note: {}", e);
+    }
+    ctx.dialogue_history.push(turn);
+}
+
+/// Structured summary produced by the LLM compactor.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SemanticSummary {
+    #[serde(default)]
+    pub goals: Vec<String>,
+    #[serde(default)]
+    pub decisions: Vec<String>,
+    #[serde(default)]
+    pub files_touched: Vec<String>,
+    #[serde(default)]
+    pub open_questions: Vec<String>,
+    #[serde(default)]
+    pub user_preferences: Vec<String>,
+}
+
+impl SemanticSummary {
+    /// Parse the compactor's reply. Tolerates a ```json fence or prose around
+    /// the object; returns `None` when nothing usable came back.
+    pub fn parse(reply: &str) -> Option<Self> {
+        let start = reply.find('{')?;
+        let end = reply.rfind('}')?;
+        if end < start {
+            return None;
+        }
+        let summary: Self = serde_json::from_str(&reply[start..=end]).ok()?;
+        (!summary.is_empty()).then_some(summary)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.goals.is_empty()
+            && self.decisions.is_empty()
+            && self.files_touched.is_empty()
+            && self.open_questions.is_empty()
+            && self.user_preferences.is_empty()
+    }
+
+    pub fn render(&self, compacted_turns: usize) -> String {
+        let mut out = format!("=== Semantic Summary ({} turns) ===", compacted_turns);
+        for (title, items) in [
+            ("Goals", &self.goals),
+            ("Decisions & constraints", &self.decisions),
+            ("Files touched", &self.files_touched),
+            ("Open questions", &self.open_questions),
+            ("User preferences", &self.user_preferences),
+        ] {
+            if items.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("\n\n## {}", title));
+            for item in items {
+                out.push_str(&format!("\n- {}", item.trim()));
+            }
+        }
+        out
+    }
+}
+
+pub(crate) fn semantic_compaction_prompt(transcript: &str) -> String {
+    format!(
+        "You are compacting the early part of a long agent session so it fits in the context \
+         window. Read the transcript below and extract what the agent must still know later.\n\n\
+         Reply with ONLY a JSON object of this shape:\n\
+         {{\"goals\": [], \"decisions\": [], \"files_touched\": [], \"open_questions\": [], \
+         \"user_preferences\": []}}\n\n\
+         - goals: what the user is trying to achieve\n\
+         - decisions: choices made and constraints agreed on, with the reason when stated\n\
+         - files_touched: paths read or modified, with a few words on what changed\n\
+         - open_questions: unresolved problems, pending follow-ups, failed attempts\n\
+         - user_preferences: style, tooling, or workflow preferences the user expressed\n\n\
+         Carry forward everything from any earlier summary in the transcript. Be terse; \
+         one fact per entry.\n\n---\n{transcript}\n---"
+    )
+}
+
+/// Render the oldest `num_turns` turns as plain text for the LLM compactor.
+/// Earlier summaries are included verbatim so their facts carry forward.
+pub(crate) fn compaction_transcript(ctx: &AgentContext, num_turns: usize) -> String {
+    let mut out = String::new();
+    let to_compact = num_turns.min(ctx.dialogue_history.len());
+    for (i, turn) in ctx.dialogue_history.iter().take(to_compact).enumerate() {
+        let mut block = String::new();
+        if is_pinned_turn(turn) {
+            block.push_str(if is_pinned_summary(turn) {
+                "[Earlier summary]\n"
+            } else {
+                "[Pinned note]\n"
+            });
+            for text in turn
+                .messages
+                .iter()
+                .flat_map(|m| &m.parts)
+                .filter_map(|p| p.text.as_deref())
+            {
+                block.push_str(text.trim_start_matches(COMPACTED_TURN_PREAMBLE).trim());
+                block.push('\n');
+            }
+        } else {
+            block.push_str(&format!(
+                "[Turn {}] User: {}\n",
+                i + 1,
+                AgentContext::truncate_chars(&turn.user_message, 500)
+            ));
+            for msg in &turn.messages {
+                for part in &msg.parts {
+                    if msg.role == "model" {
+                        if let Some(text) = &part.text {
+                            let cleaned = AgentContext::strip_thinking_tags(text);
+                            if !cleaned.is_empty() {
+                                block.push_str(&format!(
+                                    "  Agent: {}\n",
+                                    AgentContext::truncate_chars(&cleaned, 500)
+                                ));
+                            }
+                        }
+                    }
+                    if let Some(fc) = &part.function_call {
+                        block.push_str(&format!(
+                            "  -> {}({})\n",
+                            fc.name,
+                            summarize_tool_args(&fc.name, &fc.args)
+                        ));
+                    }
+                    if let Some(fr) = &part.function_response {
+                        if detect_tool_error(&fr.response) {
+                            block.push_str(&format!(
+                                "  x {} FAILED: {}\n",
+                                fr.name,
+                                AgentContext::truncate_chars(&fr.response.to_string(), 200)
+                            ));
+                        } else {
+                            block.push_str(&format!("  ok {}\n", fr.name));
+                        }
+                    }
+                }
+            }
+        }
+        if out.len() + block.len() > MAX_COMPACTION_TRANSCRIPT_CHARS {
+            out.push_str(&format!(
+                "[Turns {}-{}] (omitted due to transcript size limit)\n",
+                i + 1,
+                to_compact
+            ));
+            break;
+        }
+        out.push_str(&block);
+        out.push('\n');
+    }
+    out
+}
+
+/// Replace the oldest `num_turns` turns with a pinned pseudo-turn holding
+/// the LLM-produced `summary`.
+pub(crate) fn semantic_compact(
+    ctx: &mut AgentContext,
+    num_turns: usize,
+    summary: &SemanticSummary,
+) -> Option<String> {
+    let compacted_turns = drain_for_compaction(ctx, num_turns)?;
+    let to_compact = compacted_turns.len();
+    pin_summary(ctx, summary.render(to_compact));
+
+    let reason = format!("Compacted {} turns into semantic summary", to_compact);
+    tracing::info!("{}", reason);
+    Some(reason)
+}
+
+/// Archive the oldest `num_turns` turns to the transcript and remove them.
+fn drain_for_compaction(ctx: &mut AgentContext, num_turns: usize) -> Option<Vec<Turn>> {
     if num_turns == 0 || ctx.dialogue_history.is_empty() {
         return None;
     }
@@ -117,7 +324,31 @@ pub(crate) fn rule_based_compact(ctx: &mut AgentContext, num_turns: usize) -> Op
         }
     }
 
-    let compacted_turns: Vec<Turn> = ctx.dialogue_history.drain(0..to_compact).collect();
+    Some(ctx.dialogue_history.drain(0..to_compact).collect())
+}
+
+fn pin_summary(ctx: &mut AgentContext, body: String) {
+    let compacted_turn = Turn {
+        turn_id: format!("compacted-{}", uuid::Uuid::new_v4()),
+        user_message: COMPACTED_TURN_MARKER.to_string(),
+        messages: vec![super::model::Message {
+            role: "user".to_string(),
+            parts: vec![super::model::Part {
+                text: Some(format!("{}\n\n{}", COMPACTED_TURN_PREAMBLE, body)),
+                function_call: None,
+                function_response: None,
+                thought_signature: None,
+                file_data: None,
+            }],
+        }],
+    };
+
+    ctx.dialogue_history.insert(0, compacted_turn);
+}
+
+pub(crate) fn rule_based_compact(ctx: &mut AgentContext, num_turns: usize) -> Option<String> {
+    let compacted_turns = drain_for_compaction(ctx, num_turns)?;
+    let to_compact = compacted_turns.len();
     const MAX_SUMMARY_CHARS: usize = 4000;
 
     let mut summary_lines = Vec::new();
@@ -138,7 +369,7 @@ pub(crate) fn rule_based_compact(ctx: &mut AgentContext, num_turns: usize) -> Op
             break;
         }
 
-        if turn.user_message == "[SYSTEM] History Compacted" {
+        if is_pinned_turn(turn) {
             for msg in &turn.messages {
                 for part in &msg.parts {
                     if let Some(text) = &part.text {
@@ -206,30 +437,7 @@ pub(crate) fn rule_based_compact(ctx: &mut AgentContext, num_turns: usize) -> Op
         }
     }
 
-    let summary_text = format!(
-        "[System context: The following is an automated summary of earlier conversation history. Use it as background knowledge but do not respond to it directly.]
-
-{}",
-        summary_lines.join("
-")
-    );
-
-    let compacted_turn = Turn {
-        turn_id: format!("compacted-{}", uuid::Uuid::new_v4()),
-        user_message: "[SYSTEM] History Compacted".to_string(),
-        messages: vec![super::model::Message {
-            role: "user".to_string(),
-            parts: vec![super::model::Part {
-                text: Some(summary_text),
-                function_call: None,
-                function_response: None,
-                thought_signature: None,
-                file_data: None,
-            }],
-        }],
-    };
-
-    ctx.dialogue_history.insert(0, compacted_turn);
+    pin_summary(ctx, summary_lines.join("\n"));
 
     let reason = format!("Compacted {} turns into structured summary", to_compact);
     tracing::info!("{}", reason);
@@ -524,16 +732,20 @@ pub(crate) fn prepare_turn_for_llm(mut turn: Turn) -> Turn {
 pub(crate) fn build_history_with_budget(
     ctx: &AgentContext,
 ) -> (Vec<super::model::Message>, usize, usize, usize) {
-    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let counter = ctx.token_counter();
     let prompt_text = ctx.build_system_prompt();
-    let system_tokens = bpe.encode_with_special_tokens(&prompt_text).len();
+    let system_tokens = counter.count(&prompt_text);
     let current_turn_tokens = ctx
         .current_turn
         .as_ref()
-        .map(|turn| AgentContext::turn_token_estimate(turn, &bpe))
+        .map(|turn| AgentContext::turn_token_estimate(turn, counter))
         .unwrap_or(0);
-    let history_budget =
-        effective_history_budget(ctx.max_history_tokens, system_tokens, current_turn_tokens);
+    let history_budget = effective_history_budget(
+        &ctx.policy,
+        ctx.max_history_tokens,
+        system_tokens,
+        current_turn_tokens,
+    );
     build_history_with_token_budget(ctx, history_budget)
 }
 
@@ -541,26 +753,45 @@ pub(crate) fn build_history_with_token_budget(
     ctx: &AgentContext,
     history_budget: usize,
 ) -> (Vec<super::model::Message>, usize, usize, usize) {
-    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let counter = ctx.token_counter();
     let mut history_blocks: Vec<(usize, Vec<super::model::Message>)> = Vec::new();
     let mut current_tokens = 0;
     let mut turns_included = 0;
     let mut total_truncated_chars = 0;
     let mut protect_next_turn = false;
 
+    // Compaction summaries and notes are pinned ahead of the budgeted turns.
+    let pinned: Vec<super::model::Message> = ctx
+        .dialogue_history
+        .iter()
+        .filter(|turn| is_pinned_turn(turn))
+        .flat_map(|turn| turn.messages.iter().cloned())
+        .collect();
+    for msg in &pinned {
+        current_tokens += AgentContext::estimate_tokens(counter, msg);
+    }
+    turns_included += ctx
+        .dialogue_history
+        .iter()
+        .filter(|turn| is_pinned_turn(turn))
+        .count();
+
     for (i, turn) in ctx.dialogue_history.iter().rev().enumerate() {
+        if is_pinned_turn(turn) {
+            continue;
+        }
         let sanitized = match sanitize_turn(turn) {
             Some(v) => v,
             None => continue,
         };
 
         let user_asks_for_context = i < 10 && is_user_referencing_history(&turn.user_message);
-        let should_strip = i >= 3 && !protect_next_turn;
+        let should_strip = i >= ctx.policy.protected_recent_turns && !protect_next_turn;
 
-        let (turn, truncated) = if should_strip {
-            reconstruct_turn_for_history(&sanitized)
-        } else {
-            truncate_old_tool_results(&sanitized)
+        let (turn, truncated) = match ctx.policy.tool_output_strategy {
+            ToolOutputStrategy::Strip if should_strip => reconstruct_turn_for_history(&sanitized),
+            ToolOutputStrategy::Keep => (sanitized, 0),
+            _ => truncate_old_tool_results(&sanitized),
         };
         let turn = prepare_turn_for_llm(turn);
         total_truncated_chars += truncated;
@@ -569,7 +800,7 @@ pub(crate) fn build_history_with_token_budget(
         let turn_tokens: usize = turn
             .messages
             .iter()
-            .map(|m| AgentContext::estimate_tokens(&bpe, m))
+            .map(|m| AgentContext::estimate_tokens(counter, m))
             .sum();
 
         if current_tokens + turn_tokens > history_budget {
@@ -581,7 +812,7 @@ pub(crate) fn build_history_with_token_budget(
     }
 
     history_blocks.reverse();
-    let mut flattened = Vec::new();
+    let mut flattened = pinned;
     let mut prev_zone: Option<u8> = None;
 
     for (distance, block) in &history_blocks {
@@ -947,14 +1178,15 @@ mod tests {
 
     #[test]
     fn effective_history_budget_reserves_space_for_256k_window() {
-        let budget = effective_history_budget(256_000, 10_000, 20_000);
-        assert_eq!(response_token_reserve(256_000), 25_600);
+        let policy = ContextPolicy::default();
+        let budget = effective_history_budget(&policy, 256_000, 10_000, 20_000);
+        assert_eq!(response_token_reserve(&policy, 256_000), 25_600);
         assert_eq!(budget, 200_400);
     }
 
     #[test]
     fn effective_history_budget_caps_history_at_85_percent() {
-        let budget = effective_history_budget(256_000, 1_000, 1_000);
+        let budget = effective_history_budget(&ContextPolicy::default(), 256_000, 1_000, 1_000);
         assert_eq!(budget, 217_600);
     }
 
@@ -1112,4 +1344,45 @@ mod tests {
             .output
             .contains("Code mode history compressed"));
     }
+
+    #[test]
+    fn semantic_summary_parses_fenced_reply() {
+        let reply = "Here you go:\n```json\n{\"goals\": [\"ship v2\"], \"files_touched\": [\"src/lib.rs: added parser\"]}\n```";
+        let summary = SemanticSummary::parse(reply).expect("summary");
+        assert_eq!(summary.goals, vec!["ship v2"]);
+        assert!(summary.decisions.is_empty());
+        let rendered = summary.render(4);
+        assert!(rendered.contains("## Files touched\n- src/lib.rs: added parser"));
+        assert!(!rendered.contains("## Decisions"));
+
+        assert!(SemanticSummary::parse("{\"goals\": []}").is_none());
+        assert!(SemanticSummary::parse("no json here").is_none());
+    }
+
+    #[test]
+    fn semantic_compact_pins_summary_within_tiny_budget() {
+        let mut ctx = AgentContext::new();
+        for i in 0..4 {
+            ctx.start_turn(format!("request number {i} with some extra words"));
+            ctx.end_turn();
+        }
+        let summary = SemanticSummary {
+            decisions: vec!["keep the public API stable".to_string()],
+            ..Default::default()
+        };
+        ctx.semantic_compact(3, &summary).expect("compacted");
+        assert_eq!(ctx.dialogue_history.len(), 2);
+        assert!(is_pinned_summary(&ctx.dialogue_history[0]));
+        assert!(ctx
+            .compaction_transcript(1)
+            .contains("keep the public API stable"));
+
+        let (messages, _, turns, _) = build_history_with_token_budget(&ctx, 1);
+        assert_eq!(turns, 1);
+        assert!(messages[0].parts[0]
+            .text
+            .as_deref()
+            .unwrap()
+            .contains("keep the public API stable"));
+    }
 }
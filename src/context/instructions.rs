@@ -0,0 +1,232 @@
+//! Project instruction files (`AGENTS.md`), layered from least to most
+//! specific: the user-global file, the repository root, then every
+//! directory between the root and the paths the agent works in. Nested
+//! files are picked up lazily, the first time a tool reads or writes under
+//! their directory, and are re-read on every prompt build.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::agent_context::AgentContext;
+use super::token::TokenCounter;
+
+pub const INSTRUCTIONS_FILE: &str = "AGENTS.md";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionScope {
+    Global,
+    Root,
+    Nested,
+}
+
+/// Token accounting for one loaded instruction file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionFileStats {
+    pub path: String,
+    pub scope: InstructionScope,
+    /// 1 is the lowest precedence; later files override earlier ones.
+    pub precedence: usize,
+    pub tokens: usize,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InstructionSet {
+    /// Directories below the root known to hold an instruction file, in
+    /// precedence order (shallower first).
+    nested: Vec<PathBuf>,
+    /// Directories already checked, with or without a file.
+    seen: HashSet<PathBuf>,
+}
+
+/// `~/.config/rusty-claw/AGENTS.md`, next to `config.toml`.
+pub fn global_instructions_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("rusty-claw").join(INSTRUCTIONS_FILE))
+}
+
+/// The nearest ancestor of the working directory holding `.git`, or the
+/// working directory itself.
+pub fn project_root() -> PathBuf {
+    git_root().unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+}
+
+/// The nearest ancestor of the working directory holding `.git`.
+pub fn git_root() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+impl InstructionSet {
+    /// Note that a tool touched `path`; returns the instruction files this
+    /// newly brings into scope.
+    pub fn touch(&mut self, path: &Path) -> Vec<PathBuf> {
+        self.touch_in(project_root(), path)
+    }
+
+    fn touch_in(&mut self, root: PathBuf, path: &Path) -> Vec<PathBuf> {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| root.clone());
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            cwd.join(path)
+        };
+        let absolute = std::fs::canonicalize(&absolute).unwrap_or(absolute);
+        let root = std::fs::canonicalize(&root).unwrap_or(root);
+        let dir = if absolute.is_dir() {
+            absolute.as_path()
+        } else {
+            match absolute.parent() {
+                Some(parent) => parent,
+                None => return Vec::new(),
+            }
+        };
+        let Ok(relative) = dir.strip_prefix(&root) else {
+            return Vec::new();
+        };
+
+        let mut loaded = Vec::new();
+        let mut current = root.clone();
+        for component in relative.components() {
+            current.push(component);
+            if !self.seen.insert(current.clone()) {
+                continue;
+            }
+            let file = current.join(INSTRUCTIONS_FILE);
+            if file.is_file() {
+                self.nested.push(current.clone());
+                loaded.push(file);
+            }
+        }
+        // Keep shallower directories ahead of deeper ones.
+        self.nested.sort_by_key(|dir| dir.components().count());
+        loaded
+    }
+
+    /// Instruction files in scope, lowest precedence first.
+    pub fn files(&self) -> Vec<(InstructionScope, PathBuf)> {
+        self.files_in(&project_root())
+    }
+
+    fn files_in(&self, root: &Path) -> Vec<(InstructionScope, PathBuf)> {
+        let mut files = Vec::new();
+        if let Some(global) = global_instructions_path().filter(|path| path.is_file()) {
+            files.push((InstructionScope::Global, global));
+        }
+        let root = root.join(INSTRUCTIONS_FILE);
+        if root.is_file() {
+            files.push((InstructionScope::Root, root));
+        }
+        files.extend(
+            self.nested
+                .iter()
+                .map(|dir| (InstructionScope::Nested, dir.join(INSTRUCTIONS_FILE))),
+        );
+        files
+    }
+
+    /// The instructions section body plus per-file accounting. Each file is
+    /// capped at `max_chars_per_file`.
+    pub fn render(
+        &self,
+        max_chars_per_file: usize,
+        counter: &dyn TokenCounter,
+    ) -> (String, Vec<InstructionFileStats>) {
+        self.render_in(&project_root(), max_chars_per_file, counter)
+    }
+
+    fn render_in(
+        &self,
+        root: &Path,
+        max_chars_per_file: usize,
+        counter: &dyn TokenCounter,
+    ) -> (String, Vec<InstructionFileStats>) {
+        let mut body = String::new();
+        let mut stats = Vec::new();
+        for (scope, path) in self.files_in(root) {
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let content = content.trim();
+            if content.is_empty() {
+                continue;
+            }
+            let shown = path
+                .strip_prefix(root)
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|_| path.clone());
+            let truncated = content.chars().count() > max_chars_per_file;
+            let block = format!(
+                "### {} ({})\n{}\n\n",
+                shown.display(),
+                scope_label(scope),
+                AgentContext::truncate_chars(content, max_chars_per_file)
+            );
+            stats.push(InstructionFileStats {
+                path: shown.display().to_string(),
+                scope,
+                precedence: stats.len() + 1,
+                tokens: counter.count(&block),
+                truncated,
+            });
+            body.push_str(&block);
+        }
+        if stats.len() > 1 {
+            body.insert_str(
+                0,
+                "Files are listed from least to most specific; where they conflict, the later file wins for paths under its directory.\n\n",
+            );
+        }
+        (body, stats)
+    }
+}
+
+fn scope_label(scope: InstructionScope) -> &'static str {
+    match scope {
+        InstructionScope::Global => "user-global",
+        InstructionScope::Root => "project root",
+        InstructionScope::Nested => "directory",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_touch_loads_nested_files_once_in_depth_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(dir.path()).unwrap();
+        let deep = dir.path().join("a").join("b");
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::write(dir.path().join("a").join(INSTRUCTIONS_FILE), "outer").unwrap();
+        std::fs::write(deep.join(INSTRUCTIONS_FILE), "inner").unwrap();
+        std::fs::write(deep.join("main.rs"), "").unwrap();
+
+        let mut set = InstructionSet::default();
+        let loaded = set.touch_in(root.clone(), &deep.join("main.rs"));
+        assert_eq!(loaded.len(), 2);
+        assert!(set.touch_in(root.clone(), &deep.join("main.rs")).is_empty());
+
+        let nested: Vec<PathBuf> = set
+            .files_in(&root)
+            .into_iter()
+            .filter(|(scope, _)| *scope == InstructionScope::Nested)
+            .map(|(_, path)| path)
+            .collect();
+        assert!(nested[0].ends_with(Path::new("a").join(INSTRUCTIONS_FILE)));
+        assert!(nested[1].ends_with(Path::new("b").join(INSTRUCTIONS_FILE)));
+
+        let counter = super::super::token::default_counter();
+        let (body, stats) = set.render_in(&root, 3_000, counter.as_ref());
+        assert!(body.find("outer").unwrap() < body.find("inner").unwrap());
+        let inner = stats.last().unwrap();
+        assert_eq!(inner.scope, InstructionScope::Nested);
+        assert_eq!(inner.precedence, stats.len());
+        assert!(inner.tokens > 0);
+    }
+}
@@ -1,6 +1,10 @@
 pub mod agent_context;
+pub mod audit;
 pub mod history;
+pub mod instructions;
 pub mod model;
+pub mod pins;
+pub mod policy;
 pub mod prompt;
 pub mod report;
 pub mod sanitize;
@@ -10,7 +14,9 @@ pub mod transcript;
 pub mod turns;
 
 pub use agent_context::AgentContext;
-pub use history::ContextDiff;
+pub use history::{ContextDiff, SemanticSummary};
 pub use model::{FileData, FunctionCall, FunctionResponse, Message, Part};
+pub use pins::{Attachment, PinnedItem};
+pub use policy::{ContextPolicy, ContextPolicyOverride, ToolOutputStrategy};
 pub use prompt::{DetailedContextStats, PromptReport};
 pub use transcript::transcript_path_for_session;
@@ -0,0 +1,425 @@
+//! Context the user keeps in view: `@path` mentions inlined into one message,
+//! and `/pin`ned files or notes carried into every prompt until `/unpin`.
+//! Paths are read under the session's sandbox and stored redacted.
+
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::evidence::Evidence;
+use crate::security::redact_secrets;
+use crate::tools::ToolContext;
+
+/// Cap on how much of one file or listing is inlined.
+pub const MAX_ATTACHMENT_CHARS: usize = 12_000;
+const MAX_DIR_ENTRIES: usize = 200;
+
+static MENTION_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:^|\s)@([^\s@]+)").expect("valid mention regex"));
+
+/// A file or directory inlined into a user message via `@path`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub mention: String,
+    pub kind: &'static str,
+    pub content: String,
+}
+
+impl Attachment {
+    pub fn render(&self) -> String {
+        format!(
+            "--- [ATTACHMENT @{} ({})] ---\n{}",
+            self.mention, self.kind, self.content
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PinnedItem {
+    /// A file or directory, re-read when its [`Evidence`] goes stale.
+    Path {
+        id: String,
+        evidence: Evidence,
+    },
+    Note {
+        id: String,
+        text: String,
+    },
+}
+
+impl PinnedItem {
+    pub fn id(&self) -> &str {
+        match self {
+            PinnedItem::Path { id, .. } | PinnedItem::Note { id, .. } => id,
+        }
+    }
+
+    /// Short human label: the path, or the start of the note.
+    pub fn label(&self) -> String {
+        match self {
+            PinnedItem::Path { evidence, .. } => {
+                format!("{} ({})", evidence.source_path, evidence.source_kind)
+            }
+            PinnedItem::Note { text, .. } => {
+                format!("note: {}", super::AgentContext::truncate_chars(text, 60))
+            }
+        }
+    }
+
+    pub fn render(&self) -> String {
+        match self {
+            PinnedItem::Path { evidence, .. } => format!(
+                "--- [PINNED {} {}] ---\n{}",
+                evidence.source_kind.to_uppercase(),
+                evidence.source_path,
+                evidence.content
+            ),
+            PinnedItem::Note { text, .. } => format!("--- [PINNED NOTE] ---\n{}", text),
+        }
+    }
+
+    fn matches(&self, target: &str) -> bool {
+        match self {
+            PinnedItem::Path { id, evidence } => {
+                id == target
+                    || evidence.source_path == target
+                    || evidence.source_path.trim_end_matches('/') == target.trim_end_matches('/')
+            }
+            PinnedItem::Note { id, .. } => id == target,
+        }
+    }
+
+    /// Re-read a pinned path whose file changed. Returns whether it did.
+    fn refresh(&mut self, ctx: &ToolContext) -> bool {
+        let PinnedItem::Path { id, evidence } = self else {
+            return false;
+        };
+        if evidence.is_fresh().0 {
+            return false;
+        }
+        let content = match read_guarded(Path::new(&evidence.source_path), ctx, "pin") {
+            Ok((_, content)) => content,
+            Err(err) => format!("[Pinned path is no longer readable: {}]", err),
+        };
+        *evidence = pinned_evidence(id, &evidence.source_kind, &evidence.source_path, content);
+        true
+    }
+}
+
+/// `@path` tokens in `text` that name an existing file or directory, in
+/// order of appearance. Trailing punctuation is ignored, so
+/// "see @src/main.rs." mentions `src/main.rs`.
+pub fn mentions(text: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for capture in MENTION_RE.captures_iter(text) {
+        let raw = &capture[1];
+        let trimmed = raw.trim_end_matches(|c: char| ",.;:!?)'\"`".contains(c));
+        let candidate = if Path::new(trimmed).exists() {
+            trimmed
+        } else {
+            raw
+        };
+        if !candidate.is_empty()
+            && Path::new(candidate).exists()
+            && !found.iter().any(|seen| seen == candidate)
+        {
+            found.push(candidate.to_string());
+        }
+    }
+    found
+}
+
+/// Read every `@path` in `text`. Paths the sandbox in `ctx` hides are
+/// refused and secrets are redacted from what is read. Unreadable mentions
+/// (binary files, permission errors) are skipped with a warning.
+pub fn expand_mentions(text: &str, ctx: &ToolContext) -> Vec<Attachment> {
+    mentions(text)
+        .into_iter()
+        .filter_map(
+            |mention| match read_guarded(Path::new(&mention), ctx, "mention") {
+                Ok((kind, content)) => Some(Attachment {
+                    mention,
+                    kind,
+                    content,
+                }),
+                Err(err) => {
+                    tracing::warn!("Skipping @{}: {}", mention, err);
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// [`read_for_context`] for a path the user named: refused when the sandbox
+/// in `ctx` hides it, with secrets redacted from what is read.
+fn read_guarded(
+    path: &Path,
+    ctx: &ToolContext,
+    tool: &str,
+) -> Result<(&'static str, String), String> {
+    if let Some(sandbox) = &ctx.sandbox {
+        sandbox
+            .guard_path_access(ctx, tool, path, false)
+            .map_err(|violation| violation.to_string())?;
+    }
+    let (kind, content) = read_for_context(path)?;
+    Ok((kind, redact_secrets(&content)))
+}
+
+/// File contents, or a one-level listing for directories, capped at
+/// [`MAX_ATTACHMENT_CHARS`].
+pub fn read_for_context(path: &Path) -> Result<(&'static str, String), String> {
+    let (kind, content) = if path.is_dir() {
+        let mut entries: Vec<String> = std::fs::read_dir(path)
+            .map_err(|e| e.to_string())?
+            .flatten()
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() {
+                    format!("{}/", name)
+                } else {
+                    name
+                }
+            })
+            .collect();
+        entries.sort();
+        let total = entries.len();
+        entries.truncate(MAX_DIR_ENTRIES);
+        let mut listing = entries.join("\n");
+        if total > MAX_DIR_ENTRIES {
+            listing.push_str(&format!("\n... ({} more)", total - MAX_DIR_ENTRIES));
+        }
+        ("directory", listing)
+    } else {
+        let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        let text = String::from_utf8(bytes).map_err(|_| "not a UTF-8 text file".to_string())?;
+        ("file", text)
+    };
+    Ok((
+        kind,
+        super::AgentContext::truncate_chars(&content, MAX_ATTACHMENT_CHARS),
+    ))
+}
+
+fn pinned_evidence(id: &str, kind: &str, source_path: &str, content: String) -> Evidence {
+    Evidence::new(
+        id.to_string(),
+        kind.to_string(),
+        source_path.to_string(),
+        1.0,
+        format!("Pinned {}", kind),
+        content,
+    )
+}
+
+fn next_id(items: &[PinnedItem]) -> String {
+    let next = items
+        .iter()
+        .filter_map(|item| item.id().strip_prefix("pin-")?.parse::<usize>().ok())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    format!("pin-{}", next)
+}
+
+pub(crate) fn pin_path(
+    items: &mut Vec<PinnedItem>,
+    path: &str,
+    ctx: &ToolContext,
+) -> Result<PinnedItem, String> {
+    if let Some(existing) = items.iter().find(|item| item.matches(path)) {
+        return Err(format!("Already pinned as {}.", existing.id()));
+    }
+    let (kind, content) = read_guarded(Path::new(path), ctx, "pin")
+        .map_err(|e| format!("Cannot pin {}: {}", path, e))?;
+    let id = next_id(items);
+    let item = PinnedItem::Path {
+        evidence: pinned_evidence(&id, kind, path, content),
+        id,
+    };
+    items.push(item.clone());
+    Ok(item)
+}
+
+pub(crate) fn pin_note(items: &mut Vec<PinnedItem>, text: &str) -> PinnedItem {
+    let item = PinnedItem::Note {
+        id: next_id(items),
+        text: text.to_string(),
+    };
+    items.push(item.clone());
+    item
+}
+
+/// Remove pins matching `target` (an id or pinned path), or all of them
+/// for `all`. Returns what was removed.
+pub(crate) fn unpin(items: &mut Vec<PinnedItem>, target: &str) -> Vec<PinnedItem> {
+    let (removed, kept) = items
+        .drain(..)
+        .partition(|item| target == "all" || item.matches(target));
+    *items = kept;
+    removed
+}
+
+/// Re-read stale pinned paths; returns the ids that were refreshed.
+pub(crate) fn refresh(items: &mut [PinnedItem], ctx: &ToolContext) -> Vec<String> {
+    items
+        .iter_mut()
+        .filter_map(|item| item.refresh(ctx).then(|| item.id().to_string()))
+        .collect()
+}
+
+pub(crate) fn load(path: &Path) -> Vec<PinnedItem> {
+    let mut items: Vec<PinnedItem> = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    // Pins saved before reads were redacted.
+    for item in &mut items {
+        if let PinnedItem::Path { evidence, .. } = item {
+            evidence.content = redact_secrets(&evidence.content);
+        }
+    }
+    items
+}
+
+pub(crate) fn save(path: &Path, items: &[PinnedItem]) -> std::io::Result<()> {
+    if items.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(items).map_err(std::io::Error::other)?;
+    std::fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentions_resolve_existing_paths_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.md");
+        std::fs::write(&file, "hello").unwrap();
+        let file = file.display().to_string();
+        let text = format!(
+            "look at @{}, and @{}/ but not @missing.rs or me@example.com",
+            file,
+            dir.path().display()
+        );
+
+        let found = mentions(&text);
+        assert_eq!(
+            found,
+            vec![file.clone(), format!("{}/", dir.path().display())]
+        );
+
+        let attachments = expand_mentions(&text, &ToolContext::new("pins-test", "cli"));
+        assert_eq!(attachments[0].kind, "file");
+        assert_eq!(attachments[0].content, "hello");
+        assert_eq!(attachments[1].kind, "directory");
+        assert!(attachments[1].content.contains("notes.md"));
+    }
+
+    #[test]
+    fn test_mentions_of_hidden_paths_are_refused() {
+        use crate::tools::sandbox::{SandboxEnforcer, SandboxLevel, SandboxPolicy};
+
+        let dir = tempfile::tempdir().unwrap();
+        let hidden = dir.path().join("secrets");
+        std::fs::create_dir(&hidden).unwrap();
+        std::fs::write(hidden.join("id_rsa"), "PRIVATE").unwrap();
+        let visible = dir.path().join("notes.md");
+        std::fs::write(&visible, "API_TOKEN=abcdefgh12345").unwrap();
+
+        let mut ctx = ToolContext::new("pins-test", "cli");
+        ctx.sandbox = Some(std::sync::Arc::new(SandboxEnforcer::disabled_with_policy(
+            SandboxPolicy {
+                level: SandboxLevel::Restricted,
+                hidden_paths: vec![hidden.clone()],
+                ..Default::default()
+            },
+        )));
+        let text = format!(
+            "compare @{} with @{}",
+            hidden.join("id_rsa").display(),
+            visible.display()
+        );
+
+        let attachments = expand_mentions(&text, &ctx);
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].mention, visible.display().to_string());
+        assert!(!attachments[0].content.contains("abcdefgh12345"));
+    }
+
+    #[test]
+    fn test_pinned_file_is_reread_when_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("plan.md");
+        std::fs::write(&file, "v1").unwrap();
+        let path = file.display().to_string();
+
+        let ctx = ToolContext::new("pins-test", "cli");
+        let mut items = Vec::new();
+        pin_path(&mut items, &path, &ctx).unwrap();
+        pin_note(&mut items, "prefer sqlx over diesel");
+        assert!(refresh(&mut items, &ctx).is_empty());
+
+        std::fs::write(&file, "version two").unwrap();
+        assert_eq!(refresh(&mut items, &ctx), vec!["pin-1".to_string()]);
+        assert!(items[0].render().contains("version two"));
+
+        let removed = unpin(&mut items, &path);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(items[0].id(), "pin-2");
+    }
+    #[test]
+    fn test_pins_of_hidden_paths_are_refused_and_redacted() {
+        use crate::tools::sandbox::{SandboxEnforcer, SandboxLevel, SandboxPolicy};
+
+        let dir = tempfile::tempdir().unwrap();
+        let hidden = dir.path().join("secrets");
+        std::fs::create_dir(&hidden).unwrap();
+        std::fs::write(hidden.join("id_rsa"), "PRIVATE").unwrap();
+        let visible = dir.path().join("notes.md");
+        std::fs::write(&visible, "API_TOKEN=abcdefgh12345").unwrap();
+
+        let mut ctx = ToolContext::new("pins-test", "cli");
+        ctx.sandbox = Some(std::sync::Arc::new(SandboxEnforcer::disabled_with_policy(
+            SandboxPolicy {
+                level: SandboxLevel::Restricted,
+                hidden_paths: vec![hidden.clone()],
+                ..Default::default()
+            },
+        )));
+        let mut items = Vec::new();
+        let hidden_file = hidden.join("id_rsa").display().to_string();
+        assert!(pin_path(&mut items, &hidden_file, &ctx).is_err());
+        pin_path(&mut items, &visible.display().to_string(), &ctx).unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(!items[0].render().contains("abcdefgh12345"));
+
+        // Once its directory is hidden, a changed pin is not re-read either.
+        std::fs::write(&visible, "changed").unwrap();
+        ctx.sandbox = Some(std::sync::Arc::new(SandboxEnforcer::disabled_with_policy(
+            SandboxPolicy {
+                level: SandboxLevel::Restricted,
+                hidden_paths: vec![dir.path().to_path_buf()],
+                ..Default::default()
+            },
+        )));
+        assert_eq!(refresh(&mut items, &ctx), vec!["pin-1".to_string()]);
+        assert!(items[0]
+            .render()
+            .contains("[Pinned path is no longer readable:"));
+        assert!(!items[0].render().contains("changed"));
+    }
+}
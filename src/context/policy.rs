@@ -0,0 +1,409 @@
+//! Context budget policy: how much of the window history may use, what is
+//! reserved for the response, which recent turns keep full tool output, and
+//! the character caps of system-prompt sections.
+//!
+//! Layers, later wins: built-in defaults, `config.toml [context]`,
+//! `[providers.<name>.context]`, then per-session `/context policy set`.
+
+use serde::{Deserialize, Serialize};
+
+/// How tool results in older (unprotected) history turns are shortened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolOutputStrategy {
+    /// Replace old tool results with compact per-kind stubs.
+    #[default]
+    Strip,
+    /// Keep old tool results, truncating very large ones.
+    Truncate,
+    /// Send old tool results verbatim.
+    Keep,
+}
+
+impl std::str::FromStr for ToolOutputStrategy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "strip" => Ok(Self::Strip),
+            "truncate" => Ok(Self::Truncate),
+            "keep" => Ok(Self::Keep),
+            other => Err(format!(
+                "Unknown tool output strategy '{}' (expected strip, truncate or keep)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionCaps {
+    pub identity: usize,
+    pub runtime: usize,
+    pub custom: usize,
+    pub project: usize,
+    pub agents_md: usize,
+    pub memory_md: usize,
+    pub retrieved_memory: usize,
+}
+
+impl Default for SectionCaps {
+    fn default() -> Self {
+        Self {
+            identity: 4_000,
+            runtime: 1_000,
+            custom: 4_000,
+            project: 7_000,
+            agents_md: 3_000,
+            memory_md: 1_500,
+            retrieved_memory: 3_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextPolicy {
+    /// History may use at most this share of the context window.
+    pub history_cap_percent: usize,
+    /// Share of the window kept free for the response ...
+    pub response_reserve_percent: usize,
+    /// ... clamped to this range on windows of at least 8k tokens.
+    pub response_reserve_min: usize,
+    pub response_reserve_max: usize,
+    /// Most recent turns whose tool output is never stripped.
+    pub protected_recent_turns: usize,
+    /// Character caps, per system-prompt section.
+    pub section_chars: SectionCaps,
+    pub tool_output_strategy: ToolOutputStrategy,
+}
+
+impl Default for ContextPolicy {
+    fn default() -> Self {
+        Self {
+            history_cap_percent: 85,
+            response_reserve_percent: 10,
+            response_reserve_min: 4_096,
+            response_reserve_max: 32_000,
+            protected_recent_turns: 3,
+            section_chars: SectionCaps::default(),
+            tool_output_strategy: ToolOutputStrategy::default(),
+        }
+    }
+}
+
+/// Partial [`ContextPolicy`], as written in config or set per session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContextPolicyOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_cap_percent: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_reserve_percent: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_reserve_min: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_reserve_max: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protected_recent_turns: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section_chars: Option<SectionCapsOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_output_strategy: Option<ToolOutputStrategy>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectionCapsOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agents_md: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_md: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retrieved_memory: Option<usize>,
+}
+
+/// Keys accepted by `/context policy set <key>=<value>`.
+pub const POLICY_KEYS: &[&str] = &[
+    "history_cap_percent",
+    "response_reserve_percent",
+    "response_reserve_min",
+    "response_reserve_max",
+    "protected_recent_turns",
+    "tool_output_strategy",
+    "section.identity",
+    "section.runtime",
+    "section.custom",
+    "section.project",
+    "section.agents_md",
+    "section.memory_md",
+    "section.retrieved_memory",
+];
+
+impl ContextPolicyOverride {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply_to(&self, policy: &mut ContextPolicy) {
+        let set = |target: &mut usize, value: Option<usize>| {
+            if let Some(value) = value {
+                *target = value;
+            }
+        };
+        set(&mut policy.history_cap_percent, self.history_cap_percent);
+        set(
+            &mut policy.response_reserve_percent,
+            self.response_reserve_percent,
+        );
+        set(&mut policy.response_reserve_min, self.response_reserve_min);
+        set(&mut policy.response_reserve_max, self.response_reserve_max);
+        set(
+            &mut policy.protected_recent_turns,
+            self.protected_recent_turns,
+        );
+        if let Some(strategy) = self.tool_output_strategy {
+            policy.tool_output_strategy = strategy;
+        }
+        if let Some(caps) = &self.section_chars {
+            let target = &mut policy.section_chars;
+            set(&mut target.identity, caps.identity);
+            set(&mut target.runtime, caps.runtime);
+            set(&mut target.custom, caps.custom);
+            set(&mut target.project, caps.project);
+            set(&mut target.agents_md, caps.agents_md);
+            set(&mut target.memory_md, caps.memory_md);
+            set(&mut target.retrieved_memory, caps.retrieved_memory);
+        }
+    }
+
+    /// Set one field from `/context policy set key=value`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if key == "tool_output_strategy" {
+            self.tool_output_strategy = Some(value.parse()?);
+            return Ok(());
+        }
+        let number = value
+            .replace('_', "")
+            .parse::<usize>()
+            .map_err(|_| format!("'{}' expects a non-negative integer", key))?;
+        let caps = || SectionCapsOverride::default();
+        match key {
+            "history_cap_percent" => self.history_cap_percent = Some(number),
+            "response_reserve_percent" => self.response_reserve_percent = Some(number),
+            "response_reserve_min" => self.response_reserve_min = Some(number),
+            "response_reserve_max" => self.response_reserve_max = Some(number),
+            "protected_recent_turns" => self.protected_recent_turns = Some(number),
+            _ => {
+                let section = key
+                    .strip_prefix("section.")
+                    .ok_or_else(|| unknown_key(key))?;
+                let caps = self.section_chars.get_or_insert_with(caps);
+                let slot = match section {
+                    "identity" => &mut caps.identity,
+                    "runtime" => &mut caps.runtime,
+                    "custom" => &mut caps.custom,
+                    "project" => &mut caps.project,
+                    "agents_md" => &mut caps.agents_md,
+                    "memory_md" => &mut caps.memory_md,
+                    "retrieved_memory" => &mut caps.retrieved_memory,
+                    _ => return Err(unknown_key(key)),
+                };
+                *slot = Some(number);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unknown_key(key: &str) -> String {
+    format!(
+        "Unknown policy key '{}'. Keys: {}",
+        key,
+        POLICY_KEYS.join(", ")
+    )
+}
+
+/// How a window of `max_tokens` is split under a policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetBreakdown {
+    pub max_tokens: usize,
+    pub system_tokens: usize,
+    pub current_turn_tokens: usize,
+    pub response_reserve: usize,
+    pub history_cap: usize,
+    pub history_budget: usize,
+}
+
+impl ContextPolicy {
+    /// Effective policy for `provider`, with `session` overrides on top.
+    pub fn resolve(
+        config: &crate::config::AppConfig,
+        provider: &str,
+        session: &ContextPolicyOverride,
+    ) -> Self {
+        let mut policy = Self::default();
+        let layers = [
+            config.context.as_ref(),
+            config
+                .get_provider(provider)
+                .and_then(|provider| provider.context.as_ref()),
+            Some(session),
+        ];
+        for layer in layers.into_iter().flatten() {
+            let mut candidate = policy.clone();
+            layer.apply_to(&mut candidate);
+            match candidate.validate() {
+                Ok(()) => policy = candidate,
+                Err(e) => tracing::warn!("Ignoring invalid context policy override: {}", e),
+            }
+        }
+        policy
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        if !(10..=100).contains(&self.history_cap_percent) {
+            errors.push("history_cap_percent must be between 10 and 100".to_string());
+        }
+        if self.response_reserve_percent > 50 {
+            errors.push("response_reserve_percent must be at most 50".to_string());
+        }
+        if self.response_reserve_min > self.response_reserve_max {
+            errors.push("response_reserve_min must not exceed response_reserve_max".to_string());
+        }
+        if self.protected_recent_turns > 50 {
+            errors.push("protected_recent_turns must be at most 50".to_string());
+        }
+        let caps = &self.section_chars;
+        for (name, value) in [
+            ("identity", caps.identity),
+            ("runtime", caps.runtime),
+            ("custom", caps.custom),
+            ("project", caps.project),
+            ("agents_md", caps.agents_md),
+            ("memory_md", caps.memory_md),
+            ("retrieved_memory", caps.retrieved_memory),
+        ] {
+            if !(100..=200_000).contains(&value) {
+                errors.push(format!("section.{} must be between 100 and 200000", name));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    pub fn response_token_reserve(&self, max_tokens: usize) -> usize {
+        let share = max_tokens.saturating_mul(self.response_reserve_percent) / 100;
+        if max_tokens < 8_192 {
+            return share;
+        }
+        share.clamp(self.response_reserve_min, self.response_reserve_max)
+    }
+
+    pub fn breakdown(
+        &self,
+        max_tokens: usize,
+        system_tokens: usize,
+        current_turn_tokens: usize,
+    ) -> BudgetBreakdown {
+        let history_cap = max_tokens.saturating_mul(self.history_cap_percent) / 100;
+        let response_reserve = self.response_token_reserve(max_tokens);
+        let available = max_tokens
+            .saturating_sub(system_tokens)
+            .saturating_sub(current_turn_tokens)
+            .saturating_sub(response_reserve);
+        BudgetBreakdown {
+            max_tokens,
+            system_tokens,
+            current_turn_tokens,
+            response_reserve,
+            history_cap,
+            history_budget: history_cap.min(available),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let caps = &self.section_chars;
+        format!(
+            "  history_cap_percent      {}\n  response_reserve_percent {} (clamped {}..{})\n  protected_recent_turns   {}\n  tool_output_strategy     {:?}\n  section chars            identity={} runtime={} custom={} project={} agents_md={} memory_md={} retrieved_memory={}\n",
+            self.history_cap_percent,
+            self.response_reserve_percent,
+            self.response_reserve_min,
+            self.response_reserve_max,
+            self.protected_recent_turns,
+            self.tool_output_strategy,
+            caps.identity,
+            caps.runtime,
+            caps.custom,
+            caps.project,
+            caps.agents_md,
+            caps.memory_md,
+            caps.retrieved_memory,
+        )
+    }
+}
+
+impl BudgetBreakdown {
+    pub fn render(&self) -> String {
+        let pct = |tokens: usize| tokens as f64 * 100.0 / self.max_tokens.max(1) as f64;
+        format!(
+            "  Window:            {:>8} tokens\n  System prompt:     {:>8} ({:.1}%)\n  Current turn:      {:>8} ({:.1}%)\n  Response reserve:  {:>8} ({:.1}%)\n  History cap:       {:>8} ({:.1}%)\n  History budget:    {:>8} ({:.1}%)\n",
+            self.max_tokens,
+            self.system_tokens,
+            pct(self.system_tokens),
+            self.current_turn_tokens,
+            pct(self.current_turn_tokens),
+            self.response_reserve,
+            pct(self.response_reserve),
+            self.history_cap,
+            pct(self.history_cap),
+            self.history_budget,
+            pct(self.history_budget),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_apply_in_order_and_skip_invalid() {
+        let config: crate::config::AppConfig = toml::from_str(
+            r#"
+            [context]
+            history_cap_percent = 70
+            section_chars = { project = 9000 }
+
+            [providers.local]
+            type = "openai_compat"
+            context = { protected_recent_turns = 1, tool_output_strategy = "keep" }
+            "#,
+        )
+        .unwrap();
+        let mut session = ContextPolicyOverride::default();
+        session.set("section.agents_md", "5_000").unwrap();
+        session.set("history_cap_percent", "5").unwrap();
+
+        let policy = ContextPolicy::resolve(&config, "local", &session);
+        assert_eq!(policy.history_cap_percent, 70);
+        assert_eq!(policy.section_chars.project, 9_000);
+        assert_eq!(policy.protected_recent_turns, 1);
+        assert_eq!(policy.tool_output_strategy, ToolOutputStrategy::Keep);
+        // The session layer failed validation as a whole.
+        assert_eq!(policy.section_chars.agents_md, 3_000);
+
+        assert!(session.set("bogus", "1").is_err());
+    }
+}
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 
 use super::agent_context::AgentContext;
+use super::policy::SectionCaps;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DetailedContextStats {
@@ -17,6 +18,14 @@ pub struct DetailedContextStats {
     pub total: usize,
     pub max: usize,
     pub truncated_chars: usize,
+    /// AGENTS.md files in scope (global, root, nested), in precedence order.
+    #[serde(default)]
+    pub system_instructions: usize,
+    #[serde(default)]
+    pub instruction_files: Vec<super::instructions::InstructionFileStats>,
+    /// Tokenizer the counts above were produced with.
+    #[serde(default)]
+    pub token_counter: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,13 +47,23 @@ struct WorkspacePromptParts {
 }
 
 pub(crate) fn build_prompt_sections(ctx: &AgentContext) -> (String, DetailedContextStats) {
-    let bpe = AgentContext::get_bpe();
-    let mut stats = DetailedContextStats::default();
+    build_prompt_sections_with_caps(ctx, &ctx.policy.section_chars)
+}
+
+pub(crate) fn build_prompt_sections_with_caps(
+    ctx: &AgentContext,
+    caps: &SectionCaps,
+) -> (String, DetailedContextStats) {
+    let counter = ctx.token_counter();
+    let mut stats = DetailedContextStats {
+        token_counter: counter.name(),
+        ..Default::default()
+    };
     let mut sections = Vec::new();
 
     let identity = ctx.system_prompts.join("\n\n");
-    if let Some(section) = build_prompt_section("Identity", identity.clone(), 4_000) {
-        stats.system_static = bpe.encode_with_special_tokens(&section).len();
+    if let Some(section) = build_prompt_section("Identity", identity.clone(), caps.identity) {
+        stats.system_static = counter.count(&section);
         sections.push(section);
     }
 
@@ -57,30 +76,44 @@ pub(crate) fn build_prompt_sections(ctx: &AgentContext) -> (String, DetailedCont
     if let Some(path) = &ctx.transcript_path {
         runtime.push_str(&format!("Session Transcript: {}\n", path.display()));
     }
-    if let Some(section) = build_prompt_section("Runtime Environment", runtime, 1_000) {
-        stats.system_runtime = bpe.encode_with_special_tokens(&section).len();
+    if let Some(section) = build_prompt_section("Runtime Environment", runtime, caps.runtime) {
+        stats.system_runtime = counter.count(&section);
         sections.push(section);
     }
 
     if let Ok(custom_prompt) = fs::read_to_string(".claw_prompt.md") {
         if let Some(section) =
-            build_prompt_section("User Custom Instructions", custom_prompt, 4_000)
+            build_prompt_section("User Custom Instructions", custom_prompt, caps.custom)
         {
-            stats.system_custom = bpe.encode_with_special_tokens(&section).len();
+            stats.system_custom = counter.count(&section);
             sections.push(section);
         }
     }
 
+    let (instructions, instruction_files) = ctx.instructions.render(caps.agents_md, counter);
+    if let Some(section) = build_prompt_section("Project Instructions", instructions, usize::MAX) {
+        stats.system_instructions = counter.count(&section);
+        stats.instruction_files = instruction_files;
+        sections.push(section);
+    }
+
     let workspace = build_workspace_prompt_parts(stats.system_task_plan == 0);
-    let project_context = workspace.project_context_with_inline_memory();
-    if let Some(section) = build_prompt_section("Project Context", project_context, 7_000) {
-        stats.system_project = bpe.encode_with_special_tokens(&section).len();
+    let project_context = workspace.project_context_with_inline_memory(caps);
+    if let Some(section) = build_prompt_section("Project Context", project_context, caps.project) {
+        stats.system_project = counter.count(&section);
         sections.push(section);
     }
 
-    if let Some(memory) = &ctx.retrieved_memory {
-        if let Some(section) = build_prompt_section("Retrieved Memory", memory.clone(), 3_000) {
-            stats.memory = bpe.encode_with_special_tokens(&section).len();
+    if let Some(memory) = ctx.retrieved_memory.as_deref().map(str::trim) {
+        // Stored text from earlier sessions, documents and code: data, never
+        // instructions. Cut before fencing so the cap keeps the closing tag.
+        let memory = AgentContext::truncate_chars(memory, caps.retrieved_memory);
+        if !memory.is_empty() {
+            let section = format!(
+                "## Retrieved Memory\n{}\n",
+                crate::security::fence_untrusted("retrieved_memory", &memory)
+            );
+            stats.memory = counter.count(&section);
             sections.push(section);
         }
     }
@@ -94,7 +127,7 @@ pub(crate) fn get_detailed_stats(
     pending_user_input: Option<&str>,
 ) -> DetailedContextStats {
     let (_, mut stats) = build_prompt_sections(ctx);
-    let bpe = AgentContext::get_bpe();
+    let counter = ctx.token_counter();
 
     let (_, history_tokens, _, truncated_chars) = ctx.build_history_with_budget();
     stats.history = history_tokens;
@@ -104,32 +137,30 @@ pub(crate) fn get_detailed_stats(
         for msg in &turn.messages {
             for part in &msg.parts {
                 if let Some(text) = &part.text {
-                    stats.current_turn += bpe.encode_with_special_tokens(text).len();
+                    stats.current_turn += counter.count(text);
                 }
                 if let Some(fc) = &part.function_call {
-                    stats.current_turn += bpe.encode_with_special_tokens(&fc.name).len();
-                    stats.current_turn +=
-                        bpe.encode_with_special_tokens(&fc.args.to_string()).len();
+                    stats.current_turn += counter.count(&fc.name);
+                    stats.current_turn += counter.count(&fc.args.to_string());
                 }
                 if let Some(fr) = &part.function_response {
-                    stats.current_turn += bpe.encode_with_special_tokens(&fr.name).len();
-                    stats.current_turn += bpe
-                        .encode_with_special_tokens(&fr.response.to_string())
-                        .len();
+                    stats.current_turn += counter.count(&fr.name);
+                    stats.current_turn += counter.count(&fr.response.to_string());
                 }
             }
         }
     } else if let Some(input) = pending_user_input {
-        stats.current_turn = bpe.encode_with_special_tokens(input).len();
+        stats.current_turn = counter.count(input);
     }
 
     if let Some(last) = ctx.dialogue_history.last() {
-        stats.last_turn = AgentContext::turn_token_estimate(last, &bpe);
+        stats.last_turn = AgentContext::turn_token_estimate(last, counter);
     }
 
     stats.total = stats.system_static
         + stats.system_runtime
         + stats.system_custom
+        + stats.system_instructions
         + stats.system_project
         + stats.system_task_plan
         + stats.memory
@@ -161,12 +192,6 @@ fn build_workspace_prompt_parts(include_task_planning: bool) -> WorkspacePromptP
         project_context.push_str("If the user request is complex (e.g. multi-step refactoring, new feature implementation), you MUST use the `task_plan` tool immediately to create a structured plan (action='add').\n\n");
     }
 
-    if let Ok(content) = fs::read_to_string("AGENTS.md") {
-        project_context.push_str("### AGENTS.md\n");
-        project_context.push_str(&AgentContext::truncate_chars(&content, 3_000));
-        project_context.push_str("\n\n");
-    }
-
     WorkspacePromptParts {
         project_context,
         durable_memory: fs::read_to_string("MEMORY.md").ok(),
@@ -174,11 +199,11 @@ fn build_workspace_prompt_parts(include_task_planning: bool) -> WorkspacePromptP
 }
 
 impl WorkspacePromptParts {
-    fn project_context_with_inline_memory(&self) -> String {
+    fn project_context_with_inline_memory(&self, caps: &SectionCaps) -> String {
         let mut project_context = self.project_context.clone();
         if let Some(content) = &self.durable_memory {
             project_context.push_str("### MEMORY.md\n");
-            project_context.push_str(&AgentContext::truncate_chars(content, 1_500));
+            project_context.push_str(&AgentContext::truncate_chars(content, caps.memory_md));
             project_context.push_str("\n\n");
         }
         project_context
@@ -194,7 +219,7 @@ pub(crate) fn build_llm_payload(
     Option<super::model::Message>,
     PromptReport,
 ) {
-    let bpe = tiktoken_rs::cl100k_base().unwrap();
+    let counter = ctx.token_counter();
     let mut current_turn_messages = Vec::new();
     let mut current_turn_tokens = 0;
     if let Some(turn) = &ctx.current_turn {
@@ -210,13 +235,13 @@ pub(crate) fn build_llm_payload(
                     file_data: None,
                 }],
             };
-            current_turn_tokens += AgentContext::estimate_tokens(&bpe, &separator);
+            current_turn_tokens += AgentContext::estimate_tokens(counter, &separator);
             current_turn_messages.push(separator);
 
             current_turn_tokens += sanitized_turn
                 .messages
                 .iter()
-                .map(|m| AgentContext::estimate_tokens(&bpe, m))
+                .map(|m| AgentContext::estimate_tokens(counter, m))
                 .sum::<usize>();
             current_turn_messages.extend(sanitized_turn.messages);
         }
@@ -239,10 +264,18 @@ pub(crate) fn build_llm_payload(
         system_static.push(format!("## Custom Instructions\n{}", custom));
     }
 
+    let (instructions, _) = ctx
+        .instructions
+        .render(ctx.policy.section_chars.agents_md, counter);
+    if !instructions.trim().is_empty() {
+        system_static.push(format!("## Project Instructions\n{}", instructions.trim()));
+    }
+
     let workspace = build_workspace_prompt_parts(false);
     system_static.push(format!("## Project Context\n{}", workspace.project_context));
 
     let mut active_evidence = ctx.active_evidence.clone();
+    active_evidence.extend(ctx.evidence_store.evidence());
     if let Some(mem) = &ctx.retrieved_memory {
         active_evidence.push(crate::evidence::Evidence::new(
             "legacy_rag".into(),
@@ -258,9 +291,13 @@ pub(crate) fn build_llm_payload(
         .max_history_tokens
         .saturating_sub(current_turn_tokens)
         .saturating_sub(super::history::response_token_reserve(
+            &ctx.policy,
             ctx.max_history_tokens,
         ));
-    let system_assembler = crate::context_assembler::ContextAssembler::new(system_budget);
+    let mut system_assembler = crate::context_assembler::ContextAssembler::new(system_budget);
+    for item in &ctx.pinned {
+        system_assembler.pin(item.id(), item.render());
+    }
     let (assembled_system_text, report_data) = system_assembler.assemble_prompt(
         &system_static.join("\n\n"),
         "",
@@ -288,6 +325,7 @@ pub(crate) fn build_llm_payload(
 
     let system_prompt_tokens = report_data.used_tokens;
     let history_budget = super::history::effective_history_budget(
+        &ctx.policy,
         ctx.max_history_tokens,
         system_prompt_tokens,
         current_turn_tokens,
@@ -13,6 +13,10 @@ pub fn format_context_details(ctx: &AgentContext) -> String {
         stats.max,
         (stats.total as f64 / stats.max as f64) * 100.0
     ));
+    details.push_str(&format!(
+        "\x1b[1;33m[Tokenizer]\x1b[0m     {}\n",
+        stats.token_counter
+    ));
 
     details.push_str("\n\x1b[1;33m[System Components]\x1b[0m\n");
     details.push_str(&format!(
@@ -38,11 +42,28 @@ pub fn format_context_details(ctx: &AgentContext) -> String {
         ));
     }
 
+    if stats.system_instructions > 0 {
+        details.push_str(&format!(
+            "  - Instructions:       {} tokens (AGENTS.md, lowest precedence first)\n",
+            stats.system_instructions
+        ));
+        for file in &stats.instruction_files {
+            details.push_str(&format!(
+                "    {}. {} [{:?}] {} tokens{}\n",
+                file.precedence,
+                file.path,
+                file.scope,
+                file.tokens,
+                if file.truncated { " (truncated)" } else { "" }
+            ));
+        }
+    }
+
     details.push_str(&format!(
         "  - Project Context:    {} tokens\n",
         stats.system_project
     ));
-    let project_files = ["AGENTS.md", "README.md", "MEMORY.md"];
+    let project_files = ["README.md", "MEMORY.md"];
     for file in project_files {
         if let Ok(meta) = std::fs::metadata(file) {
             details.push_str(&format!("    * {} ({} bytes)\n", file, meta.len()));
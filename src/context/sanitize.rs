@@ -1,4 +1,5 @@
-use super::model::FunctionResponse;
+use super::audit::DedupeAction;
+use super::model::{FunctionResponse, Turn};
 use crate::tools::protocol::ToolExecutionEnvelope;
 
 pub(crate) fn strip_thinking_tags(text: &str) -> String {
@@ -171,6 +172,42 @@ pub(crate) fn strip_response_payload(fr: &mut FunctionResponse) {
     }
 }
 
+/// Replace redundant tool outputs found by [`super::audit`] with short
+/// pointers to the copy that was kept. Returns how many were replaced.
+pub(crate) fn apply_dedupe_actions(turns: &mut [Turn], actions: &[DedupeAction]) -> usize {
+    let mut applied = 0;
+    for action in actions {
+        let Some(fr) = turns
+            .get_mut(action.target.turn)
+            .and_then(|turn| turn.messages.get_mut(action.target.message))
+            .and_then(|message| message.parts.get_mut(action.target.part))
+            .and_then(|part| part.function_response.as_mut())
+        else {
+            continue;
+        };
+        let Some(result_val) = fr.response.get_mut("result") else {
+            continue;
+        };
+        let Some(result_str) = result_val.as_str() else {
+            continue;
+        };
+        let replaced = match ToolExecutionEnvelope::from_json_str(result_str) {
+            Some(mut envelope) => {
+                envelope.result.output = action.replacement.clone();
+                envelope.result.truncated = false;
+                match envelope.to_json_string() {
+                    Ok(serialized) => serialized,
+                    Err(_) => continue,
+                }
+            }
+            None => action.replacement.clone(),
+        };
+        *result_val = serde_json::Value::String(replaced);
+        applied += 1;
+    }
+    applied
+}
+
 pub(crate) fn prepare_function_response_for_llm(fr: &mut FunctionResponse) {
     let Some(obj) = fr.response.as_object_mut() else {
         return;
@@ -311,6 +348,55 @@ mod tests {
         assert!(!stripped.contains("\"truncated\""));
     }
 
+    #[test]
+    fn apply_dedupe_actions_keeps_envelope_shape() {
+        let mut turns = vec![Turn {
+            turn_id: "t1".to_string(),
+            user_message: "read it".to_string(),
+            messages: vec![crate::context::Message {
+                role: "function".to_string(),
+                parts: vec![crate::context::Part {
+                    text: None,
+                    function_call: None,
+                    function_response: Some(FunctionResponse {
+                        name: "read_file".to_string(),
+                        id: None,
+                        response: serde_json::json!({
+                            "result": serde_json::json!({
+                                "ok": true,
+                                "tool_name": "read_file",
+                                "output": "fn main() {}",
+                                "evidence_kind": "file"
+                            }).to_string()
+                        }),
+                    }),
+                    thought_signature: None,
+                    file_data: None,
+                }],
+            }],
+        }];
+        let action = DedupeAction {
+            target: crate::context::audit::PartRef {
+                turn: 0,
+                message: 0,
+                part: 0,
+            },
+            replacement: "[deduplicated: main.rs was re-read in turn 3]".to_string(),
+            tokens_saved: 4,
+        };
+
+        assert_eq!(apply_dedupe_actions(&mut turns, &[action]), 1);
+
+        let fr = turns[0].messages[0].parts[0]
+            .function_response
+            .as_ref()
+            .unwrap();
+        let envelope =
+            ToolExecutionEnvelope::from_json_str(fr.response["result"].as_str().unwrap()).unwrap();
+        assert!(envelope.result.output.starts_with("[deduplicated:"));
+        assert_eq!(envelope.effects.evidence_kind.as_deref(), Some("file"));
+    }
+
     #[test]
     fn prepare_function_response_for_llm_fences_marked_tool_output() {
         let raw = crate::tools::protocol::StructuredToolOutput::new(
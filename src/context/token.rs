@@ -1,27 +1,311 @@
 use super::model::{Message, Turn};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tiktoken_rs::CoreBPE;
 
-pub(crate) fn get_bpe() -> CoreBPE {
-    use once_cell::sync::Lazy;
-    static BPE: Lazy<CoreBPE> = Lazy::new(|| tiktoken_rs::cl100k_base().unwrap());
-    BPE.clone()
+/// Counts tokens the way a provider's tokenizer does, so context budgets
+/// match what the API will bill and enforce.
+pub trait TokenCounter: Send + Sync {
+    /// Label reported in context stats, e.g. `o200k_base`.
+    fn name(&self) -> String;
+    fn count(&self, text: &str) -> usize;
+    /// Report that a prompt we counted as `estimated` tokens was billed as
+    /// `actual` by the API. Exact tokenizers ignore this.
+    fn calibrate(&self, _estimated: usize, _actual: usize) {}
 }
 
-pub(crate) fn estimate_tokens(bpe: &CoreBPE, msg: &Message) -> usize {
+/// Exact count with one of OpenAI's BPE vocabularies.
+pub struct TiktokenCounter {
+    name: &'static str,
+    bpe: &'static CoreBPE,
+}
+
+impl TiktokenCounter {
+    pub fn cl100k() -> Self {
+        Self {
+            name: "cl100k_base",
+            bpe: tiktoken_rs::cl100k_base_singleton(),
+        }
+    }
+
+    pub fn o200k() -> Self {
+        Self {
+            name: "o200k_base",
+            bpe: tiktoken_rs::o200k_base_singleton(),
+        }
+    }
+}
+
+impl TokenCounter for TiktokenCounter {
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn count(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
+/// Exact count with a Hugging Face `tokenizer.json` (Qwen, DeepSeek).
+pub struct HfTokenizerCounter {
+    family: String,
+    tokenizer: tokenizers::Tokenizer,
+}
+
+impl HfTokenizerCounter {
+    pub fn from_file(family: &str, path: &std::path::Path) -> Result<Self, String> {
+        let tokenizer = tokenizers::Tokenizer::from_file(path)
+            .map_err(|e| format!("failed to load {}: {}", path.display(), e))?;
+        Ok(Self {
+            family: family.to_string(),
+            tokenizer,
+        })
+    }
+}
+
+impl TokenCounter for HfTokenizerCounter {
+    fn name(&self) -> String {
+        format!("tokenizer:{}", self.family)
+    }
+
+    fn count(&self, text: &str) -> usize {
+        match self.tokenizer.encode(text, false) {
+            Ok(encoding) => encoding.len(),
+            Err(_) => TiktokenCounter::cl100k().count(text),
+        }
+    }
+}
+
+/// cl100k count scaled by a ratio learned from the usage the API reports.
+/// Used where no local tokenizer exists (Gemini) or none is installed.
+pub struct CalibratedCounter {
+    family: String,
+    base: TiktokenCounter,
+    ratio: Mutex<f64>,
+}
+
+impl CalibratedCounter {
+    /// Weight of each new observation in the running ratio.
+    const SMOOTHING: f64 = 0.3;
+    const MIN_RATIO: f64 = 0.5;
+    const MAX_RATIO: f64 = 2.0;
+    /// Prompts smaller than this are dominated by fixed overhead.
+    const MIN_SAMPLE_TOKENS: usize = 256;
+
+    pub fn new(family: &str, initial_ratio: f64) -> Self {
+        Self {
+            family: family.to_string(),
+            base: TiktokenCounter::cl100k(),
+            ratio: Mutex::new(initial_ratio.clamp(Self::MIN_RATIO, Self::MAX_RATIO)),
+        }
+    }
+
+    pub fn ratio(&self) -> f64 {
+        *self.ratio.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl TokenCounter for CalibratedCounter {
+    fn name(&self) -> String {
+        format!("calibrated:{} (cl100k x{:.2})", self.family, self.ratio())
+    }
+
+    fn count(&self, text: &str) -> usize {
+        (self.base.count(text) as f64 * self.ratio()).round() as usize
+    }
+
+    fn calibrate(&self, estimated: usize, actual: usize) {
+        if estimated < Self::MIN_SAMPLE_TOKENS || actual == 0 {
+            return;
+        }
+        let mut ratio = self.ratio.lock().unwrap_or_else(|e| e.into_inner());
+        // `estimated` already includes the current ratio.
+        let observed = *ratio * actual as f64 / estimated as f64;
+        *ratio = (*ratio * (1.0 - Self::SMOOTHING) + observed * Self::SMOOTHING)
+            .clamp(Self::MIN_RATIO, Self::MAX_RATIO);
+    }
+}
+
+/// Counters are shared per tokenizer family so calibration carries over
+/// between sessions and client instances.
+static COUNTERS: Lazy<Mutex<HashMap<String, Arc<dyn TokenCounter>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Directories searched for `<family>.json` tokenizer files, in order.
+fn tokenizer_dirs() -> Vec<PathBuf> {
+    vec![
+        PathBuf::from("tokenizers"),
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("rusty-claw/tokenizers"),
+        dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(".rusty-claw/tokenizers"),
+    ]
+}
+
+/// A pinned Hugging Face `tokenizer.json`, from `config.toml
+/// [tokenizers.<family>]`. Nothing is downloaded for a family without one.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TokenizerSource {
+    /// Model repository, e.g. `Qwen/Qwen2.5-7B-Instruct`.
+    pub repo: String,
+    /// Full commit id; branch names are refused.
+    pub revision: String,
+    /// Hex sha256 of the file at that commit.
+    pub sha256: String,
+}
+
+impl TokenizerSource {
+    fn url(&self) -> Result<String, String> {
+        if self.revision.len() != 40 || !self.revision.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!(
+                "revision '{}' is not a full commit id",
+                self.revision
+            ));
+        }
+        Ok(format!(
+            "https://huggingface.co/{}/resolve/{}/tokenizer.json",
+            self.repo, self.revision
+        ))
+    }
+
+    fn verify(&self, bytes: &[u8]) -> Result<(), String> {
+        let actual = format!("{:x}", Sha256::digest(bytes));
+        if actual.eq_ignore_ascii_case(self.sha256.trim()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "sha256 mismatch: expected {}, got {}",
+                self.sha256, actual
+            ))
+        }
+    }
+}
+
+/// Larger downloads are not a tokenizer.
+const MAX_TOKENIZER_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Fetch the family's pinned tokenizer into the user config directory on a
+/// background thread; sessions started after it lands count exactly.
+fn download_tokenizer(family: &str, source: TokenizerSource) {
+    let url = match source.url() {
+        Ok(url) => url,
+        Err(e) => {
+            tracing::warn!("Not downloading {} tokenizer: {}", family, e);
+            return;
+        }
+    };
+    let Some(dir) = tokenizer_dirs().into_iter().nth(1) else {
+        return;
+    };
+    let family = family.to_string();
+    std::thread::spawn(move || {
+        let fetch = || -> Result<PathBuf, String> {
+            let response = reqwest::blocking::Client::builder()
+                .timeout(std::time::Duration::from_secs(120))
+                .build()
+                .map_err(|e| e.to_string())?
+                .get(&url)
+                .send()
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| e.to_string())?;
+            if response
+                .content_length()
+                .is_some_and(|len| len > MAX_TOKENIZER_BYTES)
+            {
+                return Err("response too large".to_string());
+            }
+            let bytes = response.bytes().map_err(|e| e.to_string())?;
+            source.verify(&bytes)?;
+            // Only keep what the tokenizer library can load.
+            tokenizers::Tokenizer::from_bytes(&bytes).map_err(|e| e.to_string())?;
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let path = dir.join(format!("{}.json", family));
+            let partial = path.with_extension("json.part");
+            std::fs::write(&partial, &bytes).map_err(|e| e.to_string())?;
+            std::fs::rename(&partial, &path).map_err(|e| e.to_string())?;
+            Ok(path)
+        };
+        match fetch() {
+            Ok(path) => {
+                tracing::info!("Downloaded {} tokenizer to {}", family, path.display());
+                if let Ok(counter) = HfTokenizerCounter::from_file(&family, &path) {
+                    COUNTERS
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(family, Arc::new(counter));
+                }
+            }
+            Err(e) => tracing::warn!(
+                "Failed to download {} tokenizer from {}: {}",
+                family,
+                url,
+                e
+            ),
+        }
+    });
+}
+
+fn load_family_counter(family: &str) -> Arc<dyn TokenCounter> {
+    match family {
+        "cl100k" => Arc::new(TiktokenCounter::cl100k()),
+        "o200k" => Arc::new(TiktokenCounter::o200k()),
+        "qwen" | "deepseek" => {
+            for dir in tokenizer_dirs() {
+                let path = dir.join(format!("{}.json", family));
+                if !path.exists() {
+                    continue;
+                }
+                match HfTokenizerCounter::from_file(family, &path) {
+                    Ok(counter) => return Arc::new(counter),
+                    Err(e) => tracing::warn!("{}", e),
+                }
+            }
+            tracing::info!(
+                "No {} tokenizer file found; using calibrated estimate",
+                family
+            );
+            let source = crate::config::AppConfig::load().tokenizers.remove(family);
+            if let Some(source) = source {
+                download_tokenizer(family, source);
+            }
+            Arc::new(CalibratedCounter::new(family, 1.0))
+        }
+        other => Arc::new(CalibratedCounter::new(other, 1.0)),
+    }
+}
+
+/// Shared counter for a tokenizer family (`cl100k`, `o200k`, `qwen`,
+/// `deepseek`, `gemini`, ...).
+pub fn counter_for_family(family: &str) -> Arc<dyn TokenCounter> {
+    let mut counters = COUNTERS.lock().unwrap_or_else(|e| e.into_inner());
+    counters
+        .entry(family.to_string())
+        .or_insert_with(|| load_family_counter(family))
+        .clone()
+}
+
+pub(crate) fn default_counter() -> Arc<dyn TokenCounter> {
+    counter_for_family("cl100k")
+}
+
+pub(crate) fn estimate_tokens(counter: &dyn TokenCounter, msg: &Message) -> usize {
     let mut count = 0;
     for part in &msg.parts {
         if let Some(text) = &part.text {
-            count += bpe.encode_with_special_tokens(text).len();
+            count += counter.count(text);
         }
         if let Some(fc) = &part.function_call {
-            count += bpe.encode_with_special_tokens(&fc.name).len();
-            count += bpe.encode_with_special_tokens(&fc.args.to_string()).len();
+            count += counter.count(&fc.name);
+            count += counter.count(&fc.args.to_string());
         }
         if let Some(fr) = &part.function_response {
-            count += bpe.encode_with_special_tokens(&fr.name).len();
-            count += bpe
-                .encode_with_special_tokens(&fr.response.to_string())
-                .len();
+            count += counter.count(&fr.name);
+            count += counter.count(&fr.response.to_string());
         }
     }
     count
@@ -34,6 +318,57 @@ pub(crate) fn truncate_chars(input: &str, max_chars: usize) -> String {
     input.chars().take(max_chars).collect()
 }
 
-pub(crate) fn turn_token_estimate(turn: &Turn, bpe: &CoreBPE) -> usize {
-    turn.messages.iter().map(|m| estimate_tokens(bpe, m)).sum()
+pub(crate) fn turn_token_estimate(turn: &Turn, counter: &dyn TokenCounter) -> usize {
+    turn.messages
+        .iter()
+        .map(|m| estimate_tokens(counter, m))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn o200k_and_cl100k_differ_on_cjk_text() {
+        let text = "上下文预算应当与模型的分词器保持一致，否则会过早压缩。";
+        let cl = TiktokenCounter::cl100k().count(text);
+        let o2 = TiktokenCounter::o200k().count(text);
+        assert!(o2 < cl, "o200k={o2} cl100k={cl}");
+    }
+
+    #[test]
+    fn calibrated_counter_converges_toward_reported_usage() {
+        let counter = CalibratedCounter::new("test", 1.0);
+        let text = "word ".repeat(400);
+        let base = TiktokenCounter::cl100k().count(&text);
+        for _ in 0..20 {
+            let estimated = counter.count(&text);
+            counter.calibrate(estimated, base * 13 / 10);
+        }
+        assert!((counter.ratio() - 1.3).abs() < 0.02, "{}", counter.ratio());
+        assert!(counter.name().starts_with("calibrated:test"));
+
+        // Tiny prompts are ignored.
+        counter.calibrate(10, 100);
+        assert!((counter.ratio() - 1.3).abs() < 0.02);
+    }
+
+    #[test]
+    fn tokenizer_source_requires_a_commit_and_matching_checksum() {
+        let mut source = TokenizerSource {
+            repo: "Qwen/Qwen2.5-7B-Instruct".to_string(),
+            revision: "main".to_string(),
+            sha256: format!("{:x}", Sha256::digest(b"{}")),
+        };
+        assert!(source.url().is_err());
+
+        source.revision = "0123456789abcdef0123456789abcdef01234567".to_string();
+        assert!(source
+            .url()
+            .unwrap()
+            .ends_with("/resolve/0123456789abcdef0123456789abcdef01234567/tokenizer.json"));
+        assert!(source.verify(b"{}").is_ok());
+        assert!(source.verify(b"{ }").is_err());
+    }
 }
@@ -45,8 +45,9 @@ pub fn append_turn(path: Option<&Path>, turn: &Turn) -> std::io::Result<()> {
         fs::create_dir_all(parent)?;
     }
     let mut file = OpenOptions::new().create(true).append(true).open(path)?;
-    let serialized = serde_json::to_string(turn)?;
-    writeln!(file, "{serialized}")?;
+    let mut value = serde_json::to_value(turn)?;
+    crate::security::redact_json_strings(&mut value);
+    writeln!(file, "{}", serde_json::to_string(&value)?)?;
     Ok(())
 }
 
@@ -57,6 +58,7 @@ pub(crate) fn load_into_context(ctx: &mut AgentContext) -> std::io::Result<usize
     let turns = load_turns(path)?;
     let loaded = turns.len();
     ctx.dialogue_history.extend(turns);
+    ctx.dedupe_history();
     Ok(loaded)
 }
 
@@ -18,6 +18,29 @@ pub fn start_turn(ctx: &mut AgentContext, text: String) {
     });
 }
 
+pub fn start_turn_with_attachments(
+    ctx: &mut AgentContext,
+    text: String,
+    attachments: &[super::Attachment],
+) {
+    start_turn(ctx, text);
+    if let Some(message) = ctx
+        .current_turn
+        .as_mut()
+        .and_then(|turn| turn.messages.first_mut())
+    {
+        message
+            .parts
+            .extend(attachments.iter().map(|attachment| Part {
+                text: Some(attachment.render()),
+                function_call: None,
+                function_response: None,
+                thought_signature: None,
+                file_data: None,
+            }));
+    }
+}
+
 pub fn add_message_to_current_turn(ctx: &mut AgentContext, msg: Message) {
     if let Some(turn) = &mut ctx.current_turn {
         turn.messages.push(msg);
@@ -30,5 +53,6 @@ pub fn end_turn(ctx: &mut AgentContext) {
             tracing::warn!("Failed to append turn to transcript: {}", e);
         }
         ctx.dialogue_history.push(turn);
+        ctx.dedupe_history();
     }
 }
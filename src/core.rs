@@ -3,7 +3,7 @@ use crate::llm_client::{LlmClient, StreamEvent};
 use crate::tools::Tool;
 use crate::trace::{shared_bus, TraceActor, TraceContext, TraceSeed, TraceSpanHandle, TraceStatus};
 use async_trait::async_trait;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -131,6 +131,93 @@ impl AgentOutput for SilentOutputWrapper {
     }
 }
 
+/// Screens user-visible text for canary tokens before it reaches the
+/// frontend.  A hit is replaced with a notice and reported to
+/// [`crate::security::scan_outgoing`], which makes the agent loop abort.
+struct CanaryGuardOutput {
+    session_id: String,
+    inner: Arc<dyn AgentOutput>,
+}
+
+impl CanaryGuardOutput {
+    const SUPPRESSED: &'static str = "[Security] Canary leak detected — suppressing output.\n";
+
+    fn screen<'a>(&self, channel: &str, text: &'a str) -> &'a str {
+        match crate::security::scan_outgoing(&self.session_id, channel, text) {
+            Ok(()) => text,
+            Err(_) => Self::SUPPRESSED,
+        }
+    }
+}
+
+#[async_trait]
+impl AgentOutput for CanaryGuardOutput {
+    async fn on_waiting(&self, message: &str) {
+        self.inner.on_waiting(message).await;
+    }
+    fn clear_waiting(&self) {
+        self.inner.clear_waiting();
+    }
+    async fn on_text(&self, text: &str) {
+        self.inner.on_text(self.screen("on_text", text)).await;
+    }
+    async fn on_text_replace(&self, text: &str) {
+        self.inner
+            .on_text_replace(self.screen("on_text", text))
+            .await;
+    }
+    async fn on_thinking(&self, text: &str) {
+        self.inner
+            .on_thinking(self.screen("on_thinking", text))
+            .await;
+    }
+    async fn on_tool_start(&self, name: &str, args: &str) {
+        self.inner
+            .on_tool_start(name, self.screen("on_tool_start", args))
+            .await;
+    }
+    async fn on_tool_end(&self, result: &str) {
+        self.inner.on_tool_end(result).await;
+    }
+    async fn on_error(&self, error: &str) {
+        self.inner.on_error(self.screen("on_error", error)).await;
+    }
+    async fn flush(&self) {
+        self.inner.flush().await;
+    }
+    async fn on_file(&self, path: &str) {
+        self.inner.on_file(path).await;
+    }
+    async fn on_plan_update(&self, state: &crate::task_state::TaskStateSnapshot) {
+        self.inner.on_plan_update(state).await;
+    }
+    async fn on_status_update(
+        &self,
+        tokens: usize,
+        max_tokens: usize,
+        energy: usize,
+        provider: &str,
+        model: &str,
+    ) {
+        self.inner
+            .on_status_update(tokens, max_tokens, energy, provider, model)
+            .await;
+    }
+    async fn on_task_finish(&self, summary: &str) {
+        self.inner
+            .on_task_finish(self.screen("on_text", summary))
+            .await;
+    }
+    async fn on_llm_request(&self, prompt_summary: &str) {
+        self.inner.on_llm_request(prompt_summary).await;
+    }
+    async fn on_llm_response(&self, response_summary: &str) {
+        self.inner
+            .on_llm_response(self.screen("on_llm_response", response_summary))
+            .await;
+    }
+}
+
 pub trait OutputRouter: Send + Sync {
     fn try_route(&self, reply_to: &str) -> Option<Arc<dyn AgentOutput>>;
 }
@@ -270,6 +357,34 @@ pub struct AgentLoop {
     active_trace: Option<ActiveTrace>,
     code_mode_service: crate::code_mode::service::CodeModeService,
     code_mode_format: crate::code_mode::description::CodeModeFormat,
+    session_sandbox: Option<Arc<crate::tools::sandbox::SessionSandbox>>,
+    skill_runtime: Option<Arc<crate::skills::runtime::SkillRuntime>>,
+    pending_compaction: Option<PendingCompaction>,
+}
+
+/// A semantic summary being written in the background for the oldest
+/// `turns` turns, whose transcript was `transcript` when it started.
+struct PendingCompaction {
+    turns: usize,
+    transcript: String,
+    usage: usize,
+    threshold: usize,
+    task: tokio::task::JoinHandle<Result<crate::context::SemanticSummary, String>>,
+}
+
+impl Drop for AgentLoop {
+    fn drop(&mut self) {
+        // Placeholders of a torn-down session can no longer be revealed.
+        crate::security::forget_session_secrets(&self.session_id);
+        // Subagent sessions end with their loop; keep the canary registry
+        // from growing with every spawned subagent.
+        if self.is_subagent {
+            crate::security::forget_session_canary(&self.session_id);
+        }
+        if let Some(pending) = self.pending_compaction.take() {
+            pending.task.abort();
+        }
+    }
 }
 
 impl AgentLoop {
@@ -288,6 +403,14 @@ impl AgentLoop {
         telemetry: Arc<crate::telemetry::TelemetryExporter>,
         task_state_store: Arc<crate::task_state::TaskStateStore>,
     ) -> Self {
+        let mut context = context;
+        context.bind_session_canary(&session_id);
+        context.set_token_counter(llm.token_counter());
+        context.set_policy_provider(llm.provider_name());
+        let output: Arc<dyn AgentOutput> = Arc::new(CanaryGuardOutput {
+            session_id: session_id.clone(),
+            inner: output,
+        });
         Self {
             session_id,
             llm,
@@ -312,6 +435,9 @@ impl AgentLoop {
             active_trace: None,
             code_mode_service: crate::code_mode::service::CodeModeService::default(),
             code_mode_format: crate::code_mode::description::CodeModeFormat::default(),
+            session_sandbox: None,
+            skill_runtime: None,
+            pending_compaction: None,
         }
     }
 
@@ -336,6 +462,26 @@ impl AgentLoop {
         self.trace_seed = Some(trace_seed);
     }
 
+    pub fn set_session_sandbox(&mut self, sandbox: Arc<crate::tools::sandbox::SessionSandbox>) {
+        self.session_sandbox = Some(sandbox);
+    }
+
+    pub fn session_sandbox(&self) -> Option<Arc<crate::tools::sandbox::SessionSandbox>> {
+        self.session_sandbox.clone()
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn set_skill_runtime(&mut self, runtime: Arc<crate::skills::runtime::SkillRuntime>) {
+        self.skill_runtime = Some(runtime);
+    }
+
+    pub fn skill_runtime(&self) -> Option<Arc<crate::skills::runtime::SkillRuntime>> {
+        self.skill_runtime.clone()
+    }
+
     pub fn remaining_session_timeout_sec(&self) -> Option<u64> {
         self.session_deadline.map(|deadline| {
             let remaining = deadline.saturating_duration_since(Instant::now());
@@ -355,16 +501,34 @@ impl AgentLoop {
             .await
     }
 
+    pub async fn code_mode_stored_values(&self) -> HashMap<String, serde_json::Value> {
+        self.code_mode_service.stored_values(&self.session_id).await
+    }
+
+    pub async fn restore_code_mode_stored_values(
+        &self,
+        values: HashMap<String, serde_json::Value>,
+    ) {
+        self.code_mode_service
+            .restore_stored_values(&self.session_id, values)
+            .await
+    }
+
     fn is_cancelled(&self) -> bool {
         self.cancelled.load(std::sync::atomic::Ordering::SeqCst)
     }
 
     pub fn update_llm(&mut self, new_llm: Arc<dyn LlmClient>) {
         self.context.max_history_tokens = new_llm.context_window();
+        self.context.set_token_counter(new_llm.token_counter());
+        self.context.set_policy_provider(new_llm.provider_name());
         self.llm = new_llm;
     }
     pub fn update_output(&mut self, output: Arc<dyn AgentOutput>) {
-        self.output = output;
+        self.output = Arc::new(CanaryGuardOutput {
+            session_id: self.session_id.clone(),
+            inner: output,
+        });
     }
 
     pub(crate) fn trace_actor(&self) -> TraceActor {
@@ -618,6 +782,10 @@ impl AgentLoop {
         self.context.get_context_details()
     }
 
+    pub fn get_context_audit(&self, large_output_tokens: usize) -> String {
+        self.context.get_context_audit(large_output_tokens)
+    }
+
     #[allow(dead_code)]
     pub fn get_detailed_stats(&self) -> crate::context::DetailedContextStats {
         self.context.get_detailed_stats(None)
@@ -660,10 +828,27 @@ impl AgentLoop {
         self.context.build_llm_payload(&state, &assembler)
     }
 
+    /// Compact the oldest turns once history passes 85% of the budget.
+    ///
+    /// The summary is written on a background task so the turn does not
+    /// wait for it; a later call applies it if those turns are unchanged,
+    /// falling back to rule-based compaction if the summary failed. `force`
+    /// (`/compact`) waits for the summary instead.
     pub async fn maybe_compact_history(
         &mut self,
         force: bool,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self
+            .pending_compaction
+            .as_ref()
+            .is_some_and(|pending| force || pending.task.is_finished())
+        {
+            self.apply_pending_compaction().await;
+        }
+        if self.pending_compaction.is_some() {
+            return Ok(());
+        }
+
         let (current_usage, max_tokens, _, _, _) = self.context.get_context_status();
         let threshold = (max_tokens as f64 * 0.85) as usize;
 
@@ -689,7 +874,53 @@ impl AgentLoop {
             threshold
         );
 
-        if let Some(reason) = self.context.rule_based_compact(num_to_compact) {
+        let transcript = self.context.compaction_transcript(num_to_compact);
+        let task = tokio::spawn(Self::summarize_for_compaction(
+            self.llm.clone(),
+            transcript.clone(),
+        ));
+        self.pending_compaction = Some(PendingCompaction {
+            turns: num_to_compact,
+            transcript,
+            usage: current_usage,
+            threshold,
+            task,
+        });
+        if force {
+            self.apply_pending_compaction().await;
+        }
+        Ok(())
+    }
+
+    /// Wait for the background summary and compact with it. Dropped if the
+    /// turns it covers changed in the meantime.
+    async fn apply_pending_compaction(&mut self) {
+        let Some(pending) = self.pending_compaction.take() else {
+            return;
+        };
+        let summary = pending
+            .task
+            .await
+            .unwrap_or_else(|e| Err(format!("compaction task failed: {}", e)));
+        if self.context.compaction_transcript(pending.turns) != pending.transcript {
+            tracing::info!("History changed while it was being summarized; compaction skipped");
+            return;
+        }
+        let (compacted, mode) = match summary {
+            Ok(summary) => (
+                self.context.semantic_compact(pending.turns, &summary),
+                "semantic",
+            ),
+            Err(e) => {
+                tracing::warn!(
+                    "Semantic compaction failed, falling back to rule-based: {}",
+                    e
+                );
+                (self.context.rule_based_compact(pending.turns), "rule_based")
+            }
+        };
+
+        if let Some(reason) = compacted {
             self.output.on_text(&format!("[System] {}\n", reason)).await;
             self.record_trace_event(
                 TraceActor::Context,
@@ -697,17 +928,16 @@ impl AgentLoop {
                 TraceStatus::Ok,
                 Some(reason.clone()),
                 serde_json::json!({
-                    "compacted_turns": num_to_compact,
-                    "usage_tokens": current_usage as u64,
-                    "threshold_tokens": threshold as u64,
-                    "history_tokens": current_usage as u64,
+                    "mode": mode,
+                    "compacted_turns": pending.turns,
+                    "usage_tokens": pending.usage as u64,
+                    "threshold_tokens": pending.threshold as u64,
+                    "history_tokens": pending.usage as u64,
                 }),
                 self.turn_span_id(),
                 None,
             );
         }
-
-        Ok(())
     }
 
     #[cfg(test)]
@@ -794,6 +1024,45 @@ impl AgentLoop {
     pub async fn step(
         &mut self,
         goal: String,
+    ) -> Result<RunExit, Box<dyn std::error::Error + Send + Sync>> {
+        self.run_step(goal, Vec::new()).await
+    }
+
+    /// [`Self::step`] for a message typed by the local user: its `@path`
+    /// mentions are read (sandbox permitting) and inlined into the turn.
+    /// Goals from subagents, jobs and chat bridges go through `step` and
+    /// never have paths expanded.
+    pub async fn step_with_mentions(
+        &mut self,
+        goal: String,
+    ) -> Result<RunExit, Box<dyn std::error::Error + Send + Sync>> {
+        let attachments = crate::context::pins::expand_mentions(&goal, &self.user_path_context());
+        self.run_step(goal, attachments).await
+    }
+
+    /// Pin `target` into every prompt: an existing path, read under the
+    /// session's sandbox, or otherwise a note.
+    pub fn pin(&mut self, target: &str) -> Result<crate::context::PinnedItem, String> {
+        let ctx = self.user_path_context();
+        self.context.pin(target, &ctx)
+    }
+
+    /// Context for reading paths the user named (`@path`, `/pin`,
+    /// `/ingest`) under the session's sandbox.
+    pub(crate) fn user_path_context(&self) -> crate::tools::ToolContext {
+        let mut ctx =
+            crate::tools::ToolContext::new(self.session_id.clone(), self.reply_to.clone());
+        ctx.sandbox = self
+            .session_sandbox
+            .as_ref()
+            .and_then(|sandbox| sandbox.current());
+        ctx
+    }
+
+    async fn run_step(
+        &mut self,
+        goal: String,
+        attachments: Vec<crate::context::Attachment>,
     ) -> Result<RunExit, Box<dyn std::error::Error + Send + Sync>> {
         let goal = goal.trim().to_string();
         if goal.is_empty() {
@@ -842,7 +1111,8 @@ impl AgentLoop {
             }
         }
 
-        self.context.start_turn(turn_goal.clone());
+        self.context
+            .start_turn_with_attachments(turn_goal.clone(), &attachments);
 
         let (mut state, mut c_ids) = self.initialize_task_state(&turn_goal);
         self.begin_trace_run(&turn_goal, state.task_id.clone());
@@ -859,6 +1129,19 @@ impl AgentLoop {
             self.turn_span_id(),
             None,
         );
+        if !attachments.is_empty() {
+            self.record_trace_event(
+                TraceActor::Context,
+                "mentions_attached",
+                TraceStatus::Ok,
+                None,
+                serde_json::json!({
+                    "paths": attachments.iter().map(|a| a.mention.as_str()).collect::<Vec<_>>(),
+                }),
+                self.turn_span_id(),
+                None,
+            );
+        }
         self.telemetry.start_span("agent_step", c_ids.clone());
 
         let output_clone = Arc::clone(&self.output);
@@ -1038,6 +1321,18 @@ impl AgentLoop {
                 });
             }
 
+            if let Some(exit) = self.check_canary_trips().await {
+                if let Some(span) = iteration_span.take() {
+                    span.finish(
+                        "iteration_finished",
+                        TraceStatus::Error,
+                        Some("canary leak".to_string()),
+                        serde_json::json!({}),
+                    );
+                }
+                return Ok(exit);
+            }
+
             if state.status == "finished" {
                 let summary = state.summary();
                 if let Err(reason) = self.prepare_finished_run(&summary, &mut state).await {
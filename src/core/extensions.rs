@@ -56,6 +56,11 @@ pub struct PromptDraft {
     pub skill_state_summary: Option<String>,
     /// General execution notices surfaced by runtime-level extensions.
     pub execution_notices: Option<String>,
+    /// Knowledge retrieved for the current request, shown as the
+    /// "Retrieved Memory" prompt section.
+    pub retrieved_memory: Option<String>,
+    /// Citation labels of the chunks in `retrieved_memory`.
+    pub retrieved_memory_sources: Vec<String>,
 }
 
 /// Returned by `before_turn_start`.
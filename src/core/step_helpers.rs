@@ -49,6 +49,42 @@ impl AgentLoop {
         false
     }
 
+    /// Feed the prompt size the API billed back into the context's token
+    /// counter so calibrated estimates track the real tokenizer.
+    fn calibrate_token_counter(
+        &self,
+        messages: &[Message],
+        system: Option<&Message>,
+        tools: &[Arc<dyn Tool>],
+        usage: crate::llm_client::TokenUsage,
+    ) {
+        let counter = self.context.token_counter();
+        let estimated = messages
+            .iter()
+            .chain(system)
+            .map(|m| crate::context::AgentContext::estimate_tokens(counter, m))
+            .sum::<usize>()
+            + tools
+                .iter()
+                .map(|tool| {
+                    let definition = tool.definition();
+                    counter.count(&definition.name)
+                        + counter.count(&definition.description)
+                        + definition
+                            .input_schema
+                            .map(|schema| counter.count(&schema.to_string()))
+                            .unwrap_or(0)
+                })
+                .sum::<usize>();
+        counter.calibrate(estimated, usage.prompt_tokens);
+        tracing::debug!(
+            "Prompt tokens: estimated {} with {}, reported {}",
+            estimated,
+            counter.name(),
+            usage.prompt_tokens
+        );
+    }
+
     pub(super) async fn collect_stream_response(
         &mut self,
         messages: Vec<Message>,
@@ -86,6 +122,19 @@ impl AgentLoop {
                 system_chars + message_chars
             );
             self.output.on_llm_request(&prompt_summary).await;
+            // Retries resend the same prompt; store it once.
+            let prompt_refs = if llm_attempts == 1 && iteration_trace_ctx.is_some() {
+                let definitions: Vec<_> =
+                    current_tools.iter().map(|tool| tool.definition()).collect();
+                let (system, messages) = (system.clone(), messages.clone());
+                tokio::task::spawn_blocking(move || {
+                    crate::trace::prompt::store_prompt(system.as_ref(), &messages, &definitions)
+                })
+                .await
+                .ok()
+            } else {
+                None
+            };
             let llm_span = iteration_trace_ctx.as_ref().map(|ctx| {
                 self.trace_bus.start_span(
                     ctx,
@@ -99,6 +148,7 @@ impl AgentLoop {
                         "approx_prompt_chars": system_chars + message_chars,
                         "approx_prompt_tokens": ((system_chars + message_chars) / 4) as u64,
                         "stream_attempt": llm_attempts,
+                        "prompt": prompt_refs,
                     }),
                 )
             });
@@ -127,6 +177,7 @@ impl AgentLoop {
             match stream_res {
                 Ok(mut rx) => {
                     let mut current_turn_text = String::new();
+                    let mut reported_usage = None;
 
                     let stream_loop_res: Result<(), crate::llm_client::LlmError> = loop {
                         tokio::select! {
@@ -160,6 +211,9 @@ impl AgentLoop {
                                         }
                                         tool_calls_accumulated.push((tc, sig));
                                     }
+                                    Some(StreamEvent::Usage(usage)) => {
+                                        reported_usage = Some(usage);
+                                    }
                                     Some(StreamEvent::Done) | None => break Ok(()),
                                     Some(StreamEvent::Error(e)) => {
                                         break Err(crate::llm_client::LlmError::ApiError(format!("Stream error: {}", e)));
@@ -189,6 +243,14 @@ impl AgentLoop {
                             {
                                 current_turn_text.push_str("</think>");
                             }
+                            if let Some(usage) = reported_usage {
+                                self.calibrate_token_counter(
+                                    &messages,
+                                    system.as_ref(),
+                                    &current_tools,
+                                    usage,
+                                );
+                            }
                             if let Some(span) = llm_span {
                                 span.finish(
                                     "llm_request_finished",
@@ -428,12 +490,18 @@ impl AgentLoop {
                 .iter()
                 .any(|(call, _)| call.name == "exec");
 
-        let args_leak = tool_calls_accumulated
-            .iter()
-            .any(|(tc, _)| crate::security::check_canary_leak(&tc.args.to_string()));
+        let leak = crate::security::find_canary_leak(full_text, "llm_output").or_else(|| {
+            tool_calls_accumulated.iter().find_map(|(tc, _)| {
+                crate::security::find_canary_leak(&tc.args.to_string(), "tool_args")
+            })
+        });
 
-        if crate::security::check_canary_leak(full_text) || args_leak {
+        if let Some(leak) = leak {
             tracing::warn!("Canary token leaked in LLM output — possible prompt extraction attack");
+            // Streamed chunks may already have tripped the output guard; this
+            // branch handles the leak, so drop those reports.
+            crate::security::take_canary_leaks(&self.session_id);
+            self.record_canary_leak(&leak);
             self.output
                 .on_text("[Security] Canary leak detected — suppressing output.\n")
                 .await;
@@ -572,6 +640,39 @@ impl AgentLoop {
         Ok(())
     }
 
+    fn record_canary_leak(&self, leak: &crate::security::CanaryLeak) {
+        self.record_trace_event(
+            TraceActor::System,
+            "canary_leak",
+            TraceStatus::Error,
+            Some(leak.to_string()),
+            serde_json::json!({
+                "channel": leak.channel,
+                "owner_session_id": leak.owner_session_id,
+            }),
+            self.turn_span_id(),
+            None,
+        );
+    }
+
+    /// Abort the run if an outgoing channel (chat output, tool arguments)
+    /// carried a canary since the last check.
+    pub(super) async fn check_canary_trips(&mut self) -> Option<RunExit> {
+        let leaks = crate::security::take_canary_leaks(&self.session_id);
+        let first = leaks.first()?;
+        for leak in &leaks {
+            self.record_canary_leak(leak);
+        }
+        let message = format!("{first} — possible prompt extraction attack");
+        self.output
+            .on_error(&format!("[Security] {message}. Run aborted."))
+            .await;
+        Some(
+            self.finalize_exit(RunExit::CriticallyFailed(message), true)
+                .await,
+        )
+    }
+
     pub(super) async fn finalize_exit(&mut self, exit: RunExit, end_span: bool) -> RunExit {
         self.output.flush().await;
         self.context.end_turn();
@@ -618,6 +719,9 @@ impl AgentLoop {
         if self.is_cancelled() {
             return Some(self.finalize_exit(RunExit::StoppedByUser, true).await);
         }
+        if let Some(exit) = self.check_canary_trips().await {
+            return Some(exit);
+        }
 
         task_state.iterations += 1;
         task_state.energy_points = task_state.energy_points.saturating_sub(1);
@@ -703,6 +807,8 @@ impl AgentLoop {
         }
         self.context.skill_instructions = draft.skill_instructions.clone();
         self.context.skill_state_summary = draft.skill_state_summary.clone();
+        self.context
+            .set_retrieved_memory(draft.retrieved_memory, draft.retrieved_memory_sources);
 
         let mut execution_notices = draft.execution_notices;
         let code_mode_visible = self.llm.capabilities().supports_code_mode
@@ -728,6 +834,20 @@ impl AgentLoop {
         }
         self.context.execution_notices = execution_notices;
 
+        let pin_ctx = self.user_path_context();
+        let refreshed_pins = self.context.refresh_pins(&pin_ctx);
+        if !refreshed_pins.is_empty() {
+            self.record_trace_event(
+                TraceActor::Context,
+                "pinned_context_refreshed",
+                TraceStatus::Ok,
+                Some(format!("{} pinned path(s) re-read", refreshed_pins.len())),
+                serde_json::json!({ "pin_ids": refreshed_pins }),
+                self.turn_span_id(),
+                None,
+            );
+        }
+
         let max_tokens = self.context.max_history_tokens;
         let assembler = crate::context_assembler::ContextAssembler::new(max_tokens);
         let (messages, system, _) = self.context.build_llm_payload(state, &assembler);
@@ -868,6 +988,7 @@ impl AgentLoop {
                 is_autopilot: self.is_autopilot,
                 todos_path: self.todos_path(),
                 execution_guard_state: self.execution_guard_state.clone(),
+                output: Some(self.output.clone()),
             },
         )
     }
@@ -1070,6 +1191,31 @@ impl AgentLoop {
             self.output.on_file(path).await;
         }
 
+        let touched_path = envelope.effects.file_path.as_deref().or(
+            match envelope.effects.evidence_kind.as_deref() {
+                Some("file" | "directory") => envelope.effects.evidence_source_path.as_deref(),
+                _ => None,
+            },
+        );
+        if let Some(path) = touched_path {
+            let loaded = self.context.touch_instructions(path);
+            if !loaded.is_empty() {
+                let files: Vec<String> = loaded.iter().map(|p| p.display().to_string()).collect();
+                self.record_trace_event(
+                    TraceActor::Context,
+                    "instructions_loaded",
+                    TraceStatus::Ok,
+                    Some(format!(
+                        "{} nested AGENTS.md file(s) now in scope",
+                        files.len()
+                    )),
+                    serde_json::json!({ "files": files, "touched": path }),
+                    self.turn_span_id(),
+                    None,
+                );
+            }
+        }
+
         if let (Some(kind), Some(source_path), Some(summary)) = (
             envelope.effects.evidence_kind.as_deref(),
             envelope.effects.evidence_source_path.as_deref(),
@@ -1081,23 +1227,29 @@ impl AgentLoop {
                 source_path.to_string(),
                 1.0,
                 summary.to_string(),
-                envelope.result.output.clone(),
-            );
-
-            if kind == "directory" || kind == "file" {
-                self.context.active_evidence.retain(|existing| {
-                    existing.source_kind != kind || existing.source_path != source_path
-                });
-            } else if kind == "diagnostic" {
-                self.context
-                    .active_evidence
-                    .retain(|existing| existing.source_kind != kind);
+                crate::security::redact_secrets(&envelope.result.output),
+            )
+            .with_line_range(envelope.effects.evidence_range.clone());
+
+            // File and directory reads go to the evidence store, which
+            // keeps the latest read per path and range and watches it for
+            // changes.
+            if let Some(evidence) = self.context.evidence_store.record(evidence) {
+                if kind == "diagnostic" {
+                    self.context
+                        .active_evidence
+                        .retain(|existing| existing.source_kind != kind);
+                }
+                self.context.active_evidence.push(evidence);
             }
-
-            self.context.active_evidence.push(evidence);
         }
 
         if envelope.effects.invalidate_diagnostic_evidence {
+            // The agent's own write: forget the earlier read rather than
+            // reporting it as changed.
+            if let Some(path) = envelope.effects.file_path.as_deref() {
+                self.context.evidence_store.forget(path);
+            }
             for evidence in &mut self.context.active_evidence {
                 if evidence.source_kind == "diagnostic" {
                     evidence.source_version = Some("invalidated_by_write".to_string());
@@ -1117,6 +1269,104 @@ impl AgentLoop {
         message
     }
 
+    /// Score external (fenced) tool output with the configured injection
+    /// detector and apply the action of the matching score band.
+    async fn screen_tool_output_for_injection(
+        &self,
+        result: String,
+        trace_ctx: Option<&crate::trace::TraceContext>,
+    ) -> String {
+        let Some(mut envelope) = Self::parse_tool_envelope(&result) else {
+            return result;
+        };
+        if envelope.effects.output_security.is_none() {
+            return result;
+        }
+        let output = envelope.result.output.clone();
+        let finding =
+            tokio::task::spawn_blocking(move || crate::security::assess_injection(&output))
+                .await
+                .ok()
+                .flatten();
+        let Some(finding) = finding else {
+            return result;
+        };
+
+        let source = envelope
+            .effects
+            .output_source
+            .clone()
+            .unwrap_or_else(|| envelope.result.tool_name.clone());
+        let mut quarantine_id = None;
+        match finding.action {
+            crate::security::InjectionAction::Warn => {}
+            crate::security::InjectionAction::Strip => {
+                envelope.result.output =
+                    crate::security::strip_injection_lines(&envelope.result.output, &finding);
+            }
+            crate::security::InjectionAction::Quarantine => {
+                let id = crate::security::quarantine_content(
+                    &self.session_id,
+                    &source,
+                    &envelope.result.output,
+                    &finding,
+                );
+                envelope.result.output = format!(
+                    "[QUARANTINED {id}] Output from `{source}` was withheld: suspected prompt \
+                     injection (score {:.2}). The user has been asked whether to release it.",
+                    finding.score.score
+                );
+                envelope.effects.await_user = Some(crate::tools::protocol::UserPromptRequest {
+                    question: format!(
+                        "Output from `{source}` looks like a prompt-injection attempt \
+                         (score {:.2}, matched: {}) and was withheld from the agent.",
+                        finding.score.score,
+                        finding.score.matched.join(", ")
+                    ),
+                    context_key: format!("quarantine:{id}"),
+                    options: vec![
+                        format!("/quarantine show {id}"),
+                        format!("/quarantine release {id}"),
+                        format!("/quarantine drop {id}"),
+                    ],
+                    recommendation: Some(format!("/quarantine show {id}")),
+                });
+                quarantine_id = Some(id);
+            }
+        }
+        envelope.effects.injection_warning = Some(finding.warning());
+
+        if let Some(ctx) = trace_ctx {
+            self.trace_bus.record_event(
+                ctx,
+                TraceActor::Tool,
+                "injection_detected",
+                if finding.action == crate::security::InjectionAction::Warn {
+                    TraceStatus::Ok
+                } else {
+                    TraceStatus::Error
+                },
+                Some(format!(
+                    "{} scored {:.2} on `{}` -> {}",
+                    finding.detector,
+                    finding.score.score,
+                    source,
+                    finding.action.as_str()
+                )),
+                serde_json::json!({
+                    "detector": finding.detector,
+                    "score": finding.score.score,
+                    "action": finding.action.as_str(),
+                    "matched": finding.score.matched,
+                    "source": source,
+                    "quarantine_id": quarantine_id,
+                }),
+            );
+        }
+
+        envelope.to_json_string().unwrap_or(result)
+    }
+
     pub(super) async fn execute_tool_round(
         &mut self,
         tool_calls_accumulated: Vec<ToolCallRecord>,
@@ -1208,6 +1458,17 @@ impl AgentLoop {
                 continue;
             }
 
+            // Record placeholders for any secrets so the session can map
+            // redacted tokens back without persisting the raw values.
+            crate::security::redact_secrets_for_session(&self.session_id, &result);
+
+            let result = if is_error {
+                result
+            } else {
+                self.screen_tool_output_for_injection(result, iteration_trace_ctx.as_ref())
+                    .await
+            };
+
             if is_error {
                 self.output.on_error(&result).await;
             } else {
@@ -1249,6 +1510,49 @@ impl AgentLoop {
         (response_parts, should_yield_to_user)
     }
 
+    /// Ask the LLM for a structured summary of the turns being compacted.
+    /// Runs on a spawned task, so it takes the client rather than `self`.
+    pub(super) async fn summarize_for_compaction(
+        llm: Arc<dyn LlmClient>,
+        transcript: String,
+    ) -> Result<crate::context::SemanticSummary, String> {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            parts: vec![Part {
+                text: Some(crate::context::history::semantic_compaction_prompt(
+                    &transcript,
+                )),
+                function_call: None,
+                function_response: None,
+                thought_signature: None,
+                file_data: None,
+            }],
+        }];
+
+        let collect = async {
+            let mut rx = llm
+                .stream(messages, None, vec![])
+                .await
+                .map_err(|e| e.to_string())?;
+            let mut reply = String::new();
+            while let Some(event) = rx.recv().await {
+                match event {
+                    StreamEvent::Text(t) => reply.push_str(&t),
+                    StreamEvent::Error(e) => return Err(e),
+                    StreamEvent::Done => break,
+                    _ => {}
+                }
+            }
+            Ok(reply)
+        };
+        let reply = tokio::time::timeout(Duration::from_secs(60), collect)
+            .await
+            .map_err(|_| "compaction summary timed out".to_string())??;
+
+        crate::context::SemanticSummary::parse(&reply)
+            .ok_or_else(|| "compactor returned no usable summary".to_string())
+    }
+
     /// Generate a user-facing status summary of current unfinished work via the LLM.
     /// Falls back to a structural status report if the LLM call fails.
     pub(super) async fn generate_status_summary(&self) -> String {
@@ -1171,6 +1171,31 @@ async fn test_canary_leak_redacts_history_and_suppresses_output() {
     cleanup_session(session_id);
 }
 
+#[tokio::test]
+async fn test_subagent_canary_is_forgotten_on_teardown() {
+    let session_id = "sub_test-canary-teardown";
+    cleanup_session(session_id);
+    let (telemetry, _handle) = crate::telemetry::TelemetryExporter::new();
+    let mut agent = AgentLoop::new(
+        session_id.to_string(),
+        Arc::new(PromptCapturingLlm::new(Vec::new())),
+        "test_cli".to_string(),
+        Vec::new(),
+        AgentContext::new(),
+        Arc::new(TestOutput::new()),
+        Arc::new(telemetry),
+        Arc::new(crate::task_state::TaskStateStore::new(session_id)),
+    );
+    agent.is_subagent = true;
+    let token = crate::security::session_canary(session_id);
+    assert!(crate::security::check_canary_leak(&token));
+
+    drop(agent);
+    assert!(!crate::security::check_canary_leak(&token));
+
+    cleanup_session(session_id);
+}
+
 #[tokio::test]
 async fn test_canary_leak_via_tool_call_args_is_caught() {
     // The LLM embeds the canary inside a tool-call argument instead of text.
@@ -1278,3 +1303,97 @@ async fn test_code_mode_failed_cells_emit_error_tool_envelopes() {
 
     cleanup_session(session_id);
 }
+
+#[tokio::test]
+async fn test_compaction_summary_runs_in_background_and_applies_later() {
+    let llm = Arc::new(PromptCapturingLlm::new(vec![StreamEvent::Text(
+        r#"{"decisions": ["keep the public API stable"]}"#.to_string(),
+    )]));
+    let output = Arc::new(TestOutput::new());
+    let session_id = "test-background-compaction";
+    cleanup_session(session_id);
+
+    let mut context = AgentContext::new();
+    for i in 0..4 {
+        context.start_turn(format!("request number {i} with some extra words"));
+        context.end_turn();
+    }
+    context.max_history_tokens = 200;
+    let (telemetry, _handle) = crate::telemetry::TelemetryExporter::new();
+    let mut agent = AgentLoop::new(
+        session_id.to_string(),
+        llm,
+        "test_cli".to_string(),
+        Vec::new(),
+        context,
+        output,
+        Arc::new(telemetry),
+        Arc::new(crate::task_state::TaskStateStore::new(session_id)),
+    );
+
+    // The turn goes on while the summary is being written.
+    agent.maybe_compact_history(false).await.unwrap();
+    assert!(agent.pending_compaction.is_some());
+    assert_eq!(agent.context.dialogue_history.len(), 4);
+
+    while !agent
+        .pending_compaction
+        .as_ref()
+        .is_some_and(|pending| pending.task.is_finished())
+    {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    agent.maybe_compact_history(false).await.unwrap();
+    assert!(crate::context::history::is_pinned_summary(
+        &agent.context.dialogue_history[0]
+    ));
+    assert!(agent.context.dialogue_history.len() < 4);
+
+    cleanup_session(session_id);
+}
+
+#[tokio::test]
+async fn test_only_step_with_mentions_inlines_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("notes.md");
+    std::fs::write(&file, "mentioned contents").unwrap();
+    let goal = format!("summarize @{}", file.display());
+
+    for (session_id, expand) in [
+        ("test-step-mentions-off", false),
+        ("test-step-mentions-on", true),
+    ] {
+        cleanup_session(session_id);
+        let llm = Arc::new(PromptCapturingLlm::new(vec![StreamEvent::Text(
+            "Done".to_string(),
+        )]));
+        let (telemetry, _handle) = crate::telemetry::TelemetryExporter::new();
+        let mut agent = AgentLoop::new(
+            session_id.to_string(),
+            llm,
+            "test_cli".to_string(),
+            Vec::new(),
+            AgentContext::new(),
+            Arc::new(TestOutput::new()),
+            Arc::new(telemetry),
+            Arc::new(crate::task_state::TaskStateStore::new(session_id)),
+        );
+
+        if expand {
+            agent.step_with_mentions(goal.clone()).await.unwrap();
+        } else {
+            agent.step(goal.clone()).await.unwrap();
+        }
+        let inlined = agent
+            .context
+            .dialogue_history
+            .iter()
+            .flat_map(|turn| &turn.messages)
+            .flat_map(|message| &message.parts)
+            .filter_map(|part| part.text.as_deref())
+            .any(|text| text.contains("mentioned contents"));
+        assert_eq!(inlined, expand);
+
+        cleanup_session(session_id);
+    }
+}
@@ -16,6 +16,9 @@ pub struct Evidence {
     pub score: f32,
     pub summary: String,
     pub content: String,
+    /// Line range of a partial file read, `None` for the whole source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_range: Option<String>,
 }
 
 impl Evidence {
@@ -39,9 +42,15 @@ impl Evidence {
             score,
             summary,
             content,
+            line_range: None,
         }
     }
 
+    pub fn with_line_range(mut self, line_range: Option<String>) -> Self {
+        self.line_range = line_range;
+        self
+    }
+
     /// Helper to get the freshness of a file source using hash + mtime.
     pub fn generate_freshness(source_kind: &str, source_path: &str) -> (Option<String>, u64) {
         let retrieved_at = SystemTime::now()
@@ -11,6 +11,7 @@ pub mod core;
 pub mod discord;
 pub mod event_log;
 pub mod evidence;
+pub mod evidence_store;
 pub mod llm_client;
 pub mod logging;
 pub mod lsp_client;
@@ -11,6 +11,7 @@ pub fn create_llm_client(
     platform_override: Option<String>,
     config: &crate::config::AppConfig,
 ) -> Result<Arc<dyn LlmClient>, String> {
+    crate::security::register_provider_secrets(config);
     if let Some(prov_config) = config.get_provider(provider) {
         tracing::info!("Initializing provider '{}' from config", provider);
         match prov_config.type_name.as_str() {
@@ -41,14 +42,18 @@ pub fn create_llm_client(
                     .context_window
                     .unwrap_or_else(|| estimate_context_window(&model_final));
 
-                Ok(Arc::new(OpenAiCompatClient::new_with_window(
+                let mut client = OpenAiCompatClient::new_with_window(
                     api_key,
                     base_url,
                     model_final,
                     provider.to_string(),
                     context_window,
                     prov_config.reasoning_effort.clone(),
-                )))
+                );
+                if let Some(stream_usage) = prov_config.stream_usage {
+                    client = client.with_stream_usage(stream_usage);
+                }
+                Ok(Arc::new(client))
             }
             "gemini" => {
                 let raw_api_key = if let Some(env_var) = &prov_config.api_key_env {
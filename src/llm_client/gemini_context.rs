@@ -521,6 +521,21 @@ pub(crate) async fn emit_sse_data_block(
                 crate::utils::truncate_log(data)
             );
 
+            // usageMetadata repeats on every chunk; the consumer keeps the last.
+            if let Some(prompt_tokens) = json["usageMetadata"]["promptTokenCount"].as_u64() {
+                let completion_tokens = json["usageMetadata"]["candidatesTokenCount"]
+                    .as_u64()
+                    .unwrap_or(0);
+                let _ = tx
+                    .send(super::protocol::StreamEvent::Usage(
+                        super::protocol::TokenUsage {
+                            prompt_tokens: prompt_tokens as usize,
+                            completion_tokens: completion_tokens as usize,
+                        },
+                    ))
+                    .await;
+            }
+
             if let Some(candidate) = json["candidates"].as_array().and_then(|a| a.first()) {
                 if let Some(thought) = candidate.get("thought").and_then(|v| v.as_str()) {
                     if !thought.is_empty() {
@@ -9,7 +9,9 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::Instrument;
 
-use super::protocol::{create_standard_client, LlmCapabilities, LlmClient, LlmError, StreamEvent};
+use super::protocol::{
+    create_standard_client, LlmCapabilities, LlmClient, LlmError, StreamEvent, TokenUsage,
+};
 use crate::utils::{format_full_error, truncate_log, truncate_log_error};
 
 pub struct OpenAiCompatClient {
@@ -21,6 +23,23 @@ pub struct OpenAiCompatClient {
     #[allow(dead_code)]
     context_window: usize,
     reasoning_effort: Option<String>,
+    /// Send `stream_options.include_usage` to get billed token counts.
+    stream_usage: bool,
+}
+
+/// Endpoints known to accept `stream_options`; others may reject the
+/// unknown field, so they only get it when configured.
+const STREAM_USAGE_HOSTS: &[&str] = &[
+    "api.openai.com",
+    "api.deepseek.com",
+    "dashscope.aliyuncs.com",
+    "openrouter.ai",
+];
+
+fn default_stream_usage(base_url: &str) -> bool {
+    STREAM_USAGE_HOSTS
+        .iter()
+        .any(|host| base_url.contains(host))
 }
 
 impl OpenAiCompatClient {
@@ -30,6 +49,24 @@ impl OpenAiCompatClient {
         active_tools: &mut std::collections::HashMap<usize, (String, String, Option<String>)>,
         index_map: &mut std::collections::HashMap<usize, usize>,
     ) {
+        if let Some(usage) = json.get("usage").filter(|v| !v.is_null()) {
+            let prompt_tokens = usage
+                .get("prompt_tokens")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as usize;
+            let completion_tokens = usage
+                .get("completion_tokens")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0) as usize;
+            if prompt_tokens > 0 {
+                let _ = tx
+                    .send(StreamEvent::Usage(TokenUsage {
+                        prompt_tokens,
+                        completion_tokens,
+                    }))
+                    .await;
+            }
+        }
         if let Some(choices) = json.get("choices").and_then(|v| v.as_array()) {
             for choice in choices {
                 if let Some(delta) = choice.get("delta") {
@@ -102,6 +139,7 @@ impl OpenAiCompatClient {
         Self {
             api_key,
             client: create_standard_client(Some(&base_url)),
+            stream_usage: default_stream_usage(&base_url),
             base_url,
             model_name,
             provider_name,
@@ -121,6 +159,7 @@ impl OpenAiCompatClient {
         let client = create_standard_client(Some(&base_url));
         Self {
             api_key,
+            stream_usage: default_stream_usage(&base_url),
             base_url,
             model_name,
             provider_name,
@@ -129,6 +168,12 @@ impl OpenAiCompatClient {
             reasoning_effort,
         }
     }
+
+    /// Override whether usage is requested in streamed responses.
+    pub fn with_stream_usage(mut self, stream_usage: bool) -> Self {
+        self.stream_usage = stream_usage;
+        self
+    }
 }
 
 #[async_trait]
@@ -226,6 +271,9 @@ impl LlmClient for OpenAiCompatClient {
             "stream": true,
             "parallel_tool_calls": true,
         });
+        if self.stream_usage {
+            body_map["stream_options"] = serde_json::json!({ "include_usage": true });
+        }
 
         if let Some(effort) = &self.reasoning_effort {
             body_map["reasoning_effort"] = serde_json::Value::String(effort.clone());
@@ -597,6 +645,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stream_usage_only_for_known_endpoints() {
+        assert!(default_stream_usage(
+            "https://api.openai.com/v1/chat/completions"
+        ));
+        assert!(default_stream_usage(
+            "https://coding.dashscope.aliyuncs.com/v1/chat/completions"
+        ));
+        assert!(!default_stream_usage(
+            "http://localhost:11434/v1/chat/completions"
+        ));
+    }
+
     #[test]
     fn test_estimate_context_window() {
         assert_eq!(estimate_context_window("gemini-1.5-pro"), 1_000_000);
@@ -1,3 +1,6 @@
+use crate::context::token::{counter_for_family, TokenCounter};
+use std::sync::Arc;
+
 pub fn estimate_context_window(model: &str) -> usize {
     let m = model.to_lowercase();
     if m.contains("gemini-2")
@@ -20,3 +23,28 @@ pub fn estimate_context_window(model: &str) -> usize {
         128_000
     }
 }
+
+/// Tokenizer family for `model`: o200k for newer OpenAI models, a local
+/// Hugging Face tokenizer for Qwen and DeepSeek, and a usage-calibrated
+/// estimate for Gemini.
+pub fn token_counter_family(provider: &str, model: &str) -> &'static str {
+    let m = model.to_lowercase();
+    if m.contains("gemini") || provider == "gemini" || provider == "vertex" {
+        "gemini"
+    } else if m.contains("qwen") || m.contains("qwq") {
+        "qwen"
+    } else if m.contains("deepseek") {
+        "deepseek"
+    } else {
+        let name = m.rsplit('/').next().unwrap_or(&m);
+        match tiktoken_rs::tokenizer::get_tokenizer(name) {
+            Some(tiktoken_rs::tokenizer::Tokenizer::O200kBase)
+            | Some(tiktoken_rs::tokenizer::Tokenizer::O200kHarmony) => "o200k",
+            _ => "cl100k",
+        }
+    }
+}
+
+pub fn token_counter_for_model(provider: &str, model: &str) -> Arc<dyn TokenCounter> {
+    counter_for_family(token_counter_family(provider, model))
+}
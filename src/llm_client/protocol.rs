@@ -23,9 +23,17 @@ pub enum StreamEvent {
     Thought(String),
     ToolCall(FunctionCall, Option<String>),
     Error(String),
+    /// Token usage the API reported for this request.
+    Usage(TokenUsage),
     Done,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct LlmCapabilities {
     pub function_tools: bool,
@@ -42,6 +50,10 @@ pub trait LlmClient: Send + Sync {
         crate::llm_client::policy::estimate_context_window(self.model_name())
     }
     fn capabilities(&self) -> LlmCapabilities;
+    /// Tokenizer used to budget prompts for this model.
+    fn token_counter(&self) -> Arc<dyn crate::context::token::TokenCounter> {
+        crate::llm_client::policy::token_counter_for_model(self.provider_name(), self.model_name())
+    }
 
     async fn stream(
         &self,
@@ -1,4 +1,4 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use console::style;
 use dotenvy::dotenv;
 use rusty_claw::app;
@@ -76,6 +76,65 @@ struct CliArgs {
     /// Code mode prompt/dispatch format: function or text
     #[arg(long, default_value = "function", value_parser = ["function", "text"])]
     code_mode_format: String,
+    #[command(subcommand)]
+    action: Option<Action>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Action {
+    /// Bundle a session (transcript, events, task state, traces) into a .tar.gz archive
+    ExportSession {
+        /// Session to export (e.g. cli, telegram:<chat_id>, cli~my-fork)
+        session_id: String,
+        /// Archive path (defaults to rusty_claw/exports/<session>-<timestamp>.tar.gz)
+        #[arg(long, short = 'o')]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Restore a session archive as a new branch of a session
+    ImportSession {
+        archive: std::path::PathBuf,
+        /// Session the import is attached to; switch with `/branches checkout <name>`
+        #[arg(long, default_value = "cli")]
+        into: String,
+        /// Branch name (defaults to import-N)
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+async fn run_action(action: Action) -> Result<(), Box<dyn std::error::Error>> {
+    let session_manager = SessionManager::new(None, Vec::new());
+    match action {
+        Action::ExportSession { session_id, output } => {
+            let (path, manifest) = session_manager.export_session(&session_id, output).await?;
+            println!(
+                "Exported '{}' to {} ({} turns, {} trace runs, {} code-mode values).",
+                manifest.source_session_id,
+                path.display(),
+                manifest.transcript_turns,
+                manifest.trace_runs.len(),
+                manifest.code_mode_values
+            );
+        }
+        Action::ImportSession {
+            archive,
+            into,
+            name,
+        } => {
+            let (import_id, fork, imported) = session_manager
+                .import_session(&into, &archive, name.as_deref())
+                .await?;
+            println!(
+                "Imported '{}' as '{}' ({} turns, {} trace runs). Run /branches checkout {} to open it.",
+                imported.manifest.source_session_id,
+                import_id,
+                imported.transcript_turns,
+                imported.trace_runs,
+                fork.name
+            );
+        }
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -95,6 +154,10 @@ async fn async_main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
     let args = CliArgs::parse();
 
+    if let Some(action) = args.action {
+        return run_action(action).await;
+    }
+
     let is_headless = args.command.is_some();
 
     if !is_headless {
@@ -144,6 +207,34 @@ async fn async_main() -> Result<(), Box<dyn std::error::Error>> {
         code_mode_format,
     ));
     session_manager.add_output_router(Arc::new(TuiOutputRouter));
+    session_manager.set_code_indexer(bootstrap.code_indexer.clone());
+    session_manager.set_memory_extractor(bootstrap.memory_extractor.clone());
+    // Bring the code index up to date in the background; only changed files
+    // are re-embedded, so this is cheap after the first run. Outside a git
+    // repository the root would be the working directory (often $HOME), so
+    // startup indexing is skipped there.
+    if std::env::var("CLAW_INDEX_ON_STARTUP").as_deref() != Ok("0")
+        && rusty_claw::context::instructions::git_root().is_some()
+    {
+        let indexer = bootstrap.code_indexer.clone();
+        tokio::spawn(async move {
+            if let Err(e) = indexer.refresh().await {
+                tracing::warn!("Code index refresh failed: {}", e);
+            }
+        });
+    }
+    tokio::spawn(bootstrap.code_indexer.store().clone().gc_loop());
+    let trace_config = config.trace.clone().unwrap_or_default();
+    tokio::task::spawn_blocking(move || {
+        let report = rusty_claw::trace::retention::gc(&trace_config);
+        if report.runs + report.blobs > 0 {
+            tracing::info!(
+                "Trace GC removed {} runs and {} prompt blobs",
+                report.runs,
+                report.blobs
+            );
+        }
+    });
     let output = Arc::new(TuiOutput::new());
 
     // Initialize and start the scheduler
@@ -0,0 +1,264 @@
+//! `MEMORY.md` as a document of named `## ` sections.
+//!
+//! Parsing keeps every byte: the preamble (anything before the first section
+//! heading) and each section's raw text are stored as they were, so a
+//! document parsed and rendered without edits comes back unchanged. Edits
+//! only rewrite the section they name.
+
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    /// Raw heading line, newline included.
+    heading: String,
+    name: String,
+    body: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryDocument {
+    preamble: String,
+    sections: Vec<Section>,
+}
+
+/// Short content hash used for optimistic concurrency checks.
+pub fn version(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))[..12].to_string()
+}
+
+impl MemoryDocument {
+    pub fn parse(text: &str) -> Self {
+        let mut doc = Self::default();
+        let mut in_fence = false;
+        for line in text.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            if !in_fence && line.starts_with("## ") {
+                doc.sections.push(Section {
+                    heading: line.to_string(),
+                    name: line[3..].trim().to_string(),
+                    body: String::new(),
+                });
+                continue;
+            }
+            match doc.sections.last_mut() {
+                Some(section) => section.body.push_str(line),
+                None => doc.preamble.push_str(line),
+            }
+        }
+        doc
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = self.preamble.clone();
+        for section in &self.sections {
+            out.push_str(&section.heading);
+            out.push_str(&section.body);
+        }
+        out
+    }
+
+    pub fn section_names(&self) -> Vec<&str> {
+        self.sections.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    /// Body of a section, matched case-insensitively.
+    pub fn section(&self, name: &str) -> Option<&str> {
+        self.position(name)
+            .map(|idx| self.sections[idx].body.as_str())
+    }
+
+    /// Add `entry` as a bullet at the end of `section`, creating the section
+    /// at the end of the document if needed.
+    pub fn append(&mut self, section: &str, entry: &str) {
+        let idx = self.position_or_create(section);
+        let mut lines = entry.trim().lines();
+        let first = lines.next().unwrap_or("");
+        let first = first
+            .strip_prefix("- ")
+            .or_else(|| first.strip_prefix("* "))
+            .unwrap_or(first);
+        let mut bullet = format!("- {}\n", first);
+        for line in lines {
+            bullet.push_str(&format!("  {}\n", line.trim_start()));
+        }
+
+        let content = self.sections[idx].body.trim_end().to_string();
+        let body = if content.trim().is_empty() {
+            format!("\n{}", bullet)
+        } else {
+            format!("{}\n{}", content, bullet)
+        };
+        self.set_body(idx, body);
+    }
+
+    /// Replace everything under the heading of `section`, creating it if
+    /// needed.
+    pub fn replace_section(&mut self, section: &str, content: &str) {
+        let idx = self.position_or_create(section);
+        let content = content.trim();
+        let body = if content.is_empty() {
+            String::new()
+        } else {
+            format!("\n{}\n", content)
+        };
+        self.set_body(idx, body);
+    }
+
+    /// Remove the bullet in `section` whose text is `entry`, or failing an
+    /// exact match, the only bullet containing it. Returns the removed text.
+    pub fn remove_entry(&mut self, section: &str, entry: &str) -> Result<String, String> {
+        let idx = self
+            .position(section)
+            .ok_or_else(|| format!("No section '{}' in MEMORY.md", section))?;
+        let needle = entry.trim();
+        let needle = needle.strip_prefix("- ").unwrap_or(needle).trim();
+        if needle.is_empty() {
+            return Err("Entry text is empty".to_string());
+        }
+
+        let lines: Vec<&str> = self.sections[idx].body.split_inclusive('\n').collect();
+        // (first line, end line exclusive, text) of each top-level bullet.
+        let mut entries: Vec<(usize, usize, String)> = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let marker = ["- ", "* ", "+ "]
+                .iter()
+                .find_map(|marker| line.strip_prefix(marker));
+            if let Some(text) = marker {
+                entries.push((i, i + 1, text.trim().to_string()));
+            } else if let Some(last) = entries.last_mut() {
+                let continues =
+                    last.1 == i && line.starts_with([' ', '\t']) && !line.trim().is_empty();
+                if continues {
+                    last.1 = i + 1;
+                    last.2.push(' ');
+                    last.2.push_str(line.trim());
+                }
+            }
+        }
+
+        let exact: Vec<&(usize, usize, String)> = entries
+            .iter()
+            .filter(|(_, _, text)| text == needle)
+            .collect();
+        let lowered = needle.to_lowercase();
+        let matches = if exact.is_empty() {
+            entries
+                .iter()
+                .filter(|(_, _, text)| text.to_lowercase().contains(&lowered))
+                .collect()
+        } else {
+            exact
+        };
+        let (start, end, text) = match matches.as_slice() {
+            [single] => (*single).clone(),
+            [] => return Err(format!("No entry matching '{}' in '{}'", needle, section)),
+            _ => {
+                return Err(format!(
+                    "{} entries in '{}' match '{}'; quote the whole entry",
+                    matches.len(),
+                    section,
+                    needle
+                ))
+            }
+        };
+        let body: String = lines[..start].concat() + &lines[end..].concat();
+        self.sections[idx].body = body;
+        Ok(text)
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        let name = name.trim();
+        self.sections
+            .iter()
+            .position(|section| section.name.eq_ignore_ascii_case(name))
+    }
+
+    fn position_or_create(&mut self, name: &str) -> usize {
+        if let Some(idx) = self.position(name) {
+            return idx;
+        }
+        // Keep a blank line between the previous content and the heading.
+        let last = match self.sections.last_mut() {
+            Some(section) if section.body.is_empty() => {
+                if !section.heading.ends_with('\n') {
+                    section.heading.push('\n');
+                }
+                section.body.push('\n');
+                &mut section.body
+            }
+            Some(section) => &mut section.body,
+            None => &mut self.preamble,
+        };
+        if !last.trim().is_empty() {
+            let content_len = last.trim_end().len();
+            last.truncate(content_len);
+            last.push_str("\n\n");
+        }
+        self.sections.push(Section {
+            heading: format!("## {}\n", name.trim()),
+            name: name.trim().to_string(),
+            body: String::new(),
+        });
+        self.sections.len() - 1
+    }
+
+    /// Set a section body, keeping a blank line before the next heading.
+    fn set_body(&mut self, idx: usize, mut body: String) {
+        if idx + 1 < self.sections.len() {
+            body.push('\n');
+        }
+        self.sections[idx].body = body;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "# Memory\n\nIntro text.\n\n## Preferences\n- Likes tabs.\n- Answers in English,\n  unless asked otherwise.\n\n## Notes\n```\n## not a heading\n```\n* loose bullet\n### Sub\ntext";
+
+    #[test]
+    fn test_round_trip_and_section_edits() {
+        let doc = MemoryDocument::parse(SAMPLE);
+        assert_eq!(doc.render(), SAMPLE);
+        assert_eq!(doc.section_names(), vec!["Preferences", "Notes"]);
+        assert_eq!(MemoryDocument::parse("").render(), "");
+        assert_eq!(
+            MemoryDocument::parse("no sections\r\nat all").render(),
+            "no sections\r\nat all"
+        );
+
+        let mut doc = MemoryDocument::parse(SAMPLE);
+        doc.append("preferences", "Prefers short commits.");
+        doc.append("Decisions", "- Use SQLite for state.");
+        let rendered = doc.render();
+        assert!(
+            rendered.contains("  unless asked otherwise.\n- Prefers short commits.\n\n## Notes\n")
+        );
+        assert!(rendered.ends_with("### Sub\ntext\n\n## Decisions\n\n- Use SQLite for state.\n"));
+        // Untouched sections survive byte for byte.
+        assert!(rendered.contains("## Notes\n```\n## not a heading\n```\n* loose bullet\n"));
+
+        assert_eq!(
+            doc.remove_entry("Preferences", "English").unwrap(),
+            "Answers in English, unless asked otherwise."
+        );
+        assert!(doc.remove_entry("Preferences", "zzz").is_err());
+        doc.append("Preferences", "Likes tabs in Go.");
+        assert!(doc
+            .remove_entry("Preferences", "likes tabs")
+            .unwrap_err()
+            .contains("2 entries"));
+        assert_eq!(
+            doc.remove_entry("Preferences", "- Likes tabs.").unwrap(),
+            "Likes tabs."
+        );
+
+        doc.replace_section("Notes", "Fresh notes.");
+        assert_eq!(doc.section("notes"), Some("\nFresh notes.\n\n"));
+        assert_ne!(version(SAMPLE), version(&doc.render()));
+    }
+}
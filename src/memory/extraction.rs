@@ -0,0 +1,696 @@
+//! Distils durable facts from finished turns into long-term memory.
+//!
+//! After each committed run [`MemoryExtractionExtension`] asks the model for
+//! preferences, conventions and decisions worth keeping across sessions.
+//! Candidates that repeat a stored memory, `MEMORY.md` or an earlier
+//! candidate are dropped; the rest wait in `memory_candidates.json` until
+//! `/memory review` approves them into the vector store, or the matching
+//! `MEMORY.md` section when there is none (`[memory] auto_approve` skips the
+//! review). Each session only reviews its own candidates, and a chat's
+//! preferences stay in its session namespace unless the local user
+//! approves them.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{MemoryConfig, WorkspaceMemory};
+use crate::context::{Message, Part};
+use crate::core::extensions::{ExecutionExtension, ExtensionDecision, FinishDecision, PromptDraft};
+use crate::llm_client::{LlmClient, StreamEvent};
+use crate::rag::VectorStore;
+use crate::tools::protocol::ToolExecutionEnvelope;
+use crate::tools::Tool;
+
+/// Most candidates taken from one turn.
+const MAX_CANDIDATES_PER_TURN: usize = 5;
+/// Cosine similarity above which a candidate restates a stored memory.
+const DUPLICATE_SIMILARITY: f32 = 0.9;
+/// Word overlap above which a candidate restates an earlier candidate.
+const DUPLICATE_OVERLAP: f32 = 0.8;
+/// Decided candidates kept for dedupe and provenance.
+const MAX_DECIDED: usize = 500;
+const REQUEST_EXCERPT_CHARS: usize = 300;
+const RESPONSE_EXCERPT_CHARS: usize = 4_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryKind {
+    Preference,
+    Convention,
+    Decision,
+    Fact,
+}
+
+impl MemoryKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Preference => "preference",
+            Self::Convention => "convention",
+            Self::Decision => "decision",
+            Self::Fact => "fact",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "preference" => Some(Self::Preference),
+            "convention" => Some(Self::Convention),
+            "decision" => Some(Self::Decision),
+            "fact" => Some(Self::Fact),
+            _ => None,
+        }
+    }
+
+    /// `MEMORY.md` section for memories of this kind.
+    fn section(self) -> &'static str {
+        match self {
+            Self::Preference => "Preferences",
+            Self::Convention => "Conventions",
+            Self::Decision => "Decisions",
+            Self::Fact => "Facts",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CandidateStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryCandidate {
+    pub id: String,
+    pub kind: MemoryKind,
+    pub text: String,
+    pub status: CandidateStatus,
+    /// Session and request the fact was extracted from.
+    pub session_id: String,
+    pub request: String,
+    pub created_at: i64,
+    /// Vector store chunk holding the memory, once approved.
+    #[serde(default)]
+    pub chunk_id: Option<i64>,
+}
+
+impl MemoryCandidate {
+    /// Promoted preferences follow the user everywhere, other preferences
+    /// stay with the session they came from; the rest belong to the project
+    /// they were learnt in.
+    fn namespace(&self, promote: bool) -> String {
+        match self.kind {
+            MemoryKind::Preference if promote => crate::rag::DEFAULT_NAMESPACE.to_string(),
+            MemoryKind::Preference => crate::tools::memory::session_namespace(&self.session_id),
+            _ => crate::tools::memory::project_namespace(),
+        }
+    }
+
+    /// `source` of the stored chunk, which is what search results cite.
+    fn provenance(&self) -> String {
+        let date = chrono::DateTime::from_timestamp(self.created_at, 0)
+            .map(|ts| ts.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        format!(
+            "{} from session {} on {} ({})",
+            self.kind.as_str(),
+            self.session_id,
+            date,
+            self.id
+        )
+    }
+}
+
+pub struct MemoryExtractor {
+    path: PathBuf,
+    candidates: Mutex<Vec<MemoryCandidate>>,
+    store: Option<Arc<VectorStore>>,
+    workspace: WorkspaceMemory,
+    auto_extract: bool,
+    auto_approve: bool,
+}
+
+impl MemoryExtractor {
+    /// Candidates persisted at `path`; approved memories go to `store`.
+    pub fn open(
+        path: PathBuf,
+        store: Option<Arc<VectorStore>>,
+        workspace_dir: &str,
+        config: &MemoryConfig,
+    ) -> Self {
+        let candidates = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            candidates: Mutex::new(candidates),
+            store,
+            workspace: WorkspaceMemory::new(workspace_dir),
+            auto_extract: config.auto_extract.unwrap_or(true),
+            auto_approve: config.auto_approve.unwrap_or(false),
+        }
+    }
+
+    /// Whether sessions should run extraction after each turn.
+    pub fn enabled(&self) -> bool {
+        self.auto_extract
+    }
+
+    /// Candidates of `session_id` awaiting review.
+    pub fn pending(&self, session_id: &str) -> Vec<MemoryCandidate> {
+        self.candidates
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|candidate| {
+                candidate.status == CandidateStatus::Pending && candidate.session_id == session_id
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Ask `llm` for durable facts in one finished turn and queue the new
+    /// ones. Returns the candidates added; with `auto_approve` they are
+    /// approved right away, promoted when `promote` is set.
+    pub async fn extract(
+        &self,
+        llm: &dyn LlmClient,
+        session_id: &str,
+        request: &str,
+        response: &str,
+        promote: bool,
+    ) -> Result<Vec<MemoryCandidate>, String> {
+        let request = crate::security::redact_secrets(request.trim());
+        let response = crate::security::redact_secrets(response.trim());
+        if request.is_empty() || request.starts_with('/') {
+            return Ok(Vec::new());
+        }
+        let reply = complete(llm, &extraction_prompt(&request, &response)).await?;
+
+        let mut added = Vec::new();
+        for (kind, text) in parse_candidates(&reply) {
+            if self.is_duplicate(&text, &added).await {
+                continue;
+            }
+            added.push(MemoryCandidate {
+                id: uuid::Uuid::new_v4().simple().to_string()[..8].to_string(),
+                kind,
+                text,
+                status: CandidateStatus::Pending,
+                session_id: session_id.to_string(),
+                request: request.chars().take(REQUEST_EXCERPT_CHARS).collect(),
+                created_at: chrono::Utc::now().timestamp(),
+                chunk_id: None,
+            });
+        }
+        if added.is_empty() {
+            return Ok(added);
+        }
+        self.candidates
+            .lock()
+            .unwrap()
+            .extend(added.iter().cloned());
+        self.save();
+
+        if self.auto_approve {
+            for candidate in &added {
+                if let Err(e) = self.approve(&candidate.id, session_id, promote).await {
+                    tracing::warn!("[Memory] Auto-approve of {} failed: {}", candidate.id, e);
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    /// Store a pending candidate of `session_id` in the vector store, or as
+    /// a bullet in the matching `MEMORY.md` section when there is no store.
+    /// `promote` (set for the local user) lets a preference into the shared
+    /// `global` namespace.
+    pub async fn approve(
+        &self,
+        id: &str,
+        session_id: &str,
+        promote: bool,
+    ) -> Result<MemoryCandidate, String> {
+        let candidate = self.find_pending(id, session_id)?;
+        let Some(store) = &self.store else {
+            if candidate.kind == MemoryKind::Preference && !promote {
+                return Err(
+                    "Keeping a preference to this session needs the knowledge base".to_string(),
+                );
+            }
+            self.workspace
+                .append(
+                    candidate.kind.section(),
+                    &format!("{} _({})_", candidate.text, candidate.provenance()),
+                    None,
+                )
+                .await
+                .map_err(|e| format!("Failed to update MEMORY.md: {}", e))?;
+            return self.decide(id, CandidateStatus::Approved, None);
+        };
+        let chunk_id = store
+            .insert_chunk_in(
+                candidate.namespace(promote),
+                candidate.text.clone(),
+                candidate.provenance(),
+            )
+            .await
+            .map_err(|e| format!("Failed to store memory: {}", e))?;
+        self.decide(id, CandidateStatus::Approved, Some(chunk_id))
+    }
+
+    /// Drop a pending candidate. It is kept, rejected, so the same fact is
+    /// not proposed again.
+    pub fn reject(&self, id: &str, session_id: &str) -> Result<MemoryCandidate, String> {
+        self.find_pending(id, session_id)?;
+        self.decide(id, CandidateStatus::Rejected, None)
+    }
+
+    fn find_pending(&self, id: &str, session_id: &str) -> Result<MemoryCandidate, String> {
+        self.candidates
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|candidate| {
+                candidate.id == id
+                    && candidate.status == CandidateStatus::Pending
+                    && candidate.session_id == session_id
+            })
+            .cloned()
+            .ok_or_else(|| format!("No pending memory candidate '{}'", id))
+    }
+
+    fn decide(
+        &self,
+        id: &str,
+        status: CandidateStatus,
+        chunk_id: Option<i64>,
+    ) -> Result<MemoryCandidate, String> {
+        let decided = {
+            let mut candidates = self.candidates.lock().unwrap();
+            let candidate = candidates
+                .iter_mut()
+                .find(|candidate| candidate.id == id)
+                .ok_or_else(|| format!("No memory candidate '{}'", id))?;
+            candidate.status = status;
+            candidate.chunk_id = chunk_id;
+            let decided = candidate.clone();
+
+            let decided_count = candidates
+                .iter()
+                .filter(|candidate| candidate.status != CandidateStatus::Pending)
+                .count();
+            let mut excess = decided_count.saturating_sub(MAX_DECIDED);
+            candidates.retain(|candidate| {
+                if excess > 0 && candidate.status != CandidateStatus::Pending {
+                    excess -= 1;
+                    return false;
+                }
+                true
+            });
+            decided
+        };
+        self.save();
+        Ok(decided)
+    }
+
+    /// Whether `text` restates an earlier candidate (including rejected
+    /// ones), `MEMORY.md` or a stored memory.
+    async fn is_duplicate(&self, text: &str, batch: &[MemoryCandidate]) -> bool {
+        let words = word_set(text);
+        let known = self.candidates.lock().unwrap().clone();
+        if known
+            .iter()
+            .chain(batch)
+            .any(|candidate| overlap(&words, &word_set(&candidate.text)) >= DUPLICATE_OVERLAP)
+        {
+            return true;
+        }
+        if let Ok(memory) = self.workspace.read_memory().await {
+            let normalized = normalize(text);
+            if !normalized.is_empty() && normalize(&memory).contains(&normalized) {
+                return true;
+            }
+        }
+        let Some(store) = &self.store else {
+            return false;
+        };
+        let namespaces = vec![
+            crate::rag::DEFAULT_NAMESPACE.to_string(),
+            crate::tools::memory::project_namespace(),
+        ];
+        match store.nearest(text, namespaces).await {
+            Ok(Some((_, similarity, _))) => similarity >= DUPLICATE_SIMILARITY,
+            Ok(None) => false,
+            Err(e) => {
+                tracing::warn!("[Memory] Duplicate check failed: {}", e);
+                false
+            }
+        }
+    }
+
+    fn save(&self) {
+        let candidates = self.candidates.lock().unwrap().clone();
+        if let Some(parent) = self.path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        match serde_json::to_string_pretty(&candidates) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(&self.path, json) {
+                    tracing::warn!("[Memory] Failed to save candidates: {}", e);
+                }
+            }
+            Err(e) => tracing::warn!("[Memory] Failed to serialize candidates: {}", e),
+        }
+    }
+}
+
+fn extraction_prompt(request: &str, response: &str) -> String {
+    format!(
+        "You maintain an assistant's long-term memory. Read one finished exchange and list the facts worth remembering in future sessions:\n\
+         - preference: how the user likes things done (style, tools, language, workflow)\n\
+         - convention: rules of this project (naming, layout, commands, testing)\n\
+         - decision: a choice that was made, with its reason\n\
+         - fact: a stable fact about the project or environment\n\n\
+         Skip anything only relevant to this task, anything that can be read from the code, guesses, and secrets. \
+         Write each fact as one self-contained sentence. Most exchanges contain nothing worth keeping.\n\n\
+         Reply with only a JSON array, e.g. [{{\"kind\": \"preference\", \"text\": \"The user prefers tabs over spaces.\"}}], or [] if there is nothing.\n\n\
+         <request>\n{}\n</request>\n\n<response>\n{}\n</response>",
+        request,
+        response.chars().take(RESPONSE_EXCERPT_CHARS).collect::<String>()
+    )
+}
+
+/// Collect a plain-text completion of `prompt`.
+async fn complete(llm: &dyn LlmClient, prompt: &str) -> Result<String, String> {
+    let messages = vec![Message {
+        role: "user".to_string(),
+        parts: vec![Part {
+            text: Some(prompt.to_string()),
+            function_call: None,
+            function_response: None,
+            thought_signature: None,
+            file_data: None,
+        }],
+    }];
+    let collect = async {
+        let mut rx = llm
+            .stream(messages, None, vec![])
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut reply = String::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                StreamEvent::Text(t) => reply.push_str(&t),
+                StreamEvent::Error(e) => return Err(e),
+                StreamEvent::Done => break,
+                _ => {}
+            }
+        }
+        Ok(reply)
+    };
+    tokio::time::timeout(Duration::from_secs(60), collect)
+        .await
+        .map_err(|_| "memory extraction timed out".to_string())?
+}
+
+#[derive(Deserialize)]
+struct RawCandidate {
+    kind: String,
+    text: String,
+}
+
+/// The JSON array in a model reply, tolerating code fences and prose.
+fn parse_candidates(reply: &str) -> Vec<(MemoryKind, String)> {
+    let (Some(start), Some(end)) = (reply.find('['), reply.rfind(']')) else {
+        return Vec::new();
+    };
+    if end < start {
+        return Vec::new();
+    }
+    let raw: Vec<RawCandidate> = serde_json::from_str(&reply[start..=end]).unwrap_or_default();
+    raw.into_iter()
+        .filter_map(|candidate| {
+            let text = candidate.text.trim();
+            if text.len() < 8 {
+                return None;
+            }
+            Some((MemoryKind::parse(&candidate.kind)?, text.to_string()))
+        })
+        .take(MAX_CANDIDATES_PER_TURN)
+        .collect()
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn word_set(text: &str) -> HashSet<String> {
+    normalize(text).split(' ').map(str::to_string).collect()
+}
+
+/// Jaccard similarity of two word sets.
+fn overlap(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+/// Runs [`MemoryExtractor::extract`] in the background after every
+/// committed run of a session.
+pub struct MemoryExtractionExtension {
+    session_id: String,
+    llm: Arc<dyn LlmClient>,
+    extractor: Arc<MemoryExtractor>,
+    /// Whether the session belongs to the local user, whose auto-approved
+    /// preferences are promoted.
+    local: bool,
+    request: Mutex<Option<String>>,
+}
+
+impl MemoryExtractionExtension {
+    pub fn new(
+        session_id: &str,
+        llm: Arc<dyn LlmClient>,
+        extractor: Arc<MemoryExtractor>,
+        local: bool,
+    ) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            llm,
+            extractor,
+            local,
+            request: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionExtension for MemoryExtractionExtension {
+    async fn before_turn_start(&self, input: &str) -> ExtensionDecision {
+        *self.request.lock().unwrap() = Some(input.to_string());
+        ExtensionDecision::Continue
+    }
+
+    async fn before_prompt_build(&self, draft: PromptDraft) -> PromptDraft {
+        draft
+    }
+
+    async fn before_tool_resolution(&self, tools: Vec<Arc<dyn Tool>>) -> Vec<Arc<dyn Tool>> {
+        tools
+    }
+
+    async fn after_tool_result(&self, _result: &ToolExecutionEnvelope) {}
+
+    async fn before_finish(&self) -> FinishDecision {
+        FinishDecision::Allow
+    }
+
+    async fn on_finish_committed(&self, summary: &str) {
+        let Some(request) = self.request.lock().unwrap().take() else {
+            return;
+        };
+        // Off the turn's critical path: the user should not wait for it.
+        let llm = self.llm.clone();
+        let extractor = self.extractor.clone();
+        let session_id = self.session_id.clone();
+        let local = self.local;
+        let response = summary.to_string();
+        tokio::spawn(async move {
+            match extractor
+                .extract(llm.as_ref(), &session_id, &request, &response, local)
+                .await
+            {
+                Ok(added) if !added.is_empty() => tracing::info!(
+                    "[Memory] {} new memory candidates from {}; see /memory review",
+                    added.len(),
+                    session_id
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("[Memory] Extraction failed: {}", e),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::{LlmCapabilities, LlmError};
+    use tokio::sync::mpsc;
+
+    struct ScriptedLlm(String);
+
+    #[async_trait]
+    impl LlmClient for ScriptedLlm {
+        fn model_name(&self) -> &str {
+            "scripted"
+        }
+
+        fn provider_name(&self) -> &str {
+            "test"
+        }
+
+        fn capabilities(&self) -> LlmCapabilities {
+            LlmCapabilities {
+                function_tools: false,
+                custom_tools: false,
+                parallel_tool_calls: false,
+                supports_code_mode: false,
+            }
+        }
+
+        async fn stream(
+            &self,
+            _messages: Vec<Message>,
+            _system_instruction: Option<Message>,
+            _tools: Vec<Arc<dyn Tool>>,
+        ) -> Result<mpsc::Receiver<StreamEvent>, LlmError> {
+            let (tx, rx) = mpsc::channel(4);
+            let _ = tx.try_send(StreamEvent::Text(self.0.clone()));
+            let _ = tx.try_send(StreamEvent::Done);
+            Ok(rx)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extract_dedupes_and_reviews_candidates() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("MEMORY.md"),
+            "- The project uses cargo nextest for tests.\n",
+        )
+        .unwrap();
+        let path = dir.path().join("candidates.json");
+        let extractor = MemoryExtractor::open(
+            path.clone(),
+            None,
+            dir.path().to_str().unwrap(),
+            &MemoryConfig::default(),
+        );
+        let llm = ScriptedLlm(
+            "```json\n[\
+             {\"kind\": \"preference\", \"text\": \"The user prefers short commit messages.\"},\
+             {\"kind\": \"convention\", \"text\": \"The project uses cargo nextest for tests.\"},\
+             {\"kind\": \"preference\", \"text\": \"The user prefers short commit messages!\"},\
+             {\"kind\": \"mood\", \"text\": \"The user seems happy today.\"}\
+             ]\n```"
+                .to_string(),
+        );
+
+        let added = extractor
+            .extract(&llm, "cli", "commit this please", "Committed.", true)
+            .await
+            .unwrap();
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].kind, MemoryKind::Preference);
+        assert_eq!(added[0].request, "commit this please");
+
+        // The same fact is not proposed twice, and commands are ignored.
+        assert!(extractor
+            .extract(&llm, "cli", "commit again", "Done.", true)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(extractor
+            .extract(&llm, "cli", "/help", "", true)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let id = added[0].id.clone();
+        // Without a vector store approved memories land in MEMORY.md.
+        extractor.approve(&id, "cli", true).await.unwrap();
+        let memory = std::fs::read_to_string(dir.path().join("MEMORY.md")).unwrap();
+        assert!(memory.contains("## Preferences\n\n- The user prefers short commit messages. _(preference from session cli"));
+        assert!(extractor.pending("cli").is_empty());
+        assert!(extractor.reject(&id, "cli").is_err());
+
+        let reopened = MemoryExtractor::open(
+            path,
+            None,
+            dir.path().to_str().unwrap(),
+            &MemoryConfig::default(),
+        );
+        assert!(reopened.pending("cli").is_empty());
+        assert!(reopened
+            .extract(&llm, "cli", "commit once more", "Done.", true)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+    #[tokio::test]
+    async fn test_candidates_stay_with_their_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(
+            VectorStore::open(
+                &dir.path().join("kb.db"),
+                Arc::new(crate::rag::embedder::HashedWordsEmbedder),
+            )
+            .unwrap(),
+        );
+        let extractor = MemoryExtractor::open(
+            dir.path().join("candidates.json"),
+            Some(store.clone()),
+            dir.path().to_str().unwrap(),
+            &MemoryConfig::default(),
+        );
+        let llm = ScriptedLlm(
+            "[{\"kind\": \"preference\", \"text\": \"The user prefers replies in French.\"}]"
+                .to_string(),
+        );
+        let added = extractor
+            .extract(&llm, "telegram:1", "answer in french", "D'accord.", false)
+            .await
+            .unwrap();
+        let id = added[0].id.clone();
+
+        // Other sessions can neither see nor decide it.
+        assert!(extractor.pending("cli").is_empty());
+        assert!(extractor.approve(&id, "cli", true).await.is_err());
+        assert!(extractor.reject(&id, "cli").is_err());
+        assert_eq!(extractor.pending("telegram:1").len(), 1);
+
+        // Approved from the chat, the preference stays in its namespace.
+        extractor.approve(&id, "telegram:1", false).await.unwrap();
+        let namespaces: Vec<String> = store
+            .namespaces()
+            .into_iter()
+            .map(|(namespace, _)| namespace)
+            .collect();
+        assert_eq!(namespaces, vec!["session:telegram:1".to_string()]);
+    }
+}
@@ -0,0 +1,235 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+use tokio::fs;
+
+pub mod document;
+pub mod extraction;
+pub mod retrieval;
+
+pub use document::MemoryDocument;
+
+/// `[memory]` in `config.toml`.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct MemoryConfig {
+    /// Extract memory candidates after each finished run (default true).
+    pub auto_extract: Option<bool>,
+    /// Store candidates without `/memory review` (default false).
+    pub auto_approve: Option<bool>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MemoryError {
+    #[error("MEMORY.md changed since version {expected} (now {actual}); read it again and retry")]
+    Conflict { expected: String, actual: String },
+    #[error("{0}")]
+    Edit(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// `MEMORY.md` in a workspace. Every write holds an exclusive lock on
+/// `MEMORY.md.lock` and replaces the file atomically, so concurrent sessions
+/// never interleave; callers that read first can pass the version they saw
+/// to fail instead of overwriting someone else's edit.
+pub struct WorkspaceMemory {
+    memory_file_path: PathBuf,
+}
+
+impl WorkspaceMemory {
+    pub fn new(workspace_dir: &str) -> Self {
+        let mut path = PathBuf::from(workspace_dir);
+        path.push("MEMORY.md");
+        Self {
+            memory_file_path: path,
+        }
+    }
+
+    pub async fn read_memory(&self) -> std::io::Result<String> {
+        if self.memory_file_path.exists() {
+            fs::read_to_string(&self.memory_file_path).await
+        } else {
+            Ok(String::new())
+        }
+    }
+
+    /// Content and its [`document::version`].
+    pub async fn read_versioned(&self) -> std::io::Result<(String, String)> {
+        let content = self.read_memory().await?;
+        let version = document::version(&content);
+        Ok((content, version))
+    }
+
+    pub async fn write_memory(&self, content: &str) -> std::io::Result<()> {
+        let content = content.to_string();
+        self.edit(None, move |doc| {
+            *doc = MemoryDocument::parse(&content);
+            Ok(())
+        })
+        .await
+        .map(|_| ())
+        .map_err(|e| match e {
+            MemoryError::Io(e) => e,
+            other => std::io::Error::other(other.to_string()),
+        })
+    }
+
+    /// Add a bullet to `section`. Returns the new version.
+    pub async fn append(
+        &self,
+        section: &str,
+        entry: &str,
+        expected_version: Option<&str>,
+    ) -> Result<String, MemoryError> {
+        let (section, entry) = (section.to_string(), entry.to_string());
+        self.edit(expected_version, move |doc| {
+            doc.append(&section, &entry);
+            Ok(())
+        })
+        .await
+        .map(|(_, version)| version)
+    }
+
+    /// Replace the body of `section`. Returns the new version.
+    pub async fn replace_section(
+        &self,
+        section: &str,
+        content: &str,
+        expected_version: Option<&str>,
+    ) -> Result<String, MemoryError> {
+        let (section, content) = (section.to_string(), content.to_string());
+        self.edit(expected_version, move |doc| {
+            doc.replace_section(&section, &content);
+            Ok(())
+        })
+        .await
+        .map(|(_, version)| version)
+    }
+
+    /// Remove one bullet from `section`. Returns the removed text and the
+    /// new version.
+    pub async fn remove_entry(
+        &self,
+        section: &str,
+        entry: &str,
+        expected_version: Option<&str>,
+    ) -> Result<(String, String), MemoryError> {
+        let (section, entry) = (section.to_string(), entry.to_string());
+        self.edit(expected_version, move |doc| {
+            doc.remove_entry(&section, &entry)
+        })
+        .await
+    }
+
+    /// Apply `op` to the current document under the file lock and write
+    /// the result. Fails with [`MemoryError::Conflict`] when
+    /// `expected_version` is given and no longer current.
+    pub async fn edit<T: Send + 'static>(
+        &self,
+        expected_version: Option<&str>,
+        op: impl FnOnce(&mut MemoryDocument) -> Result<T, String> + Send + 'static,
+    ) -> Result<(T, String), MemoryError> {
+        let path = self.memory_file_path.clone();
+        let expected_version = expected_version.map(str::to_string);
+        tokio::task::spawn_blocking(move || {
+            let lock = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(path.with_extension("md.lock"))?;
+            lock.lock()?;
+
+            let current = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e.into()),
+            };
+            let actual = document::version(&current);
+            if let Some(expected) = expected_version {
+                if expected != actual {
+                    return Err(MemoryError::Conflict { expected, actual });
+                }
+            }
+            let mut doc = MemoryDocument::parse(&current);
+            let result = op(&mut doc).map_err(MemoryError::Edit)?;
+            let rendered = doc.render();
+            let tmp = path.with_extension("md.tmp");
+            std::fs::write(&tmp, &rendered)?;
+            std::fs::rename(&tmp, &path)?;
+            Ok((result, document::version(&rendered)))
+        })
+        .await
+        .map_err(|e| MemoryError::Io(std::io::Error::other(e)))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_workspace_memory() {
+        let dir = tempdir().unwrap();
+        let mem = WorkspaceMemory::new(dir.path().to_str().unwrap());
+
+        let empty = mem.read_memory().await.unwrap();
+        assert_eq!(empty, "");
+
+        mem.write_memory("Hello World").await.unwrap();
+
+        let read_back = mem.read_memory().await.unwrap();
+        assert_eq!(read_back, "Hello World");
+    }
+
+    #[tokio::test]
+    async fn test_regression_workspace_memory_overwrite() {
+        let dir = tempdir().unwrap();
+        let mem = WorkspaceMemory::new(dir.path().to_str().unwrap());
+
+        mem.write_memory("v1").await.unwrap();
+        mem.write_memory("v2").await.unwrap();
+
+        let read_back = mem.read_memory().await.unwrap();
+        assert_eq!(read_back, "v2");
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_appends_and_version_conflicts() {
+        let dir = tempdir().unwrap();
+        let mem = std::sync::Arc::new(WorkspaceMemory::new(dir.path().to_str().unwrap()));
+        mem.write_memory("# Memory\n\nKept as is.\n").await.unwrap();
+
+        let mut handles = Vec::new();
+        for i in 0..8 {
+            let mem = mem.clone();
+            handles.push(tokio::spawn(async move {
+                mem.append("Facts", &format!("fact {}", i), None)
+                    .await
+                    .unwrap()
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        let (content, version) = mem.read_versioned().await.unwrap();
+        assert!(content.starts_with("# Memory\n\nKept as is.\n\n## Facts\n"));
+        for i in 0..8 {
+            assert!(content.contains(&format!("- fact {}\n", i)));
+        }
+
+        let stale = version.clone();
+        let version = mem
+            .replace_section("Facts", "- only fact", Some(&version))
+            .await
+            .unwrap();
+        assert!(matches!(
+            mem.append("Facts", "late", Some(&stale)).await,
+            Err(MemoryError::Conflict { .. })
+        ));
+        let (removed, _) = mem
+            .remove_entry("Facts", "only", Some(&version))
+            .await
+            .unwrap();
+        assert_eq!(removed, "only fact");
+    }
+}
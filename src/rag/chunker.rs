@@ -0,0 +1,316 @@
+//! Syntax-aware chunking for the codebase index.
+//!
+//! No parser is involved: top-level items are found from indentation and
+//! brace depth (C-family and Rust), indentation alone (Python), or headings
+//! (Markdown). Good enough to keep a function, an `impl` or a doc section in
+//! one chunk, which is what retrieval quality mostly depends on.
+
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Units longer than this are split at nested items, then into windows.
+pub const MAX_CHUNK_LINES: usize = 120;
+/// Adjacent units shorter than this (imports, constants) are merged.
+const MIN_CHUNK_LINES: usize = 8;
+/// Window size for files with no recognised structure.
+const WINDOW_LINES: usize = 60;
+
+static BRACE_ITEM_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^\s*(?:(?:pub(?:\([^)]*\))?|export|default|async|unsafe|const|static|public|private|protected|abstract|final|extern(?:\s+\x22C\x22)?|inline|virtual|override)\s+)*(?:fn|impl|struct|enum|trait|mod|macro_rules!|union|type|func|function|class|interface|namespace|object|record|def)\b",
+    )
+    .expect("valid item regex")
+});
+
+static PYTHON_ITEM_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:async\s+def|def|class)\b").expect("valid python regex"));
+
+static HEADING_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^#{1,6}\s+\S").expect("valid heading regex"));
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeChunk {
+    /// 1-based, inclusive.
+    pub start_line: usize,
+    pub end_line: usize,
+    /// First line of the unit (signature or heading), if it has one.
+    pub symbol: Option<String>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Braces,
+    Python,
+    Markdown,
+    Plain,
+}
+
+fn syntax_for(path: &Path) -> Syntax {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "cxx" | "go" | "java" | "kt" | "kts" | "js"
+        | "jsx" | "mjs" | "ts" | "tsx" | "cs" | "swift" | "scala" | "php" | "dart" | "zig" => {
+            Syntax::Braces
+        }
+        "py" | "pyi" => Syntax::Python,
+        "md" | "markdown" | "mdx" => Syntax::Markdown,
+        _ => Syntax::Plain,
+    }
+}
+
+/// Whether the indexer should look at a file with this path at all.
+pub fn is_indexable(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    syntax_for(path) != Syntax::Plain
+        || matches!(
+            ext.as_str(),
+            "toml" | "yaml" | "yml" | "sh" | "bash" | "sql" | "proto" | "txt" | "rb" | "lua"
+        )
+}
+
+pub fn chunk_file(path: &Path, content: &str) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.iter().all(|line| line.trim().is_empty()) {
+        return Vec::new();
+    }
+    let starts = match syntax_for(path) {
+        Syntax::Braces => brace_item_starts(&lines, 0..lines.len(), 0),
+        Syntax::Python => lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| PYTHON_ITEM_RE.is_match(line))
+            .map(|(idx, _)| idx)
+            .collect(),
+        Syntax::Markdown => {
+            let mut in_fence = false;
+            let mut starts = Vec::new();
+            for (idx, line) in lines.iter().enumerate() {
+                if line.trim_start().starts_with("```") {
+                    in_fence = !in_fence;
+                } else if !in_fence && HEADING_RE.is_match(line) {
+                    starts.push(idx);
+                }
+            }
+            starts
+        }
+        Syntax::Plain => Vec::new(),
+    };
+
+    let mut units = split_at(&lines, 0..lines.len(), &starts, true);
+    units = merge_small(units);
+    let mut chunks = Vec::new();
+    for (unit_start, unit_end) in units {
+        let mut symbol = None;
+        for (start, end) in split_large(&lines, unit_start, unit_end, syntax_for(path)) {
+            let text = lines[start..end].join("\n");
+            if text.trim().is_empty() {
+                continue;
+            }
+            // A window cut mid-item has no signature of its own; label it
+            // with the one it continues.
+            let own = symbol_for(&lines[start..end]);
+            let starts_item = own.as_deref().is_some_and(is_item_line);
+            if start == unit_start || starts_item || symbol.is_none() {
+                symbol = own;
+            }
+            chunks.push(CodeChunk {
+                start_line: start + 1,
+                end_line: end,
+                symbol: if start == unit_start || starts_item {
+                    symbol.clone()
+                } else {
+                    symbol.as_ref().map(|s| format!("{} (continued)", s))
+                },
+                content: text,
+            });
+        }
+    }
+    chunks
+}
+
+/// Line indexes in `range` where an item starts at brace depth `depth`,
+/// pulled back over attached doc comments and attributes.
+fn brace_item_starts(lines: &[&str], range: std::ops::Range<usize>, depth: i64) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut current = 0i64;
+    for idx in range.clone() {
+        if current == depth && BRACE_ITEM_RE.is_match(lines[idx]) {
+            let mut start = idx;
+            while start > range.start && is_attached(lines[start - 1]) {
+                start -= 1;
+            }
+            starts.push(start);
+        }
+        current += brace_delta(lines[idx]);
+    }
+    starts
+}
+
+fn is_attached(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    ["///", "//!", "//", "/**", "*", "#[", "#![", "@"]
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix))
+}
+
+/// Net `{` minus `}` on a line, ignoring string literals, char literals
+/// and `//` comments.
+fn brace_delta(line: &str) -> i64 {
+    let line = line.replace("'{'", "").replace("'}'", "");
+    let mut delta = 0;
+    let mut in_string = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '/' if !in_string && chars.peek() == Some(&'/') => break,
+            '{' if !in_string => delta += 1,
+            '}' if !in_string => delta -= 1,
+            _ => {}
+        }
+    }
+    delta
+}
+
+/// Half-open `(start, end)` units of `range`, split at `starts`. A preamble
+/// before the first start becomes its own unit when `keep_preamble`, and is
+/// otherwise folded into the first unit (an `impl` header into its first
+/// method).
+fn split_at(
+    lines: &[&str],
+    range: std::ops::Range<usize>,
+    starts: &[usize],
+    keep_preamble: bool,
+) -> Vec<(usize, usize)> {
+    let mut bounds: Vec<usize> = starts
+        .iter()
+        .copied()
+        .filter(|idx| range.contains(idx))
+        .collect();
+    bounds.dedup();
+    if bounds.first() != Some(&range.start) {
+        if keep_preamble || bounds.is_empty() {
+            bounds.insert(0, range.start);
+        } else {
+            bounds[0] = range.start;
+        }
+    }
+    let mut units = Vec::new();
+    for (i, &start) in bounds.iter().enumerate() {
+        let end = bounds.get(i + 1).copied().unwrap_or(range.end);
+        // Trailing blank lines belong to nobody.
+        let mut trimmed_end = end;
+        while trimmed_end > start && lines[trimmed_end - 1].trim().is_empty() {
+            trimmed_end -= 1;
+        }
+        if trimmed_end > start {
+            units.push((start, trimmed_end));
+        }
+    }
+    units
+}
+
+fn merge_small(units: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in units {
+        match merged.last_mut() {
+            Some(last) if last.1 - last.0 < MIN_CHUNK_LINES && end - last.0 <= MAX_CHUNK_LINES => {
+                last.1 = end;
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn split_large(lines: &[&str], start: usize, end: usize, syntax: Syntax) -> Vec<(usize, usize)> {
+    if end - start <= MAX_CHUNK_LINES {
+        return vec![(start, end)];
+    }
+    // An impl, class or module: split at its members first.
+    if syntax == Syntax::Braces {
+        let nested = brace_item_starts(lines, start..end, 1);
+        if nested.len() > 1 {
+            return merge_small(split_at(lines, start..end, &nested, false))
+                .into_iter()
+                .flat_map(|(s, e)| windows(s, e))
+                .collect();
+        }
+    }
+    windows(start, end)
+}
+
+fn windows(start: usize, end: usize) -> Vec<(usize, usize)> {
+    if end - start <= MAX_CHUNK_LINES {
+        return vec![(start, end)];
+    }
+    (start..end)
+        .step_by(WINDOW_LINES)
+        .map(|s| (s, (s + WINDOW_LINES).min(end)))
+        .collect()
+}
+
+fn is_item_line(line: &str) -> bool {
+    BRACE_ITEM_RE.is_match(line) || PYTHON_ITEM_RE.is_match(line) || HEADING_RE.is_match(line)
+}
+
+fn symbol_for(lines: &[&str]) -> Option<String> {
+    let line = lines
+        .iter()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !is_attached(line) || HEADING_RE.is_match(line))?;
+    let line = line.trim_end_matches('{').trim();
+    Some(line.chars().take(80).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_items_and_markdown_sections() {
+        let mut source = String::from("use std::fmt;\n\n/// Adds.\n#[inline]\npub fn add(a: i32, b: i32) -> i32 {\n    let s = \"}\";\n    a + b\n}\n\n");
+        source.push_str("impl Thing {\n");
+        for i in 0..70 {
+            source.push_str(&format!(
+                "    fn m{}(&self) {{\n        todo!()\n    }}\n",
+                i
+            ));
+        }
+        source.push_str("}\n");
+
+        let chunks = chunk_file(Path::new("lib.rs"), &source);
+        assert_eq!(chunks[0].start_line, 1);
+        assert!(chunks[0].content.contains("pub fn add"));
+        assert!(chunks[0].content.contains("/// Adds."));
+        // The 212-line impl is split at its methods.
+        assert!(chunks.len() > 2);
+        assert!(chunks
+            .iter()
+            .all(|c| c.end_line - c.start_line < MAX_CHUNK_LINES));
+        assert_eq!(chunks.last().unwrap().end_line, source.lines().count());
+
+        let doc = "# Title\nintro\n\n## Setup\n```\n# not a heading\n```\nsteps\n";
+        let chunks = chunk_file(Path::new("README.md"), doc);
+        assert_eq!(chunks.len(), 1); // both sections are small, so merged
+        let long_section = format!("# A\n{}\n# B\nb\n", "line\n".repeat(20));
+        let chunks = chunk_file(Path::new("doc.md"), &long_section);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].symbol.as_deref(), Some("# B"));
+        assert_eq!(chunks[1].start_line, 23);
+    }
+}
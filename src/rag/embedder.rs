@@ -0,0 +1,438 @@
+//! Embedding backends for the vector store.
+//!
+//! Every stored vector records the [`Embedder::model_id`] that produced it,
+//! so switching backends or models never mixes incompatible vectors in one
+//! search: chunks from another model are skipped until `/index reindex`
+//! re-embeds them.
+
+use std::sync::Mutex;
+
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use serde::Deserialize;
+
+type EmbedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// What every vector in databases created before model tracking was made with.
+pub const LEGACY_MODEL_ID: &str = "fastembed:Qdrant/all-MiniLM-L6-v2-onnx";
+
+/// Texts sent per request to remote backends.
+const REMOTE_BATCH: usize = 64;
+
+/// Turns text into vectors. Calls block; the store runs them on blocking
+/// threads.
+pub trait Embedder: Send + Sync {
+    /// Stable identifier of backend and model, stored with each vector.
+    fn model_id(&self) -> &str;
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedError>;
+}
+
+/// `config.toml [embedding]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EmbeddingConfig {
+    /// `"fastembed"` (default), `"gemini"` or `"openai_compat"`.
+    pub backend: Option<String>,
+    /// Backend-specific model name. For fastembed either the enum name
+    /// (`BGESmallENV15`) or the model code (`Xenova/bge-small-en-v1.5`).
+    pub model: Option<String>,
+    /// Reuse the key and base URL of this `[providers.<name>]` entry.
+    pub provider: Option<String>,
+    pub api_key_env: Option<String>,
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+    /// Requested output size, for models that support truncation.
+    pub dimensions: Option<usize>,
+}
+
+/// Build the embedder selected in config, defaulting to local MiniLM.
+pub fn from_config(config: &crate::config::AppConfig) -> Result<Box<dyn Embedder>, EmbedError> {
+    let embedding = config.embedding.clone().unwrap_or_default();
+    let provider = embedding
+        .provider
+        .as_deref()
+        .and_then(|name| config.get_provider(name));
+    let backend = embedding
+        .backend
+        .clone()
+        .or_else(|| provider.map(|p| p.type_name.clone()))
+        .unwrap_or_else(|| "fastembed".to_string());
+
+    let api_key = || -> Result<String, EmbedError> {
+        let env_var = embedding
+            .api_key_env
+            .clone()
+            .or_else(|| provider.and_then(|p| p.api_key_env.clone()));
+        let key = env_var
+            .and_then(|var| std::env::var(var).ok())
+            .or_else(|| embedding.api_key.clone())
+            .or_else(|| provider.and_then(|p| p.api_key.clone()));
+        let key = match (key, backend.as_str()) {
+            (Some(key), _) => key,
+            (None, "gemini") => std::env::var("GEMINI_API_KEY")
+                .map_err(|_| "No API key for gemini embeddings (set GEMINI_API_KEY)")?,
+            (None, _) => return Err("No API key configured for [embedding]".into()),
+        };
+        Ok(key.trim().to_string())
+    };
+
+    match backend.as_str() {
+        "fastembed" => Ok(Box::new(FastEmbedder::new(embedding.model.as_deref())?)),
+        "gemini" => Ok(Box::new(
+            GeminiEmbedder {
+                api_key: api_key()?,
+                model: embedding
+                    .model
+                    .clone()
+                    .unwrap_or_else(|| "gemini-embedding-001".to_string()),
+                dimensions: embedding.dimensions,
+                model_id: String::new(),
+            }
+            .with_id(),
+        )),
+        "openai_compat" | "aliyun" => {
+            let base_url = embedding
+                .base_url
+                .clone()
+                .or_else(|| provider.and_then(|p| p.base_url.clone()))
+                .ok_or("base_url required for openai_compat embeddings")?;
+            Ok(Box::new(
+                OpenAiCompatEmbedder {
+                    api_key: api_key()?,
+                    url: embeddings_url(&base_url),
+                    model: embedding
+                        .model
+                        .clone()
+                        .unwrap_or_else(|| "text-embedding-3-small".to_string()),
+                    dimensions: embedding.dimensions,
+                    model_id: String::new(),
+                }
+                .with_id(),
+            ))
+        }
+        other => Err(format!("Unknown embedding backend '{}'", other).into()),
+    }
+}
+
+/// `https://host/v1/chat/completions` or `https://host/v1` →
+/// `https://host/v1/embeddings`.
+fn embeddings_url(base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    let base = base.strip_suffix("/chat/completions").unwrap_or(base);
+    let base = base.strip_suffix("/embeddings").unwrap_or(base);
+    format!("{}/embeddings", base)
+}
+
+/// Local ONNX models via fastembed.
+pub struct FastEmbedder {
+    model: Mutex<TextEmbedding>,
+    model_id: String,
+}
+
+impl FastEmbedder {
+    pub fn new(model: Option<&str>) -> Result<Self, EmbedError> {
+        let choice = match model {
+            None => EmbeddingModel::AllMiniLML6V2,
+            Some(name) => TextEmbedding::list_supported_models()
+                .into_iter()
+                .find(|info| info.model_code.eq_ignore_ascii_case(name))
+                .map(|info| info.model)
+                .or_else(|| name.parse().ok())
+                .ok_or_else(|| format!("Unknown fastembed model '{}'", name))?,
+        };
+        let code = TextEmbedding::get_model_info(&choice)
+            .map(|info| info.model_code.clone())
+            .map_err(|e| e.to_string())?;
+
+        let mut opts = InitOptions::new(choice);
+        opts.show_download_progress = false;
+
+        // WORKAROUND: hf-mirror.com and similar mirrors often strip the `Content-Range` header on 200 OK
+        // responses, which crashes the rust `hf-hub` crate used by `fastembed`.
+        // If the user has a proxy configured, we can temporarily unset HF_ENDPOINT to download locally.
+        let hf_endpoint = std::env::var("HF_ENDPOINT").unwrap_or_default();
+        let has_proxy = std::env::var("https_proxy").is_ok()
+            || std::env::var("all_proxy").is_ok()
+            || std::env::var("HTTP_PROXY").is_ok();
+
+        if hf_endpoint.contains("hf-mirror") && has_proxy {
+            tracing::warn!("[RAG] Temporarily unsetting HF_ENDPOINT to bypass download bug, relying on system proxy...");
+            std::env::remove_var("HF_ENDPOINT");
+        }
+
+        let model_res = TextEmbedding::try_new(opts);
+
+        // Restore HF_ENDPOINT
+        if !hf_endpoint.is_empty() {
+            std::env::set_var("HF_ENDPOINT", hf_endpoint);
+        }
+
+        let model = match model_res {
+            Ok(m) => m,
+            Err(e) => {
+                if e.to_string().contains("Content-Range") {
+                    eprintln!("======================================================");
+                    eprintln!("❌ RAG Initialization Error: Failed to download embedding model.");
+                    eprintln!("This is caused by your HF_ENDPOINT stripping the 'Content-Range'");
+                    eprintln!("header which is strictly required by the underlying hf-hub crate.");
+                    eprintln!("Workarounds:");
+                    eprintln!(" 1. Run with a proxy and bypass the mirror:");
+                    eprintln!("    env HF_ENDPOINT= cargo run");
+                    eprintln!(" 2. Download '{}' manually into:", code);
+                    eprintln!("    ~/.cache/huggingface/hub/");
+                    eprintln!("======================================================");
+                }
+                return Err(e.into());
+            }
+        };
+
+        Ok(Self {
+            model: Mutex::new(model),
+            model_id: format!("fastembed:{}", code),
+        })
+    }
+}
+
+impl Embedder for FastEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedError> {
+        let mut model = self.model.lock().unwrap();
+        Ok(model.embed(texts, None)?)
+    }
+}
+
+/// Gemini `batchEmbedContents`.
+pub struct GeminiEmbedder {
+    api_key: String,
+    model: String,
+    dimensions: Option<usize>,
+    model_id: String,
+}
+
+impl GeminiEmbedder {
+    fn with_id(mut self) -> Self {
+        self.model_id = match self.dimensions {
+            Some(dim) => format!("gemini:{}@{}", self.model, dim),
+            None => format!("gemini:{}", self.model),
+        };
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct GeminiBatchResponse {
+    embeddings: Vec<GeminiValues>,
+}
+
+#[derive(Deserialize)]
+struct GeminiValues {
+    values: Vec<f32>,
+}
+
+impl Embedder for GeminiEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedError> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:batchEmbedContents",
+            self.model
+        );
+        let mut out = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(REMOTE_BATCH) {
+            let requests: Vec<serde_json::Value> = batch
+                .iter()
+                .map(|text| {
+                    let mut request = serde_json::json!({
+                        "model": format!("models/{}", self.model),
+                        "content": {"parts": [{"text": text}]},
+                    });
+                    if let Some(dim) = self.dimensions {
+                        request["outputDimensionality"] = dim.into();
+                    }
+                    request
+                })
+                .collect();
+            let body = serde_json::json!({ "requests": requests });
+            let response: GeminiBatchResponse =
+                post_json(&url, &[("x-goog-api-key", self.api_key.as_str())], &body)?;
+            out.extend(response.embeddings.into_iter().map(|e| e.values));
+        }
+        Ok(out)
+    }
+}
+
+/// Any server implementing OpenAI's `POST /embeddings`.
+pub struct OpenAiCompatEmbedder {
+    api_key: String,
+    url: String,
+    model: String,
+    dimensions: Option<usize>,
+    model_id: String,
+}
+
+impl OpenAiCompatEmbedder {
+    fn with_id(mut self) -> Self {
+        let host = url::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        self.model_id = match self.dimensions {
+            Some(dim) => format!("openai_compat:{}/{}@{}", host, self.model, dim),
+            None => format!("openai_compat:{}/{}", host, self.model),
+        };
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbeddingResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl Embedder for OpenAiCompatEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedError> {
+        let bearer = format!("Bearer {}", self.api_key);
+        let mut out = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(REMOTE_BATCH) {
+            let mut body = serde_json::json!({ "model": self.model, "input": batch });
+            if let Some(dim) = self.dimensions {
+                body["dimensions"] = dim.into();
+            }
+            let mut response: OpenAiEmbeddingResponse =
+                post_json(&self.url, &[("Authorization", bearer.as_str())], &body)?;
+            response.data.sort_by_key(|item| item.index);
+            out.extend(response.data.into_iter().map(|item| item.embedding));
+        }
+        Ok(out)
+    }
+}
+
+/// Blocking POST that is safe to call from anywhere. On a multi-threaded
+/// runtime's worker (the injection detector is built on one) the request
+/// runs under `block_in_place`, so the worker's other tasks move elsewhere
+/// instead of waiting on the network; a current-thread runtime cannot
+/// hand its tasks off, so there the request gets its own thread.
+fn post_json<T: serde::de::DeserializeOwned + Send>(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &serde_json::Value,
+) -> Result<T, EmbedError> {
+    use tokio::runtime::{Handle, RuntimeFlavor};
+
+    match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(RuntimeFlavor::CurrentThread) => std::thread::scope(|scope| {
+            scope
+                .spawn(|| send_json(url, headers, body))
+                .join()
+                .map_err(|_| -> EmbedError { "Embedding request thread panicked".into() })?
+        }),
+        Ok(_) => tokio::task::block_in_place(|| send_json(url, headers, body)),
+        Err(_) => send_json(url, headers, body),
+    }
+}
+
+fn send_json<T: serde::de::DeserializeOwned>(
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &serde_json::Value,
+) -> Result<T, EmbedError> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(60))
+        .build()?;
+    let mut request = client.post(url).json(body);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let response = request.send()?;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().unwrap_or_default();
+        return Err(format!(
+            "Embedding request failed ({}): {}",
+            status,
+            text.chars().take(300).collect::<String>()
+        )
+        .into());
+    }
+    Ok(response.json()?)
+}
+
+/// Offline stand-in for a real model: hashed bag of words, so vector
+/// search only matches whole words and keyword search has to cover
+/// the rest.
+#[cfg(test)]
+pub(crate) struct HashedWordsEmbedder;
+
+#[cfg(test)]
+impl Embedder for HashedWordsEmbedder {
+    fn model_id(&self) -> &str {
+        "test:hashed-words"
+    }
+
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedError> {
+        use std::hash::{Hash, Hasher};
+        Ok(texts
+            .iter()
+            .map(|text| {
+                let mut vector = vec![0.0; 64];
+                for word in text
+                    .to_lowercase()
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty())
+                {
+                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                    word.hash(&mut hasher);
+                    vector[hasher.finish() as usize % 64] += 1.0;
+                }
+                vector
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embeddings_url_and_model_ids() {
+        assert_eq!(
+            embeddings_url("https://api.example.com/v1/chat/completions"),
+            "https://api.example.com/v1/embeddings"
+        );
+        assert_eq!(
+            embeddings_url("https://api.example.com/v1/"),
+            "https://api.example.com/v1/embeddings"
+        );
+
+        let openai = OpenAiCompatEmbedder {
+            api_key: "k".into(),
+            url: embeddings_url("https://api.example.com/v1"),
+            model: "text-embedding-3-small".into(),
+            dimensions: Some(256),
+            model_id: String::new(),
+        }
+        .with_id();
+        assert_eq!(
+            openai.model_id(),
+            "openai_compat:api.example.com/text-embedding-3-small@256"
+        );
+
+        let config: crate::config::AppConfig =
+            toml::from_str("[embedding]\nbackend = \"nope\"\n").unwrap();
+        assert!(from_config(&config).is_err());
+    }
+}
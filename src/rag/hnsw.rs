@@ -0,0 +1,605 @@
+//! Hierarchical navigable small world graph (Malkov & Yashunin) for
+//! approximate nearest-neighbour search over normalised embeddings.
+//!
+//! Vectors live in memory next to the graph; only the graph is persisted,
+//! since the vectors are already in SQLite. Deletes are tombstones: the node
+//! keeps routing searches but is never returned, and the index is rebuilt
+//! once too many accumulate.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::{Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"RCHNSW01";
+/// Links per node on upper layers; layer 0 gets twice as many.
+const DEFAULT_M: usize = 16;
+const DEFAULT_EF_CONSTRUCTION: usize = 100;
+const MAX_LEVEL: usize = 16;
+
+/// Distance and node, ordered by distance.
+#[derive(Clone, Copy, PartialEq)]
+struct Scored(f32, u32);
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+pub struct HnswIndex {
+    dim: usize,
+    m: usize,
+    ef_construction: usize,
+    ids: Vec<i64>,
+    /// `links[node][layer]`; a node exists on layers `0..links[node].len()`.
+    links: Vec<Vec<Vec<u32>>>,
+    /// Unit-length vectors, node-major.
+    vectors: Vec<f32>,
+    deleted: Vec<bool>,
+    deleted_count: usize,
+    by_id: HashMap<i64, u32>,
+    entry: Option<u32>,
+    rng: u64,
+    /// Per-node visit stamps, reused across searches to avoid a set per call.
+    visited: RefCell<(Vec<u32>, u32)>,
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+impl HnswIndex {
+    pub fn new(dim: usize) -> Self {
+        Self {
+            dim,
+            m: DEFAULT_M,
+            ef_construction: DEFAULT_EF_CONSTRUCTION,
+            ids: Vec::new(),
+            links: Vec::new(),
+            vectors: Vec::new(),
+            deleted: Vec::new(),
+            deleted_count: 0,
+            by_id: HashMap::new(),
+            entry: None,
+            rng: 0x9E37_79B9_7F4A_7C15,
+            visited: RefCell::new((Vec::new(), 0)),
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Live (non-deleted) vectors.
+    pub fn len(&self) -> usize {
+        self.ids.len() - self.deleted_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        self.by_id.contains_key(&id)
+    }
+
+    /// Share of nodes that are tombstones.
+    pub fn deleted_ratio(&self) -> f32 {
+        if self.ids.is_empty() {
+            0.0
+        } else {
+            self.deleted_count as f32 / self.ids.len() as f32
+        }
+    }
+
+    /// The stored (normalised) vector of a live `id`.
+    pub fn vector_of(&self, id: i64) -> Option<&[f32]> {
+        self.by_id.get(&id).map(|node| self.vector(*node))
+    }
+
+    fn vector(&self, node: u32) -> &[f32] {
+        let start = node as usize * self.dim;
+        &self.vectors[start..start + self.dim]
+    }
+
+    fn distance(&self, query: &[f32], node: u32) -> f32 {
+        let dot: f32 = query
+            .iter()
+            .zip(self.vector(node))
+            .map(|(a, b)| a * b)
+            .sum();
+        1.0 - dot
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.m * 2
+        } else {
+            self.m
+        }
+    }
+
+    fn random_level(&mut self) -> usize {
+        // xorshift64*; quality is irrelevant, determinism is handy.
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let bits = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        let uniform = ((bits >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let level = (-uniform.ln() / (self.m as f64).ln()).floor() as usize;
+        level.min(MAX_LEVEL)
+    }
+
+    /// Add or replace the vector for `id`.
+    pub fn insert(&mut self, id: i64, vector: &[f32]) {
+        if vector.len() != self.dim {
+            return;
+        }
+        self.remove(id);
+        let query = normalize(vector);
+        let node = self.ids.len() as u32;
+        let level = self.random_level();
+        self.ids.push(id);
+        self.vectors.extend_from_slice(&query);
+        self.links.push(vec![Vec::new(); level + 1]);
+        self.deleted.push(false);
+        self.by_id.insert(id, node);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            return;
+        };
+        let top = self.links[entry as usize].len() - 1;
+        let mut nearest = entry;
+        for layer in (level + 1..=top).rev() {
+            nearest = self.greedy(&query, nearest, layer);
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let candidates = self.search_layer(&query, &[nearest], self.ef_construction, layer);
+            let neighbours = self.select_neighbours(&candidates, self.max_links(layer));
+            for &neighbour in &neighbours {
+                self.connect(neighbour, node, layer);
+            }
+            self.links[node as usize][layer] = neighbours;
+            nearest = candidates[0].1;
+        }
+        if level > top {
+            self.entry = Some(node);
+        }
+    }
+
+    /// Tombstone `id`. Returns whether it was present.
+    pub fn remove(&mut self, id: i64) -> bool {
+        match self.by_id.remove(&id) {
+            Some(node) => {
+                self.deleted[node as usize] = true;
+                self.deleted_count += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Add `to` to `from`'s links on `layer`, pruning to the closest when full.
+    fn connect(&mut self, from: u32, to: u32, layer: usize) {
+        let max = self.max_links(layer);
+        self.links[from as usize][layer].push(to);
+        if self.links[from as usize][layer].len() <= max {
+            return;
+        }
+        let base = self.vector(from).to_vec();
+        let mut scored: Vec<Scored> = self.links[from as usize][layer]
+            .iter()
+            .map(|&n| Scored(self.distance(&base, n), n))
+            .collect();
+        scored.sort();
+        self.links[from as usize][layer] = self.select_neighbours(&scored, max);
+    }
+
+    /// The heuristic from the paper: keep a candidate only if it is closer to
+    /// the base than to every neighbour already kept, which spreads links
+    /// across clusters. Tops up with the nearest rejects.
+    fn select_neighbours(&self, sorted: &[Scored], max: usize) -> Vec<u32> {
+        let mut kept: Vec<u32> = Vec::with_capacity(max);
+        let mut rejected = Vec::new();
+        for &Scored(distance, node) in sorted {
+            if kept.len() >= max {
+                break;
+            }
+            let candidate = self.vector(node);
+            if kept.iter().all(|&k| self.distance(candidate, k) > distance) {
+                kept.push(node);
+            } else {
+                rejected.push(node);
+            }
+        }
+        for node in rejected {
+            if kept.len() >= max {
+                break;
+            }
+            kept.push(node);
+        }
+        kept
+    }
+
+    fn greedy(&self, query: &[f32], start: u32, layer: usize) -> u32 {
+        let mut current = start;
+        let mut best = self.distance(query, current);
+        loop {
+            let mut improved = false;
+            for &neighbour in &self.links[current as usize][layer] {
+                let distance = self.distance(query, neighbour);
+                if distance < best {
+                    best = distance;
+                    current = neighbour;
+                    improved = true;
+                }
+            }
+            if !improved {
+                return current;
+            }
+        }
+    }
+
+    /// Best-first search on one layer; returns up to `ef` nodes, nearest first.
+    fn search_layer(&self, query: &[f32], starts: &[u32], ef: usize, layer: usize) -> Vec<Scored> {
+        let mut visited = self.visited.borrow_mut();
+        let (stamps, stamp) = &mut *visited;
+        stamps.resize(self.ids.len(), 0);
+        *stamp = stamp.wrapping_add(1);
+        if *stamp == 0 {
+            stamps.iter_mut().for_each(|s| *s = 0);
+            *stamp = 1;
+        }
+        for &start in starts {
+            stamps[start as usize] = *stamp;
+        }
+        let mut frontier: BinaryHeap<std::cmp::Reverse<Scored>> = BinaryHeap::new();
+        let mut best: BinaryHeap<Scored> = BinaryHeap::new();
+        for &start in starts {
+            let scored = Scored(self.distance(query, start), start);
+            frontier.push(std::cmp::Reverse(scored));
+            best.push(scored);
+        }
+        while let Some(std::cmp::Reverse(current)) = frontier.pop() {
+            if best.len() >= ef && current.0 > best.peek().map(|s| s.0).unwrap_or(f32::MAX) {
+                break;
+            }
+            for &neighbour in &self.links[current.1 as usize][layer] {
+                if stamps[neighbour as usize] == *stamp {
+                    continue;
+                }
+                stamps[neighbour as usize] = *stamp;
+                let scored = Scored(self.distance(query, neighbour), neighbour);
+                if best.len() < ef || scored.0 < best.peek().map(|s| s.0).unwrap_or(f32::MAX) {
+                    frontier.push(std::cmp::Reverse(scored));
+                    best.push(scored);
+                    if best.len() > ef {
+                        best.pop();
+                    }
+                }
+            }
+        }
+        best.into_sorted_vec()
+    }
+
+    /// Approximate top-`k` by cosine similarity among ids `accept` admits.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        ef: usize,
+        accept: impl Fn(i64) -> bool,
+    ) -> Vec<(i64, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        if query.len() != self.dim {
+            return Vec::new();
+        }
+        let query = normalize(query);
+        let mut nearest = entry;
+        for layer in (1..self.links[entry as usize].len()).rev() {
+            nearest = self.greedy(&query, nearest, layer);
+        }
+        self.search_layer(&query, &[nearest], ef.max(k), 0)
+            .into_iter()
+            .filter(|s| !self.deleted[s.1 as usize] && accept(self.ids[s.1 as usize]))
+            .take(k)
+            .map(|s| (self.ids[s.1 as usize], 1.0 - s.0))
+            .collect()
+    }
+
+    /// Exact top-`k` by scanning every live vector.
+    pub fn exact(&self, query: &[f32], k: usize, accept: impl Fn(i64) -> bool) -> Vec<(i64, f32)> {
+        if query.len() != self.dim {
+            return Vec::new();
+        }
+        let query = normalize(query);
+        let mut scored: Vec<Scored> = (0..self.ids.len() as u32)
+            .filter(|&node| !self.deleted[node as usize] && accept(self.ids[node as usize]))
+            .map(|node| Scored(self.distance(&query, node), node))
+            .collect();
+        let k = k.min(scored.len());
+        if k == 0 {
+            return Vec::new();
+        }
+        scored.select_nth_unstable(k - 1);
+        scored.truncate(k);
+        scored.sort();
+        scored
+            .into_iter()
+            .map(|s| (self.ids[s.1 as usize], 1.0 - s.0))
+            .collect()
+    }
+
+    /// A fresh index over the live vectors only.
+    pub fn compacted(&self) -> Self {
+        let mut index = Self::new(self.dim);
+        for node in 0..self.ids.len() as u32 {
+            if !self.deleted[node as usize] {
+                index.insert(self.ids[node as usize], self.vector(node));
+            }
+        }
+        index
+    }
+
+    /// Write the graph (not the vectors) with a header binding it to a model
+    /// and a store generation.
+    pub fn save(&self, path: &Path, model_id: &str, generation: u64) -> std::io::Result<()> {
+        let mut out = Vec::with_capacity(64 + self.ids.len() * (self.m * 10));
+        out.extend_from_slice(MAGIC);
+        write_u32(&mut out, model_id.len() as u32);
+        out.extend_from_slice(model_id.as_bytes());
+        out.extend_from_slice(&generation.to_le_bytes());
+        write_u32(&mut out, self.dim as u32);
+        write_u32(&mut out, self.m as u32);
+        write_u32(&mut out, self.ef_construction as u32);
+        out.extend_from_slice(&self.entry.map(i64::from).unwrap_or(-1).to_le_bytes());
+        out.extend_from_slice(&self.rng.to_le_bytes());
+        write_u32(&mut out, self.ids.len() as u32);
+        for node in 0..self.ids.len() {
+            out.extend_from_slice(&self.ids[node].to_le_bytes());
+            out.push(self.deleted[node] as u8);
+            out.push(self.links[node].len() as u8);
+            for layer in &self.links[node] {
+                write_u32(&mut out, layer.len() as u32);
+                for &neighbour in layer {
+                    write_u32(&mut out, neighbour);
+                }
+            }
+        }
+        let tmp = path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(&out)?;
+        file.sync_all()?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Read a graph written by [`Self::save`], taking each live node's
+    /// vector from `vectors`. `None` when the file is missing, corrupt, for
+    /// another model or generation, or does not cover exactly `vectors`.
+    pub fn load(
+        path: &Path,
+        model_id: &str,
+        generation: u64,
+        vectors: &HashMap<i64, Vec<f32>>,
+    ) -> Option<Self> {
+        let mut bytes = Vec::new();
+        std::fs::File::open(path)
+            .ok()?
+            .read_to_end(&mut bytes)
+            .ok()?;
+        let mut reader = Reader {
+            bytes: &bytes,
+            pos: 0,
+        };
+        if reader.take(8)? != MAGIC {
+            return None;
+        }
+        let model_len = reader.u32()? as usize;
+        if reader.take(model_len)? != model_id.as_bytes() || reader.u64()? != generation {
+            return None;
+        }
+        let dim = reader.u32()? as usize;
+        let mut index = Self::new(dim);
+        index.m = reader.u32()? as usize;
+        index.ef_construction = reader.u32()? as usize;
+        let entry = reader.i64()?;
+        index.entry = u32::try_from(entry).ok();
+        index.rng = reader.u64()?;
+        let count = reader.u32()? as usize;
+        for node in 0..count {
+            let id = reader.i64()?;
+            let deleted = reader.take(1)?[0] != 0;
+            let layers = reader.take(1)?[0] as usize;
+            let mut links = Vec::with_capacity(layers);
+            for _ in 0..layers {
+                let n = reader.u32()? as usize;
+                let mut layer = Vec::with_capacity(n);
+                for _ in 0..n {
+                    let neighbour = reader.u32()?;
+                    if neighbour as usize >= count {
+                        return None;
+                    }
+                    layer.push(neighbour);
+                }
+                links.push(layer);
+            }
+            if layers == 0 {
+                return None;
+            }
+            // Tombstones keep routing, so they need a vector too; a zero
+            // vector is fine since they are never returned.
+            let vector = match vectors.get(&id) {
+                Some(vector) if !deleted && vector.len() == dim => normalize(vector),
+                _ if deleted => vec![0.0; dim],
+                _ => return None,
+            };
+            index.ids.push(id);
+            index.vectors.extend_from_slice(&vector);
+            index.links.push(links);
+            index.deleted.push(deleted);
+            if deleted {
+                index.deleted_count += 1;
+            } else {
+                index.by_id.insert(id, node as u32);
+            }
+        }
+        if index.entry.is_some_and(|entry| entry as usize >= count) {
+            return None;
+        }
+        // Vectors added after the graph was saved.
+        if index.len() != vectors.len() {
+            return None;
+        }
+        Some(index)
+    }
+}
+
+fn write_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(slice)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Deterministic clustered vectors, roughly like sentence embeddings.
+    fn dataset(n: usize, dim: usize, seed: u64) -> Vec<Vec<f32>> {
+        let mut state = seed;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+        };
+        let centres: Vec<Vec<f32>> = (0..32)
+            .map(|_| (0..dim).map(|_| next()).collect())
+            .collect();
+        (0..n)
+            .map(|i| {
+                centres[i % centres.len()]
+                    .iter()
+                    .map(|c| c + next() * 0.6)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// An index of `n` clustered vectors and 100 held-out queries from the
+    /// same distribution.
+    fn indexed(n: usize, dim: usize) -> (HnswIndex, Vec<Vec<f32>>, Vec<Vec<f32>>) {
+        let mut data = dataset(n + 100, dim, 7);
+        let queries = data.split_off(n);
+        let mut index = HnswIndex::new(dim);
+        for (i, vector) in data.iter().enumerate() {
+            index.insert(i as i64, vector);
+        }
+        (index, data, queries)
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let (n, k) = (2_000, 10);
+        let (mut index, data, queries) = indexed(n, 32);
+
+        let mut hits = 0;
+        for query in &queries {
+            let exact = index.exact(query, k, |_| true);
+            let approx = index.search(query, k, 64, |_| true);
+            hits += approx
+                .iter()
+                .filter(|(id, _)| exact.iter().any(|(e, _)| e == id))
+                .count();
+        }
+        let recall = hits as f32 / (queries.len() * k) as f32;
+        assert!(recall >= 0.9, "recall {} too low", recall);
+
+        // Tombstones are never returned and survive a save/load round trip.
+        for id in 0..100 {
+            index.remove(id);
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.hnsw");
+        index.save(&path, "test-model", 3).unwrap();
+        let vectors: HashMap<i64, Vec<f32>> = data
+            .iter()
+            .enumerate()
+            .skip(100)
+            .map(|(i, v)| (i as i64, v.clone()))
+            .collect();
+        let loaded = HnswIndex::load(&path, "test-model", 3, &vectors).unwrap();
+        assert_eq!(loaded.len(), n - 100);
+        let results = loaded.search(&data[5], k, 64, |_| true);
+        assert!(results.iter().all(|(id, _)| *id >= 100));
+        assert!(HnswIndex::load(&path, "test-model", 4, &vectors).is_none());
+    }
+
+    /// Timing is meaningless in unoptimized builds; run with
+    /// `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn test_ann_search_beats_exact_scan() {
+        let (index, _, queries) = indexed(20_000, 64);
+        let time = |search: &dyn Fn(&[f32])| {
+            let start = Instant::now();
+            for query in &queries {
+                search(query);
+            }
+            start.elapsed()
+        };
+        let exact = time(&|query| {
+            index.exact(query, 10, |_| true);
+        });
+        let ann = time(&|query| {
+            index.search(query, 10, 64, |_| true);
+        });
+        assert!(
+            ann < exact,
+            "ANN {:?} not faster than exact {:?}",
+            ann,
+            exact
+        );
+    }
+}
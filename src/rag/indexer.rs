@@ -0,0 +1,240 @@
+//! Keeps the vector store in sync with the workspace source tree.
+//!
+//! Walks the project root honouring `.gitignore` and the sandbox's hidden
+//! paths, chunks each source file with [`super::chunker`] and re-embeds
+//! only files whose content hash changed since the last run. Files that
+//! disappeared are dropped.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::chunker;
+use super::VectorStore;
+use crate::tools::sandbox::{SandboxEnforcer, SandboxLevel, SandboxPolicy};
+
+/// Files larger than this are assumed to be generated or vendored.
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+
+/// Outcome of one refresh pass.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexReport {
+    pub files_scanned: usize,
+    pub files_indexed: usize,
+    pub files_unchanged: usize,
+    pub files_removed: usize,
+    pub chunks_added: usize,
+    pub errors: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl IndexReport {
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Indexed {} of {} files ({} unchanged, {} removed), {} chunks in {}ms.",
+            self.files_indexed,
+            self.files_scanned,
+            self.files_unchanged,
+            self.files_removed,
+            self.chunks_added,
+            self.duration_ms
+        );
+        for error in self.errors.iter().take(5) {
+            out.push_str(&format!("\n  - {}", error));
+        }
+        if self.errors.len() > 5 {
+            out.push_str(&format!("\n  ... {} more errors", self.errors.len() - 5));
+        }
+        out
+    }
+}
+
+pub struct CodeIndexer {
+    store: Arc<VectorStore>,
+    root: PathBuf,
+    /// Checks walked paths against the sandbox's hidden paths.
+    guard: SandboxEnforcer,
+    /// Serialises refreshes; holds the last report.
+    last: Mutex<Option<IndexReport>>,
+}
+
+impl CodeIndexer {
+    pub fn new(store: Arc<VectorStore>, root: PathBuf) -> Self {
+        Self {
+            store,
+            root,
+            guard: SandboxEnforcer::disabled(),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Never walk into `hidden_paths`, whatever the sandbox level.
+    pub fn with_hidden_paths(mut self, hidden_paths: Vec<PathBuf>) -> Self {
+        self.guard = SandboxEnforcer::disabled_with_policy(SandboxPolicy {
+            level: SandboxLevel::Restricted,
+            hidden_paths,
+            ..Default::default()
+        });
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn store(&self) -> &Arc<VectorStore> {
+        &self.store
+    }
+
+    /// Re-index changed files. Runs on a blocking thread.
+    pub async fn refresh(self: &Arc<Self>) -> Result<IndexReport, String> {
+        let indexer = self.clone();
+        tokio::task::spawn_blocking(move || indexer.refresh_blocking())
+            .await
+            .map_err(|e| format!("Index refresh panicked: {}", e))?
+    }
+
+    pub fn refresh_blocking(&self) -> Result<IndexReport, String> {
+        let mut last = self.last.lock().unwrap();
+        let start = Instant::now();
+        let mut report = IndexReport::default();
+        let mut known = self
+            .store
+            .indexed_files()
+            .map_err(|e| format!("Failed to read index state: {}", e))?;
+
+        for path in self.source_files() {
+            let Some(relative) = self.relative(&path) else {
+                continue;
+            };
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                // Not UTF-8; not worth indexing.
+                continue;
+            };
+            report.files_scanned += 1;
+            let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+            let previous = known.remove(&relative);
+            if previous.is_some_and(|file| file.content_hash == hash) {
+                report.files_unchanged += 1;
+                continue;
+            }
+            let chunks = chunker::chunk_file(&path, &content);
+            match self.store.replace_file_chunks(&relative, &hash, chunks) {
+                Ok(count) => {
+                    report.files_indexed += 1;
+                    report.chunks_added += count;
+                }
+                Err(e) => report.errors.push(format!("{}: {}", relative, e)),
+            }
+        }
+
+        // Anything left in `known` was not seen on disk this time.
+        for path in known.into_keys() {
+            match self.store.remove_file(&path) {
+                Ok(()) => report.files_removed += 1,
+                Err(e) => report.errors.push(format!("{}: {}", path, e)),
+            }
+        }
+
+        if report.files_indexed + report.files_removed > 0 {
+            self.store.save_index();
+        }
+        report.duration_ms = start.elapsed().as_millis() as u64;
+        tracing::info!("[RAG] {}", report.render());
+        *last = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Summary of what is in the index right now.
+    pub fn status(&self) -> Result<String, String> {
+        let files = self
+            .store
+            .indexed_files()
+            .map_err(|e| format!("Failed to read index state: {}", e))?;
+        let chunks: usize = files.values().map(|file| file.chunk_count).sum();
+        let newest = files.values().map(|file| file.indexed_at).max();
+        let mut out = format!(
+            "Code index for {}: {} files, {} chunks.",
+            self.root.display(),
+            files.len(),
+            chunks
+        );
+        if let Some(newest) = newest.and_then(|ts| chrono::DateTime::from_timestamp(ts, 0)) {
+            out.push_str(&format!(
+                "\nLast file indexed: {}",
+                newest.format("%Y-%m-%d %H:%M:%S UTC")
+            ));
+        }
+        out.push_str(&format!(
+            "\nEmbedding model: {}",
+            self.store.embedder().model_id()
+        ));
+        for (model, count) in self.store.stale_models() {
+            out.push_str(&format!(
+                "\n  {} chunks from {} are excluded from search; run /index reindex",
+                count,
+                if model.is_empty() {
+                    "an unknown model"
+                } else {
+                    &model
+                }
+            ));
+        }
+        match self.last.try_lock().ok().and_then(|last| last.clone()) {
+            Some(report) => out.push_str(&format!("\nLast refresh: {}", report.render())),
+            None => out.push_str("\nNo refresh has completed in this process yet."),
+        }
+        Ok(out)
+    }
+
+    fn source_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let guard = self.guard.clone();
+        let walker = ignore::WalkBuilder::new(&self.root)
+            .hidden(true)
+            .git_ignore(true)
+            .require_git(false)
+            .filter_entry(move |entry| {
+                let name = entry.file_name().to_string_lossy();
+                !matches!(name.as_ref(), "rusty_claw" | "target" | "node_modules")
+                    && guard
+                        .check_path_access(entry.path(), false, guard.default_policy())
+                        .is_ok()
+            })
+            .build();
+        for entry in walker.flatten() {
+            let path = entry.path();
+            if !entry.file_type().is_some_and(|ty| ty.is_file()) || !chunker::is_indexable(path) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy();
+            if name.ends_with(".lock") || name.ends_with(".min.js") {
+                continue;
+            }
+            if entry
+                .metadata()
+                .map(|meta| meta.len() > MAX_FILE_BYTES)
+                .unwrap_or(true)
+            {
+                continue;
+            }
+            files.push(path.to_path_buf());
+        }
+        files.sort();
+        files
+    }
+
+    fn relative(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        Some(
+            relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+        )
+    }
+}
@@ -0,0 +1,658 @@
+//! Turns documents on disk into knowledge-base chunks.
+//!
+//! PDFs are read page by page through `pdftotext` (poppler), run inside
+//! the OS sandbox when the session's policy restricts anything, HTML is
+//! converted to Markdown the same way `web_fetch` does it, and Markdown is
+//! split at its headings. Each page or section is cut into overlapping
+//! chunks whose source records where they came from (`path#page 3`,
+//! `path#Design > Storage`). A document whose content hash matches the last
+//! ingestion is skipped.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::VectorStore;
+use crate::core::AgentOutput;
+use crate::tools::sandbox::{SandboxEnforcer, SandboxLevel};
+use crate::tools::ToolContext;
+
+/// Chunks aim for this many bytes of text...
+const CHUNK_BYTES: usize = 1_500;
+/// ...and repeat this much of the previous chunk so a sentence cut at the
+/// boundary is still whole in one of them.
+const OVERLAP_BYTES: usize = 200;
+/// Larger files are skipped; scanned books are not design docs.
+const MAX_DOCUMENT_BYTES: u64 = 32 * 1024 * 1024;
+const PDFTOTEXT_MISSING: &str = "reading PDFs needs `pdftotext` (poppler-utils) on PATH";
+
+static HEADING_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(#{1,6})\s+(.+?)\s*#*\s*$").expect("valid heading regex"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Pdf,
+    Html,
+    Markdown,
+    Text,
+}
+
+fn format_for(path: &Path) -> Option<Format> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "pdf" => Some(Format::Pdf),
+        "html" | "htm" | "xhtml" => Some(Format::Html),
+        "md" | "markdown" | "mdx" => Some(Format::Markdown),
+        "txt" | "text" | "rst" | "adoc" => Some(Format::Text),
+        _ => None,
+    }
+}
+
+pub fn is_ingestible(path: &Path) -> bool {
+    format_for(path).is_some()
+}
+
+/// A page or section of a document; `location` is `None` for plain text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub location: Option<String>,
+    pub text: String,
+}
+
+/// Outcome of one ingestion.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestReport {
+    pub namespace: String,
+    pub files_ingested: usize,
+    pub files_unchanged: usize,
+    pub chunks_added: usize,
+    pub errors: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl IngestReport {
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Ingested {} file{} into '{}' ({} unchanged), {} chunks in {}ms.",
+            self.files_ingested,
+            if self.files_ingested == 1 { "" } else { "s" },
+            self.namespace,
+            self.files_unchanged,
+            self.chunks_added,
+            self.duration_ms
+        );
+        for error in self.errors.iter().take(5) {
+            out.push_str(&format!("\n  - {}", error));
+        }
+        if self.errors.len() > 5 {
+            out.push_str(&format!("\n  ... {} more errors", self.errors.len() - 5));
+        }
+        out
+    }
+}
+
+/// Supported documents named by `pattern`: a file, a directory (walked
+/// honouring `.gitignore`) or a glob such as `docs/**/*.pdf`. Relative
+/// patterns start at `root`; nothing outside `root` is returned.
+pub fn resolve(root: &Path, pattern: &str) -> Result<Vec<PathBuf>, String> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Err("No path given".to_string());
+    }
+    let (base, glob) = split_glob(pattern);
+    let base = root.join(base);
+    let inside_root = match (base.canonicalize(), root.canonicalize()) {
+        (Ok(base), Ok(root)) => base.starts_with(root),
+        _ => return Err(format!("{}: no such file or directory", base.display())),
+    };
+    if !inside_root {
+        return Err(format!(
+            "{}: outside the project root {}",
+            pattern,
+            root.display()
+        ));
+    }
+    if glob.is_none() && base.is_file() {
+        return if is_ingestible(&base) {
+            Ok(vec![base])
+        } else {
+            Err(format!(
+                "{}: unsupported format (PDF, HTML, Markdown or text)",
+                pattern
+            ))
+        };
+    }
+    if !base.is_dir() {
+        return Err(format!("{}: no such file or directory", base.display()));
+    }
+
+    let mut walker = ignore::WalkBuilder::new(&base);
+    walker.hidden(true).git_ignore(true).require_git(false);
+    if let Some(glob) = &glob {
+        let mut overrides = ignore::overrides::OverrideBuilder::new(&base);
+        overrides
+            .add(glob)
+            .map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?;
+        walker.overrides(
+            overrides
+                .build()
+                .map_err(|e| format!("Invalid glob '{}': {}", pattern, e))?,
+        );
+    }
+    let mut files: Vec<PathBuf> = walker
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|ty| ty.is_file()))
+        .map(|entry| entry.into_path())
+        .filter(|path| is_ingestible(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Refuse the whole batch when the sandbox in `ctx` hides any of `files`.
+pub fn guard_files(files: &[PathBuf], ctx: &ToolContext) -> Result<(), String> {
+    if let Some(sandbox) = &ctx.sandbox {
+        for file in files {
+            sandbox
+                .guard_path_access(ctx, "ingest", file, false)
+                .map_err(|violation| violation.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits a pattern into the directory before its first wildcard and the
+/// rest as a glob relative to it.
+fn split_glob(pattern: &str) -> (PathBuf, Option<String>) {
+    let components: Vec<&str> = pattern.split('/').collect();
+    let Some(first) = components
+        .iter()
+        .position(|part| part.contains(['*', '?', '[', '{']))
+    else {
+        return (PathBuf::from(pattern), None);
+    };
+    let base = components[..first].join("/");
+    let base = if base.is_empty() && pattern.starts_with('/') {
+        "/".to_string()
+    } else if base.is_empty() {
+        ".".to_string()
+    } else {
+        base
+    };
+    (PathBuf::from(base), Some(components[first..].join("/")))
+}
+
+/// Pages or sections of the document at `path` whose content is `bytes`,
+/// with empty ones left out. External converters run under `sandbox`,
+/// with `cwd` as their working directory.
+pub fn extract(
+    path: &Path,
+    bytes: &[u8],
+    sandbox: Option<&SandboxEnforcer>,
+    cwd: &Path,
+) -> Result<Vec<Section>, String> {
+    let format = format_for(path).ok_or("unsupported format")?;
+    if format == Format::Pdf {
+        return extract_pdf(path, sandbox, cwd);
+    }
+    let text = String::from_utf8_lossy(bytes);
+    Ok(match format {
+        Format::Html => markdown_sections(&crate::tools::web::html_to_clean_markdown(&text)),
+        Format::Markdown => markdown_sections(&text),
+        _ => vec![Section {
+            location: None,
+            text: text.trim().to_string(),
+        }],
+    }
+    .into_iter()
+    .filter(|section| !section.text.trim().is_empty())
+    .collect())
+}
+
+fn extract_pdf(
+    path: &Path,
+    sandbox: Option<&SandboxEnforcer>,
+    cwd: &Path,
+) -> Result<Vec<Section>, String> {
+    let sandbox = sandbox.filter(|s| s.default_policy().level != SandboxLevel::Unrestricted);
+    let mut command = match sandbox {
+        Some(sandbox) if !sandbox.is_available() => {
+            return Err(sandbox.shell_execution_error());
+        }
+        Some(sandbox) => {
+            let script = format!("pdftotext -enc UTF-8 {} -", shell_quote(&cwd.join(path)));
+            sandbox.build_std_command(&script, sandbox.default_policy(), cwd)
+        }
+        None => {
+            let mut command = std::process::Command::new("pdftotext");
+            command.args(["-enc", "UTF-8"]).arg(path).arg("-");
+            command
+        }
+    };
+    let output = command.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => PDFTOTEXT_MISSING.to_string(),
+        _ => format!("failed to run pdftotext: {}", e),
+    })?;
+    // bash inside the sandbox reports a missing binary as exit code 127.
+    if output.status.code() == Some(127) {
+        return Err(PDFTOTEXT_MISSING.to_string());
+    }
+    if !output.status.success() {
+        return Err(format!(
+            "pdftotext failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(pdf_pages(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Single-quoted for `bash -c`.
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', r"'\''"))
+}
+
+/// `pdftotext` ends every page with a form feed.
+fn pdf_pages(text: &str) -> Vec<Section> {
+    text.split('\x0c')
+        .enumerate()
+        .filter(|(_, page)| !page.trim().is_empty())
+        .map(|(idx, page)| Section {
+            location: Some(format!("page {}", idx + 1)),
+            text: page.trim().to_string(),
+        })
+        .collect()
+}
+
+/// One section per heading, located by the heading path (`A > B`). Text
+/// before the first heading has no location.
+fn markdown_sections(markdown: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut trail: Vec<(usize, String)> = Vec::new();
+    let mut current = Section {
+        location: None,
+        text: String::new(),
+    };
+    let mut in_fence = false;
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        } else if let Some(caps) = HEADING_RE.captures(line).filter(|_| !in_fence) {
+            let level = caps[1].len();
+            trail.retain(|(depth, _)| *depth < level);
+            trail.push((level, caps[2].to_string()));
+            let location = trail
+                .iter()
+                .map(|(_, title)| title.as_str())
+                .collect::<Vec<_>>()
+                .join(" > ");
+            sections.push(std::mem::replace(
+                &mut current,
+                Section {
+                    location: Some(location),
+                    text: String::new(),
+                },
+            ));
+        }
+        current.text.push_str(line);
+        current.text.push('\n');
+    }
+    sections.push(current);
+    for section in &mut sections {
+        section.text = section.text.trim().to_string();
+    }
+    sections
+}
+
+/// Cuts `text` into chunks of about `size` bytes at paragraph, then word
+/// boundaries; each chunk after the first starts with the last `overlap`
+/// bytes of the one before.
+pub fn split_with_overlap(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if paragraph.len() <= size {
+            pieces.push(paragraph.to_string());
+            continue;
+        }
+        let mut piece = String::new();
+        for word in paragraph.split_whitespace() {
+            if !piece.is_empty() && piece.len() + 1 + word.len() > size {
+                pieces.push(std::mem::take(&mut piece));
+            }
+            if !piece.is_empty() {
+                piece.push(' ');
+            }
+            piece.push_str(word);
+        }
+        if !piece.is_empty() {
+            pieces.push(piece);
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for piece in pieces {
+        if !current.is_empty() && current.len() + 2 + piece.len() > size {
+            let tail = overlap_tail(&current, overlap).to_string();
+            chunks.push(std::mem::replace(&mut current, tail));
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&piece);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// The last `overlap` bytes of `text`, moved forward to a word start.
+fn overlap_tail(text: &str, overlap: usize) -> &str {
+    if overlap == 0 || text.len() <= overlap {
+        return if overlap == 0 { "" } else { text };
+    }
+    let mut start = text.len() - overlap;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    let tail = &text[start..];
+    match tail.find(char::is_whitespace) {
+        Some(space) => tail[space..].trim_start(),
+        None => tail,
+    }
+}
+
+/// `(source, content)` chunks of a document stored under `key`.
+pub fn chunk_document(key: &str, sections: &[Section]) -> Vec<(String, String)> {
+    let mut chunks = Vec::new();
+    for section in sections {
+        let source = match &section.location {
+            Some(location) => format!("{}#{}", key, location),
+            None => key.to_string(),
+        };
+        for content in split_with_overlap(&section.text, CHUNK_BYTES, OVERLAP_BYTES) {
+            chunks.push((source.clone(), content));
+        }
+    }
+    chunks
+}
+
+/// Ingest `files` into `namespace`, reporting each file through
+/// `progress`. Documents are keyed by their path relative to `root`;
+/// converters run under `sandbox` when it restricts anything.
+pub async fn ingest_files(
+    store: &Arc<VectorStore>,
+    root: &Path,
+    files: Vec<PathBuf>,
+    namespace: &str,
+    sandbox: Option<Arc<SandboxEnforcer>>,
+    progress: Option<&dyn AgentOutput>,
+) -> IngestReport {
+    let start = Instant::now();
+    let mut report = IngestReport {
+        namespace: namespace.to_string(),
+        ..Default::default()
+    };
+    let total = files.len();
+    for (idx, path) in files.into_iter().enumerate() {
+        let key = document_key(root, &path);
+        if let Some(output) = progress {
+            output
+                .on_waiting(&format!("Ingesting {}/{}: {}", idx + 1, total, key))
+                .await;
+        }
+        let store = store.clone();
+        let namespace = namespace.to_string();
+        let task_key = key.clone();
+        let sandbox = sandbox.clone();
+        let root = root.to_path_buf();
+        let outcome = tokio::task::spawn_blocking(move || {
+            ingest_file(
+                &store,
+                &namespace,
+                &task_key,
+                &path,
+                sandbox.as_deref(),
+                &root,
+            )
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("ingestion panicked: {}", e)));
+        match outcome {
+            Ok(Some(count)) => {
+                report.files_ingested += 1;
+                report.chunks_added += count;
+            }
+            Ok(None) => report.files_unchanged += 1,
+            Err(e) => report.errors.push(format!("{}: {}", key, e)),
+        }
+    }
+    if let Some(output) = progress {
+        output.clear_waiting();
+    }
+    if report.files_ingested > 0 {
+        store.save_index();
+    }
+    report.duration_ms = start.elapsed().as_millis() as u64;
+    tracing::info!("[RAG] {}", report.render());
+    report
+}
+
+/// Chunks written, or `None` when the file is unchanged since last time.
+fn ingest_file(
+    store: &VectorStore,
+    namespace: &str,
+    key: &str,
+    path: &Path,
+    sandbox: Option<&SandboxEnforcer>,
+    root: &Path,
+) -> Result<Option<usize>, String> {
+    let size = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if size > MAX_DOCUMENT_BYTES {
+        return Err(format!("larger than {} MB", MAX_DOCUMENT_BYTES >> 20));
+    }
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let hash = format!("{:x}", Sha256::digest(&bytes));
+    if store.is_ingested(namespace, key, &hash) {
+        return Ok(None);
+    }
+    let chunks = chunk_document(key, &extract(path, &bytes, sandbox, root)?);
+    if chunks.is_empty() {
+        return Err("no text found".to_string());
+    }
+    store
+        .replace_document(namespace, key, &hash, chunks)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// `/`-separated path relative to `root`, or the full path outside it.
+fn document_key(root: &Path, path: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(relative) => relative
+            .components()
+            .filter(|c| !matches!(c, std::path::Component::CurDir))
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => path.to_string_lossy().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::embedder::Embedder;
+
+    struct LengthEmbedder;
+
+    impl Embedder for LengthEmbedder {
+        fn model_id(&self) -> &str {
+            "test:length"
+        }
+
+        fn embed(
+            &self,
+            texts: Vec<String>,
+        ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(texts
+                .iter()
+                .map(|text| vec![1.0, text.len() as f32 / 100.0])
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_pdfs_are_not_converted_outside_a_restricted_sandbox() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = SandboxEnforcer::disabled_with_policy(crate::tools::sandbox::SandboxPolicy {
+            level: SandboxLevel::Restricted,
+            ..Default::default()
+        });
+        let err = extract(
+            &dir.path().join("a.pdf"),
+            b"%PDF-1.4",
+            Some(&sandbox),
+            dir.path(),
+        )
+        .unwrap_err();
+        assert_eq!(err, sandbox.shell_execution_error());
+        assert_eq!(
+            shell_quote(Path::new("/docs/it's.pdf")),
+            r"'/docs/it'\''s.pdf'"
+        );
+    }
+
+    #[test]
+    fn test_sections_and_overlapping_chunks() {
+        let markdown = "Intro text.\n\n# Design\nOverview.\n```\n# not a heading\n```\n## Storage\nRows live in SQLite.\n# FAQ\nNone yet.\n";
+        let sections = markdown_sections(markdown);
+        let locations: Vec<_> = sections.iter().map(|s| s.location.as_deref()).collect();
+        assert_eq!(
+            locations,
+            vec![None, Some("Design"), Some("Design > Storage"), Some("FAQ")]
+        );
+        assert!(sections[1].text.contains("# not a heading"));
+
+        let pages = pdf_pages("first page\x0c\x0cthird page\x0c");
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[1].location.as_deref(), Some("page 3"));
+
+        let text = (0..40)
+            .map(|n| format!("Sentence number {} is here.", n))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let chunks = split_with_overlap(&text, 200, 50);
+        assert!(chunks.len() > 5);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 200 + 50 + 2));
+        for pair in chunks.windows(2) {
+            // The next chunk starts with words the previous one ended with.
+            let head: String = pair[1]
+                .split_whitespace()
+                .take(3)
+                .collect::<Vec<_>>()
+                .join(" ");
+            assert!(pair[0].contains(&head), "{:?}", pair);
+        }
+        assert!(chunks.last().unwrap().ends_with("number 39 is here."));
+
+        let chunks = chunk_document("docs/a.pdf", &pages);
+        assert_eq!(chunks[0], ("docs/a.pdf#page 1".into(), "first page".into()));
+
+        assert_eq!(
+            split_glob("docs/**/*.pdf"),
+            (PathBuf::from("docs"), Some("**/*.pdf".into()))
+        );
+        assert_eq!(
+            split_glob("*.md"),
+            (PathBuf::from("."), Some("*.md".into()))
+        );
+        assert_eq!(split_glob("docs/a.md"), (PathBuf::from("docs/a.md"), None));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_skips_unchanged_and_replaces_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("ws");
+        std::fs::create_dir_all(root.join("docs/sub")).unwrap();
+        std::fs::write(
+            root.join("docs/design.md"),
+            "# Design\nOverview.\n## Storage\nRows live in SQLite.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("docs/sub/notes.txt"),
+            "Tea is brewed at 80 degrees.",
+        )
+        .unwrap();
+        std::fs::write(root.join("docs/logo.png"), "png").unwrap();
+        let store = Arc::new(
+            VectorStore::open(&dir.path().join("kb.db"), Arc::new(LengthEmbedder)).unwrap(),
+        );
+
+        assert_eq!(resolve(&root, "docs/**/*.txt").unwrap().len(), 1);
+        assert!(resolve(&root, "docs/logo.png").is_err());
+        std::fs::write(dir.path().join("outside.md"), "# Private").unwrap();
+        for pattern in ["../outside.md", "../*.md"] {
+            assert!(resolve(&root, pattern)
+                .unwrap_err()
+                .contains("outside the project root"));
+        }
+        let absolute = dir.path().join("outside.md").display().to_string();
+        assert!(resolve(&root, &absolute).is_err());
+        let files = resolve(&root, "docs").unwrap();
+        assert_eq!(files.len(), 2);
+        let report = ingest_files(&store, &root, files, "docs", None, None).await;
+        assert_eq!((report.files_ingested, report.chunks_added), (2, 3));
+        let mut sources: Vec<String> = store
+            .list_chunks("docs", 10)
+            .into_iter()
+            .map(|chunk| chunk.source)
+            .collect();
+        sources.sort();
+        assert_eq!(
+            sources,
+            vec![
+                "docs/design.md#Design",
+                "docs/design.md#Design > Storage",
+                "docs/sub/notes.txt"
+            ]
+        );
+
+        let report = ingest_files(
+            &store,
+            &root,
+            resolve(&root, "docs").unwrap(),
+            "docs",
+            None,
+            None,
+        )
+        .await;
+        assert_eq!((report.files_ingested, report.files_unchanged), (0, 2));
+
+        std::fs::write(root.join("docs/design.md"), "# Design\nRewritten.\n").unwrap();
+        let report = ingest_files(
+            &store,
+            &root,
+            resolve(&root, "docs").unwrap(),
+            "docs",
+            None,
+            None,
+        )
+        .await;
+        assert_eq!((report.files_ingested, report.files_unchanged), (1, 1));
+        let design: Vec<_> = store
+            .list_chunks("docs", 10)
+            .into_iter()
+            .filter(|chunk| chunk.source.starts_with("docs/design.md"))
+            .collect();
+        assert_eq!(design.len(), 1);
+        assert!(design[0].preview.contains("Rewritten"));
+    }
+}
@@ -0,0 +1,2031 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+use crate::security::InjectionDetector;
+
+pub mod chunker;
+pub mod embedder;
+pub mod hnsw;
+pub mod indexer;
+pub mod ingest;
+pub mod ranking;
+pub mod rerank;
+pub mod retention;
+
+use embedder::Embedder;
+use hnsw::HnswIndex;
+use ranking::RetrievalConfig;
+use rerank::Reranker;
+use retention::{GcReport, RetentionConfig, Usage};
+
+/// Namespace for memories not tied to a project, user or chat.
+pub const DEFAULT_NAMESPACE: &str = "global";
+/// Namespace owned by the codebase indexer.
+pub const CODE_NAMESPACE: &str = "code";
+/// Default namespace for documents read by [`ingest`].
+pub const DOCS_NAMESPACE: &str = "docs";
+
+fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.to_string()
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RagChunk {
+    pub id: i64,
+    pub content: String,
+    pub source: String,
+    pub embedding: Vec<f32>,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    /// [`Embedder::model_id`] of the model that produced `embedding`.
+    #[serde(default)]
+    pub model_id: String,
+    /// Set for chunks produced by the codebase indexer.
+    #[serde(default)]
+    pub span: Option<SourceSpan>,
+}
+
+/// Where an indexed chunk came from, in a form `read_file` accepts.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SourceSpan {
+    /// Workspace-relative, `/`-separated.
+    pub path: String,
+    /// 1-based, inclusive.
+    pub start_line: usize,
+    pub end_line: usize,
+    /// sha256 of the whole file at index time.
+    pub content_hash: String,
+    pub symbol: Option<String>,
+}
+
+/// A stored chunk without its embedding, for listings.
+#[derive(Serialize, Debug, Clone)]
+pub struct ChunkInfo {
+    pub id: i64,
+    pub namespace: String,
+    pub source: String,
+    pub preview: String,
+}
+
+/// Usage and retention figures for one namespace.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct NamespaceStats {
+    pub namespace: String,
+    pub chunks: usize,
+    /// Search hits over all its chunks.
+    pub hits: u64,
+    pub never_hit: usize,
+    /// Chunks with an expiry set.
+    pub expiring: usize,
+    pub oldest_created_at: Option<i64>,
+    pub last_hit_at: Option<i64>,
+}
+
+/// Snapshot for `/memory stats`.
+#[derive(Serialize, Debug, Clone)]
+pub struct StoreStats {
+    pub namespaces: Vec<NamespaceStats>,
+    /// Chunks outside the `code` namespace, which the GC budget applies to.
+    pub memory_chunks: usize,
+    pub max_chunks: usize,
+    pub db_bytes: u64,
+    /// Time and result of the last garbage collection in this process.
+    pub last_gc: Option<(i64, GcReport)>,
+}
+
+/// Bookkeeping row for one indexed file.
+#[derive(Debug, Clone)]
+pub struct IndexedFile {
+    pub path: String,
+    pub content_hash: String,
+    pub chunk_count: usize,
+    pub indexed_at: i64,
+}
+
+/// Default database; the ANN graph is persisted next to it with the
+/// extension `hnsw`.
+const DB_PATH: &str = ".rusty_claw_memory.db";
+/// Below this many vectors an exact scan is as fast as the graph.
+const ANN_MIN_VECTORS: usize = 2_000;
+/// Use the exact scan when the namespace filter admits less than
+/// 1/ANN_MAX_SELECTIVITY of the vectors; the graph would mostly visit
+/// rejected nodes.
+const ANN_MAX_SELECTIVITY: usize = 5;
+/// Vector candidates re-ranked with keyword scores.
+const SEARCH_CANDIDATES: usize = 100;
+/// Keyword hits taken from FTS5 per search.
+const KEYWORD_CANDIDATES: usize = 50;
+const ANN_EF_SEARCH: usize = 200;
+/// Rebuild the graph once this share of its nodes are tombstones.
+const ANN_MAX_DELETED_RATIO: f32 = 0.3;
+/// Save the graph after this many unsaved single-chunk changes.
+const ANN_SAVE_EVERY: usize = 64;
+
+/// What the store keeps in memory per chunk; content and vectors stay in
+/// SQLite until needed.
+#[derive(Clone)]
+struct ChunkMeta {
+    namespace: String,
+    source: String,
+    model_id: String,
+    span: Option<SourceSpan>,
+    usage: Usage,
+}
+
+/// The graph over the active model's vectors, loaded on first use.
+#[derive(Default)]
+struct AnnState {
+    index: Option<HnswIndex>,
+    /// Changes applied since the graph was last written to disk.
+    unsaved: usize,
+}
+
+pub struct VectorStore {
+    // Shared (Arc) so other local classifiers can reuse the loaded model.
+    embedder: Arc<dyn Embedder>,
+    // SQLite connection for persistence and FTS5 search
+    conn: Mutex<Connection>,
+    // Metadata of every chunk keyed by id. RwLock allows multiple readers
+    // (searches) at once.
+    chunks: RwLock<BTreeMap<i64, ChunkMeta>>,
+    // Lock ordering rule: always conn -> chunks -> ann.
+    ann: Mutex<AnnState>,
+    ann_path: PathBuf,
+    reranker: Option<Arc<dyn Reranker>>,
+    retrieval: RetrievalConfig,
+    retention: RetentionConfig,
+    db_path: PathBuf,
+    last_gc: Mutex<Option<(i64, GcReport)>>,
+}
+
+impl VectorStore {
+    /// Open the store with the embedder selected in `config.toml [embedding]`
+    /// and the search and retention settings of `[retrieval]` and `[retention]`.
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let config = crate::config::AppConfig::load();
+        let embedder =
+            embedder::from_config(&config).map_err(|e| -> Box<dyn std::error::Error> { e })?;
+        let retrieval = config.retrieval.unwrap_or_default();
+        // Search still works without the cross-encoder, so a model that
+        // fails to load is not fatal.
+        let reranker = retrieval.reranker.as_deref().and_then(|model| {
+            match rerank::FastReranker::new(Some(model)) {
+                Ok(reranker) => Some(Arc::new(reranker) as Arc<dyn Reranker>),
+                Err(e) => {
+                    tracing::warn!("[RAG] Reranking disabled: {}", e);
+                    None
+                }
+            }
+        });
+        Ok(Self::with_embedder(Arc::from(embedder))?
+            .with_retrieval(retrieval, reranker)
+            .with_retention(config.retention.unwrap_or_default()))
+    }
+
+    pub fn with_embedder(embedder: Arc<dyn Embedder>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::open(std::path::Path::new(DB_PATH), embedder)
+    }
+
+    /// Open the store at `db_path`; the ANN graph is kept next to it.
+    pub fn open(
+        db_path: &std::path::Path,
+        embedder: Arc<dyn Embedder>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let start = Instant::now();
+        tracing::debug!(
+            "[RAG] Initializing VectorStore with {}...",
+            embedder.model_id()
+        );
+
+        // Open a SQLite connection for hybrid search
+        let mut conn = Connection::open(db_path)?;
+
+        // Initialize the tables
+        // 1. chunks: The source of truth
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS chunks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                content TEXT NOT NULL,
+                source TEXT NOT NULL,
+                embedding_json TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        // Columns added after the first release; older databases lack them.
+        let existing: Vec<String> = conn
+            .prepare("PRAGMA table_info(chunks)")?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<Result<_, _>>()?;
+        for (column, ty) in [
+            ("path", "TEXT"),
+            ("start_line", "INTEGER"),
+            ("end_line", "INTEGER"),
+            ("content_hash", "TEXT"),
+            ("symbol", "TEXT"),
+            ("namespace", "TEXT NOT NULL DEFAULT 'global'"),
+            ("model_id", "TEXT"),
+            ("dimension", "INTEGER"),
+            ("embedding", "BLOB"),
+            ("created_at", "INTEGER"),
+            ("last_hit_at", "INTEGER"),
+            ("hit_count", "INTEGER NOT NULL DEFAULT 0"),
+            ("expires_at", "INTEGER"),
+        ] {
+            if !existing.iter().any(|name| name == column) {
+                conn.execute(&format!("ALTER TABLE chunks ADD COLUMN {column} {ty}"), [])?;
+                if column == "namespace" {
+                    // Indexed code predates namespaces; move it to its own.
+                    conn.execute(
+                        "UPDATE chunks SET namespace = ?1 WHERE path IS NOT NULL",
+                        params![CODE_NAMESPACE],
+                    )?;
+                }
+                if column == "model_id" {
+                    // Before model tracking every vector came from MiniLM.
+                    conn.execute(
+                        "UPDATE chunks SET model_id = ?1",
+                        params![embedder::LEGACY_MODEL_ID],
+                    )?;
+                }
+                if column == "dimension" {
+                    conn.execute(
+                        "UPDATE chunks SET dimension = json_array_length(embedding_json)",
+                        [],
+                    )?;
+                }
+                if column == "embedding" {
+                    migrate_embeddings_to_blobs(&mut conn)?;
+                }
+                if column == "created_at" {
+                    // Real ages are unknown; count existing chunks as new
+                    // so the first GC does not treat them as long unused.
+                    conn.execute(
+                        "UPDATE chunks SET created_at = ?1",
+                        params![chrono::Utc::now().timestamp()],
+                    )?;
+                }
+            }
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_chunks_path ON chunks(path)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_chunks_namespace_source ON chunks(namespace, source)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS indexed_files (
+                path TEXT PRIMARY KEY,
+                content_hash TEXT NOT NULL,
+                chunk_count INTEGER NOT NULL,
+                indexed_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ingested_documents (
+                namespace TEXT NOT NULL,
+                path TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                chunk_count INTEGER NOT NULL,
+                ingested_at INTEGER NOT NULL,
+                PRIMARY KEY (namespace, path)
+            )",
+            [],
+        )?;
+        // Store-wide counters; `generation` changes with every write so a
+        // persisted ANN graph can tell whether it is still current.
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS rag_meta (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // 2. chunks_fts: The keyword search index (external content table)
+        conn.execute(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(
+                content,
+                content_rowid UNINDEXED
+            )",
+            [],
+        )?;
+
+        // 3. Triggers to keep FTS in sync
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS chunks_ai AFTER INSERT ON chunks BEGIN
+              INSERT INTO chunks_fts(content, content_rowid) VALUES (new.content, new.id);
+            END;",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS chunks_ad AFTER DELETE ON chunks BEGIN
+              DELETE FROM chunks_fts WHERE content_rowid = old.id;
+            END;",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TRIGGER IF NOT EXISTS chunks_au AFTER UPDATE OF content ON chunks BEGIN
+              UPDATE chunks_fts SET content = new.content WHERE content_rowid = old.id;
+            END;",
+            [],
+        )?;
+
+        // Load chunk metadata; vectors are read when the ANN graph is first
+        // needed and content when a chunk is returned.
+        let mut loaded_chunks = BTreeMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT id, source, path, start_line, end_line, content_hash, symbol, namespace, model_id,
+                    created_at, last_hit_at, hit_count, expires_at FROM chunks",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let path: Option<String> = row.get(2)?;
+            let span = match path {
+                Some(path) => Some(SourceSpan {
+                    path,
+                    start_line: row.get::<_, i64>(3)? as usize,
+                    end_line: row.get::<_, i64>(4)? as usize,
+                    content_hash: row.get(5)?,
+                    symbol: row.get(6)?,
+                }),
+                None => None,
+            };
+            Ok((
+                id,
+                ChunkMeta {
+                    source: row.get(1)?,
+                    namespace: row.get(7)?,
+                    model_id: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+                    span,
+                    usage: Usage {
+                        created_at: row.get::<_, Option<i64>>(9)?.unwrap_or_default(),
+                        last_hit_at: row.get(10)?,
+                        hit_count: row.get::<_, i64>(11)? as u32,
+                        expires_at: row.get(12)?,
+                    },
+                },
+            ))
+        })?;
+
+        for chunk in rows {
+            let (id, meta) = chunk?;
+            loaded_chunks.insert(id, meta);
+        }
+
+        // Drop the statement to release the borrow on conn
+        drop(stmt);
+
+        tracing::debug!(
+            "[RAG] Loaded metadata for {} chunks in {}ms",
+            loaded_chunks.len(),
+            start.elapsed().as_millis()
+        );
+        let stale = loaded_chunks
+            .values()
+            .filter(|chunk| chunk.model_id != embedder.model_id())
+            .count();
+        if stale > 0 {
+            tracing::warn!(
+                "[RAG] {} chunks were embedded with another model and are excluded from search until `/index reindex`",
+                stale
+            );
+        }
+
+        Ok(Self {
+            embedder,
+            conn: Mutex::new(conn),
+            chunks: RwLock::new(loaded_chunks),
+            ann: Mutex::new(AnnState::default()),
+            ann_path: db_path.with_extension("hnsw"),
+            reranker: None,
+            retrieval: RetrievalConfig::default(),
+            retention: RetentionConfig::default(),
+            db_path: db_path.to_path_buf(),
+            last_gc: Mutex::new(None),
+        })
+    }
+
+    /// Use `config` for search weighting and garbage collection.
+    pub fn with_retention(mut self, config: RetentionConfig) -> Self {
+        self.retention = config;
+        self
+    }
+
+    /// Use `config` for fusion and diversity, and `reranker` to rescore
+    /// the head of each search.
+    pub fn with_retrieval(
+        mut self,
+        config: RetrievalConfig,
+        reranker: Option<Arc<dyn Reranker>>,
+    ) -> Self {
+        self.retrieval = config;
+        self.reranker = reranker;
+        self
+    }
+
+    /// The active embedder, for reuse by other local classifiers.
+    pub fn embedder(&self) -> Arc<dyn Embedder> {
+        self.embedder.clone()
+    }
+
+    /// Chunks embedded with a model other than the active one, by model id.
+    pub fn stale_models(&self) -> Vec<(String, usize)> {
+        let chunks = self.chunks.read().unwrap();
+        let mut counts = BTreeMap::new();
+        for chunk in chunks.values() {
+            if chunk.model_id != self.embedder.model_id() {
+                *counts.entry(chunk.model_id.clone()).or_insert(0) += 1;
+            }
+        }
+        counts.into_iter().collect()
+    }
+
+    /// Re-embed every chunk produced by another model with the active one.
+    /// Returns how many chunks were migrated.
+    pub async fn reindex(
+        self: &Arc<Self>,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || store.reindex_blocking())
+            .await
+            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?
+    }
+
+    fn reindex_blocking(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let model_id = self.embedder.model_id().to_string();
+        let pending: Vec<i64> = {
+            let chunks = self.chunks.read().unwrap();
+            chunks
+                .iter()
+                .filter(|(_, chunk)| chunk.model_id != model_id)
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        let mut migrated = 0;
+        // Batches keep memory flat and let an interrupted run resume where
+        // it stopped: finished batches are already committed.
+        for batch in pending.chunks(64) {
+            let texts: Vec<(i64, String)> = {
+                let conn = self.conn.lock().unwrap();
+                let mut stmt = conn.prepare("SELECT content, path FROM chunks WHERE id = ?1")?;
+                let mut texts = Vec::with_capacity(batch.len());
+                for id in batch {
+                    let row = stmt.query_row(params![id], |row| {
+                        Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+                    });
+                    match row {
+                        Ok((content, path)) => {
+                            texts.push((*id, embedding_text(path.as_deref(), &content)))
+                        }
+                        // Deleted since the batch list was taken.
+                        Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                texts
+            };
+            if texts.is_empty() {
+                continue;
+            }
+            let embeddings = self
+                .embedder
+                .embed(texts.iter().map(|(_, text)| text.clone()).collect())?;
+            let mut conn = self.conn.lock().unwrap();
+            self.ensure_ann(&conn);
+            let tx = conn.transaction()?;
+            for ((id, _), embedding) in texts.iter().zip(&embeddings) {
+                tx.execute(
+                    "UPDATE chunks SET embedding = ?1, embedding_json = '', model_id = ?2, dimension = ?3 WHERE id = ?4",
+                    params![to_blob(embedding), model_id, embedding.len() as i64, id],
+                )?;
+            }
+            bump_generation(&tx)?;
+            tx.commit()?;
+            let mut chunks = self.chunks.write().unwrap();
+            for (id, _) in &texts {
+                if let Some(chunk) = chunks.get_mut(id) {
+                    chunk.model_id = model_id.clone();
+                }
+            }
+            drop(chunks);
+            let added: Vec<(i64, Vec<f32>)> =
+                texts.iter().map(|(id, _)| *id).zip(embeddings).collect();
+            self.apply_to_ann(&conn, &[], &added, true);
+            migrated += texts.len();
+        }
+        self.save_index();
+        Ok(migrated)
+    }
+
+    /// Insert a chunk into the default namespace.
+    pub async fn insert_chunk(
+        self: &Arc<Self>,
+        content: String,
+        source: String,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.insert_chunk_in(DEFAULT_NAMESPACE.to_string(), content, source)
+            .await
+            .map(|_| ())
+    }
+
+    /// Insert a chunk into `namespace`, returning its id.
+    /// Runs embedding computation and SQLite writes on a blocking thread
+    /// to avoid stalling the tokio runtime.
+    pub async fn insert_chunk_in(
+        self: &Arc<Self>,
+        namespace: String,
+        content: String,
+        source: String,
+    ) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            store
+                .write_chunk_blocking(namespace, content, source, false)
+                .map(|(id, _)| id)
+        })
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?
+    }
+
+    /// Replace every chunk in `namespace` keyed by `source` with `content`.
+    /// Returns the new id and how many chunks were replaced.
+    pub async fn upsert_chunk(
+        self: &Arc<Self>,
+        namespace: String,
+        content: String,
+        source: String,
+    ) -> Result<(i64, usize), Box<dyn std::error::Error + Send + Sync>> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || {
+            store.write_chunk_blocking(namespace, content, source, true)
+        })
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?
+    }
+
+    fn write_chunk_blocking(
+        &self,
+        namespace: String,
+        content: String,
+        source: String,
+        replace: bool,
+    ) -> Result<(i64, usize), Box<dyn std::error::Error + Send + Sync>> {
+        let documents = vec![content.clone()];
+
+        // Generate embedding (CPU-intensive)
+        let embedding = self
+            .embedder
+            .embed(documents)?
+            .into_iter()
+            .next()
+            .ok_or("Embedder returned no vector")?;
+        let model_id = self.embedder.model_id().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        // Write to DB; the delete and insert commit together so an upsert
+        // never leaves the key empty or doubled.
+        let mut conn = self.conn.lock().unwrap();
+        self.ensure_ann(&conn);
+        let replaced_ids: Vec<i64> = if replace {
+            let chunks = self.chunks.read().unwrap();
+            chunks
+                .iter()
+                .filter(|(_, chunk)| chunk.namespace == namespace && chunk.source == source)
+                .map(|(id, _)| *id)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let tx = conn.transaction()?;
+        let replaced = if replace {
+            tx.execute(
+                "DELETE FROM chunks WHERE namespace = ?1 AND source = ?2",
+                params![namespace, source],
+            )?
+        } else {
+            0
+        };
+        tx.execute(
+            "INSERT INTO chunks (content, source, embedding_json, embedding, namespace, model_id, dimension, created_at) VALUES (?1, ?2, '', ?3, ?4, ?5, ?6, ?7)",
+            params![
+                content,
+                source,
+                to_blob(&embedding),
+                namespace,
+                model_id,
+                embedding.len() as i64,
+                now
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        bump_generation(&tx)?;
+        tx.commit()?;
+
+        // Update in-memory cache
+        let mut chunks = self.chunks.write().unwrap();
+        for replaced_id in &replaced_ids {
+            chunks.remove(replaced_id);
+        }
+        chunks.insert(
+            id,
+            ChunkMeta {
+                namespace,
+                source,
+                model_id,
+                span: None,
+                usage: Usage::new(now),
+            },
+        );
+        drop(chunks);
+        self.apply_to_ann(&conn, &replaced_ids, &[(id, embedding)], false);
+
+        Ok((id, replaced))
+    }
+
+    /// Namespace of a stored chunk, if it exists.
+    pub fn chunk_namespace(&self, id: i64) -> Option<String> {
+        let chunks = self.chunks.read().unwrap();
+        chunks.get(&id).map(|chunk| chunk.namespace.clone())
+    }
+
+    /// Delete one chunk. Returns whether it existed.
+    pub fn delete_by_id(&self, id: i64) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        self.ensure_ann(&conn);
+        let tx = conn.transaction()?;
+        let deleted = tx.execute("DELETE FROM chunks WHERE id = ?1", params![id])?;
+        bump_generation(&tx)?;
+        tx.commit()?;
+        self.chunks.write().unwrap().remove(&id);
+        self.apply_to_ann(&conn, &[id], &[], false);
+        Ok(deleted > 0)
+    }
+
+    /// Delete every chunk in `namespace` keyed by `source`.
+    pub fn delete_by_source(
+        &self,
+        namespace: &str,
+        source: &str,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        self.ensure_ann(&conn);
+        let tx = conn.transaction()?;
+        let deleted = tx.execute(
+            "DELETE FROM chunks WHERE namespace = ?1 AND source = ?2",
+            params![namespace, source],
+        )?;
+        bump_generation(&tx)?;
+        tx.commit()?;
+        let mut chunks = self.chunks.write().unwrap();
+        let ids: Vec<i64> = chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.namespace == namespace && chunk.source == source)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &ids {
+            chunks.remove(id);
+        }
+        drop(chunks);
+        self.apply_to_ann(&conn, &ids, &[], false);
+        Ok(deleted)
+    }
+
+    /// Set or clear the Unix time after which a chunk is no longer
+    /// returned and is removed by the next GC. Returns whether it exists.
+    pub fn set_expiry(
+        &self,
+        id: i64,
+        expires_at: Option<i64>,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE chunks SET expires_at = ?1 WHERE id = ?2",
+            params![expires_at, id],
+        )?;
+        if let Some(chunk) = self.chunks.write().unwrap().get_mut(&id) {
+            chunk.usage.expires_at = expires_at;
+        }
+        Ok(updated > 0)
+    }
+
+    /// Delete expired memories and, over the `[retention]` budget, old ones
+    /// no search ever returned. Indexed code is never collected.
+    pub fn gc(&self) -> Result<GcReport, Box<dyn std::error::Error + Send + Sync>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut conn = self.conn.lock().unwrap();
+        let (doomed, report) = {
+            let chunks = self.chunks.read().unwrap();
+            let memories: Vec<(i64, Usage)> = chunks
+                .iter()
+                .filter(|(_, chunk)| chunk.namespace != CODE_NAMESPACE)
+                .map(|(id, chunk)| (*id, chunk.usage))
+                .collect();
+            retention::plan_gc(&memories, now, &self.retention)
+        };
+        if !doomed.is_empty() {
+            self.ensure_ann(&conn);
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare("DELETE FROM chunks WHERE id = ?1")?;
+                for id in &doomed {
+                    stmt.execute(params![id])?;
+                }
+            }
+            bump_generation(&tx)?;
+            tx.commit()?;
+            let mut chunks = self.chunks.write().unwrap();
+            for id in &doomed {
+                chunks.remove(id);
+            }
+            drop(chunks);
+            self.apply_to_ann(&conn, &doomed, &[], false);
+        }
+        *self.last_gc.lock().unwrap() = Some((now, report));
+        Ok(report)
+    }
+
+    /// Run [`Self::gc`] now and then every `gc_interval_hours`, until the
+    /// process exits. Returns at once when collection is disabled.
+    pub async fn gc_loop(self: Arc<Self>) {
+        let Some(interval) = self.retention.gc_interval() else {
+            return;
+        };
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let store = self.clone();
+            match tokio::task::spawn_blocking(move || store.gc()).await {
+                Ok(Ok(report)) if report.expired + report.pruned > 0 => tracing::info!(
+                    "[RAG] GC removed {} expired and {} unused chunks",
+                    report.expired,
+                    report.pruned
+                ),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => tracing::warn!("[RAG] GC failed: {}", e),
+                Err(e) => tracing::warn!("[RAG] GC task failed: {}", e),
+            }
+        }
+    }
+
+    /// Per-namespace usage figures and the GC budget.
+    pub fn stats(&self) -> StoreStats {
+        let chunks = self.chunks.read().unwrap();
+        let mut namespaces: BTreeMap<&str, NamespaceStats> = BTreeMap::new();
+        for chunk in chunks.values() {
+            let stats = namespaces
+                .entry(chunk.namespace.as_str())
+                .or_insert_with(|| NamespaceStats {
+                    namespace: chunk.namespace.clone(),
+                    ..Default::default()
+                });
+            let usage = &chunk.usage;
+            stats.chunks += 1;
+            stats.hits += usage.hit_count as u64;
+            stats.never_hit += (usage.hit_count == 0) as usize;
+            stats.expiring += usage.expires_at.is_some() as usize;
+            stats.oldest_created_at = Some(
+                stats
+                    .oldest_created_at
+                    .map_or(usage.created_at, |oldest| oldest.min(usage.created_at)),
+            );
+            stats.last_hit_at = stats.last_hit_at.max(usage.last_hit_at);
+        }
+        let memory_chunks = chunks
+            .values()
+            .filter(|chunk| chunk.namespace != CODE_NAMESPACE)
+            .count();
+        StoreStats {
+            namespaces: namespaces.into_values().collect(),
+            memory_chunks,
+            max_chunks: self.retention.max_chunks(),
+            db_bytes: std::fs::metadata(&self.db_path)
+                .map(|meta| meta.len())
+                .unwrap_or(0),
+            last_gc: *self.last_gc.lock().unwrap(),
+        }
+    }
+
+    /// Most recent chunks in `namespace`, newest first.
+    pub fn list_chunks(&self, namespace: &str, limit: usize) -> Vec<ChunkInfo> {
+        let conn = self.conn.lock().unwrap();
+        let result = conn
+            .prepare(
+                "SELECT id, source, substr(content, 1, 120) FROM chunks
+                 WHERE namespace = ?1 ORDER BY id DESC LIMIT ?2",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map(params![namespace, limit as i64], |row| {
+                    Ok(ChunkInfo {
+                        id: row.get(0)?,
+                        namespace: namespace.to_string(),
+                        source: row.get(1)?,
+                        preview: row.get(2)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()
+            });
+        result.unwrap_or_else(|e| {
+            tracing::warn!("[RAG] Failed to list chunks in {}: {}", namespace, e);
+            Vec::new()
+        })
+    }
+
+    /// Every namespace with its chunk count, sorted by name.
+    pub fn namespaces(&self) -> Vec<(String, usize)> {
+        let chunks = self.chunks.read().unwrap();
+        let mut counts = BTreeMap::new();
+        for chunk in chunks.values() {
+            *counts.entry(chunk.namespace.clone()).or_insert(0) += 1;
+        }
+        counts.into_iter().collect()
+    }
+
+    /// Files recorded by the codebase indexer, keyed by path.
+    pub fn indexed_files(
+        &self,
+    ) -> Result<
+        std::collections::HashMap<String, IndexedFile>,
+        Box<dyn std::error::Error + Send + Sync>,
+    > {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT path, content_hash, chunk_count, indexed_at FROM indexed_files")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(IndexedFile {
+                path: row.get(0)?,
+                content_hash: row.get(1)?,
+                chunk_count: row.get::<_, i64>(2)? as usize,
+                indexed_at: row.get(3)?,
+            })
+        })?;
+        let mut files = std::collections::HashMap::new();
+        for file in rows {
+            let file = file?;
+            files.insert(file.path.clone(), file);
+        }
+        Ok(files)
+    }
+
+    /// Replace every chunk of `path` with freshly embedded `chunks`.
+    /// Blocking: embeds on the calling thread. Call [`Self::save_index`]
+    /// after the last file.
+    pub fn replace_file_chunks(
+        &self,
+        path: &str,
+        content_hash: &str,
+        chunks: Vec<chunker::CodeChunk>,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let documents: Vec<String> = chunks
+            .iter()
+            .map(|chunk| embedding_text(Some(path), &chunk.content))
+            .collect();
+        let embeddings = if documents.is_empty() {
+            Vec::new()
+        } else {
+            self.embedder.embed(documents)?
+        };
+        let model_id = self.embedder.model_id().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        let mut conn = self.conn.lock().unwrap();
+        self.ensure_ann(&conn);
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM chunks WHERE path = ?1", params![path])?;
+        let mut inserted = Vec::with_capacity(chunks.len());
+        for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
+            let source = format!("{}:{}-{}", path, chunk.start_line, chunk.end_line);
+            tx.execute(
+                "INSERT INTO chunks (content, source, embedding_json, embedding, path, start_line, end_line, content_hash, symbol, namespace, model_id, dimension, created_at)
+                 VALUES (?1, ?2, '', ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    chunk.content,
+                    source,
+                    to_blob(&embedding),
+                    path,
+                    chunk.start_line as i64,
+                    chunk.end_line as i64,
+                    content_hash,
+                    chunk.symbol,
+                    CODE_NAMESPACE,
+                    model_id,
+                    embedding.len() as i64,
+                    now,
+                ],
+            )?;
+            let meta = ChunkMeta {
+                namespace: CODE_NAMESPACE.to_string(),
+                source,
+                model_id: model_id.clone(),
+                span: Some(SourceSpan {
+                    path: path.to_string(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                    content_hash: content_hash.to_string(),
+                    symbol: chunk.symbol,
+                }),
+                usage: Usage::new(now),
+            };
+            inserted.push((tx.last_insert_rowid(), meta, embedding));
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO indexed_files (path, content_hash, chunk_count, indexed_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![path, content_hash, inserted.len() as i64, now],
+        )?;
+        bump_generation(&tx)?;
+        tx.commit()?;
+
+        let count = inserted.len();
+        let mut cache = self.chunks.write().unwrap();
+        let removed = remove_path(&mut cache, path);
+        let mut added = Vec::with_capacity(count);
+        for (id, meta, embedding) in inserted {
+            cache.insert(id, meta);
+            added.push((id, embedding));
+        }
+        drop(cache);
+        self.apply_to_ann(&conn, &removed, &added, true);
+        Ok(count)
+    }
+
+    /// Drop an indexed file and its chunks. Like [`Self::replace_file_chunks`]
+    /// this leaves saving the ANN graph to [`Self::save_index`].
+    pub fn remove_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut conn = self.conn.lock().unwrap();
+        self.ensure_ann(&conn);
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM chunks WHERE path = ?1", params![path])?;
+        tx.execute("DELETE FROM indexed_files WHERE path = ?1", params![path])?;
+        bump_generation(&tx)?;
+        tx.commit()?;
+        let removed = remove_path(&mut self.chunks.write().unwrap(), path);
+        self.apply_to_ann(&conn, &removed, &[], true);
+        Ok(())
+    }
+
+    /// Whether `path` was ingested into `namespace` with this content hash
+    /// and all of its chunks are still there (GC may have pruned some).
+    pub fn is_ingested(&self, namespace: &str, path: &str, content_hash: &str) -> bool {
+        let conn = self.conn.lock().unwrap();
+        let recorded: Option<(String, i64)> = conn
+            .query_row(
+                "SELECT content_hash, chunk_count FROM ingested_documents WHERE namespace = ?1 AND path = ?2",
+                params![namespace, path],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .ok();
+        drop(conn);
+        let Some((hash, count)) = recorded else {
+            return false;
+        };
+        let chunks = self.chunks.read().unwrap();
+        let live = chunks
+            .values()
+            .filter(|chunk| chunk.namespace == namespace && is_document_source(&chunk.source, path))
+            .count();
+        hash == content_hash && live as i64 == count
+    }
+
+    /// Replace every chunk of an ingested document with freshly embedded
+    /// `chunks` of `(source, content)`; sources are `path` or
+    /// `path#<location>`. Blocking: embeds on the calling thread. Call
+    /// [`Self::save_index`] after the last document.
+    pub fn replace_document(
+        &self,
+        namespace: &str,
+        path: &str,
+        content_hash: &str,
+        chunks: Vec<(String, String)>,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let documents: Vec<String> = chunks
+            .iter()
+            .map(|(source, content)| embedding_text(Some(source), content))
+            .collect();
+        let embeddings = if documents.is_empty() {
+            Vec::new()
+        } else {
+            self.embedder.embed(documents)?
+        };
+        let model_id = self.embedder.model_id().to_string();
+        let now = chrono::Utc::now().timestamp();
+
+        let mut conn = self.conn.lock().unwrap();
+        self.ensure_ann(&conn);
+        let removed: Vec<i64> = self
+            .chunks
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, chunk)| {
+                chunk.namespace == namespace && is_document_source(&chunk.source, path)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        let tx = conn.transaction()?;
+        for id in &removed {
+            tx.execute("DELETE FROM chunks WHERE id = ?1", params![id])?;
+        }
+        let mut inserted = Vec::with_capacity(chunks.len());
+        for ((source, content), embedding) in chunks.into_iter().zip(embeddings) {
+            tx.execute(
+                "INSERT INTO chunks (content, source, embedding_json, embedding, namespace, model_id, dimension, created_at) VALUES (?1, ?2, '', ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    content,
+                    source,
+                    to_blob(&embedding),
+                    namespace,
+                    model_id,
+                    embedding.len() as i64,
+                    now
+                ],
+            )?;
+            let meta = ChunkMeta {
+                namespace: namespace.to_string(),
+                source,
+                model_id: model_id.clone(),
+                span: None,
+                usage: Usage::new(now),
+            };
+            inserted.push((tx.last_insert_rowid(), meta, embedding));
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO ingested_documents (namespace, path, content_hash, chunk_count, ingested_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![namespace, path, content_hash, inserted.len() as i64, now],
+        )?;
+        bump_generation(&tx)?;
+        tx.commit()?;
+
+        let count = inserted.len();
+        let mut cache = self.chunks.write().unwrap();
+        for id in &removed {
+            cache.remove(id);
+        }
+        let mut added = Vec::with_capacity(count);
+        for (id, meta, embedding) in inserted {
+            cache.insert(id, meta);
+            added.push((id, embedding));
+        }
+        drop(cache);
+        self.apply_to_ann(&conn, &removed, &added, true);
+        Ok(count)
+    }
+
+    /// Write the ANN graph to disk if it has unsaved changes. Bulk writers
+    /// (the code indexer, reindex) call this when they finish; single
+    /// writes are batched and the rest is saved when the store is dropped.
+    pub fn save_index(&self) {
+        let conn = self.conn.lock().unwrap();
+        let mut ann = self.ann.lock().unwrap();
+        self.save_locked(&conn, &mut ann);
+    }
+
+    fn save_locked(&self, conn: &Connection, ann: &mut AnnState) {
+        let Some(index) = &ann.index else {
+            return;
+        };
+        if ann.unsaved == 0 {
+            return;
+        }
+        let result = generation(conn)
+            .map_err(std::io::Error::other)
+            .and_then(|generation| {
+                index.save(&self.ann_path, self.embedder.model_id(), generation)
+            });
+        match result {
+            Ok(()) => ann.unsaved = 0,
+            Err(e) => tracing::warn!("[RAG] Failed to save ANN index: {}", e),
+        }
+    }
+
+    /// Make sure the graph reflects the database before a write changes
+    /// it. Call with `conn` locked; a graph loaded after the write would
+    /// see a newer generation and be rebuilt instead.
+    fn ensure_ann(&self, conn: &Connection) {
+        let mut ann = self.ann.lock().unwrap();
+        if ann.index.is_some() {
+            return;
+        }
+        match self.load_ann(conn) {
+            Ok((index, fresh)) => {
+                ann.index = Some(index);
+                // A rebuilt graph is saved straight away so the next start
+                // can skip the rebuild.
+                ann.unsaved = if fresh { 1 } else { 0 };
+                if fresh {
+                    self.save_locked(conn, &mut ann);
+                }
+            }
+            Err(e) => tracing::warn!("[RAG] Failed to load vectors for ANN index: {}", e),
+        }
+    }
+
+    /// Read the active model's vectors and the persisted graph over them,
+    /// rebuilding the graph when it is missing or stale. The flag is set
+    /// when the graph was rebuilt.
+    fn load_ann(&self, conn: &Connection) -> rusqlite::Result<(HnswIndex, bool)> {
+        let start = Instant::now();
+        let model_id = self.embedder.model_id();
+        let mut vectors = HashMap::new();
+        let mut stmt =
+            conn.prepare("SELECT id, embedding FROM chunks WHERE model_id = ?1 ORDER BY id")?;
+        let rows = stmt.query_map(params![model_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<Vec<u8>>>(1)?))
+        })?;
+        for row in rows {
+            let (id, blob) = row?;
+            if let Some(blob) = blob {
+                vectors.insert(id, from_blob(&blob));
+            }
+        }
+        let generation = generation(conn)?;
+        if let Some(index) = HnswIndex::load(&self.ann_path, model_id, generation, &vectors) {
+            tracing::debug!(
+                "[RAG] Loaded ANN index over {} vectors in {}ms",
+                index.len(),
+                start.elapsed().as_millis()
+            );
+            return Ok((index, false));
+        }
+
+        let dim = vectors.values().next().map(Vec::len).unwrap_or(0);
+        let mut index = HnswIndex::new(dim);
+        let mut ids: Vec<i64> = vectors.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let vector = &vectors[&id];
+            if vector.len() == dim {
+                index.insert(id, vector);
+            }
+        }
+        tracing::info!(
+            "[RAG] Built ANN index over {} vectors in {}ms",
+            index.len(),
+            start.elapsed().as_millis()
+        );
+        Ok((index, true))
+    }
+
+    /// Mirror a committed write into the graph, if one is loaded. Bulk
+    /// writers pass `bulk` and save once when they finish.
+    fn apply_to_ann(
+        &self,
+        conn: &Connection,
+        removed: &[i64],
+        added: &[(i64, Vec<f32>)],
+        bulk: bool,
+    ) {
+        let mut ann = self.ann.lock().unwrap();
+        let Some(index) = ann.index.as_mut() else {
+            return;
+        };
+        for id in removed {
+            index.remove(*id);
+        }
+        for (id, vector) in added {
+            if index.dim() != vector.len() {
+                if !index.is_empty() {
+                    continue;
+                }
+                *index = HnswIndex::new(vector.len());
+            }
+            index.insert(*id, vector);
+        }
+        if index.deleted_ratio() > ANN_MAX_DELETED_RATIO {
+            *index = index.compacted();
+        }
+        ann.unsaved += removed.len() + added.len();
+        if !bulk && ann.unsaved >= ANN_SAVE_EVERY {
+            self.save_locked(conn, &mut ann);
+        }
+    }
+
+    /// The `k` chunks closest to `query_embedding` by cosine similarity.
+    /// Uses the HNSW graph for large stores and an exact scan when the store
+    /// is small or the namespace filter leaves too few chunks for the graph
+    /// to find. Vectors from another model live in a different space and
+    /// are never in the graph.
+    fn vector_candidates(
+        &self,
+        query_embedding: &[f32],
+        k: usize,
+        namespaces: Option<&[String]>,
+    ) -> Vec<(i64, ChunkMeta, f32)> {
+        self.ensure_ann(&self.conn.lock().unwrap());
+        let now = chrono::Utc::now().timestamp();
+        let chunks = self.chunks.read().unwrap();
+        let accept = |id: i64| {
+            chunks
+                .get(&id)
+                .is_some_and(|chunk| self.searchable(chunk, namespaces, now))
+        };
+        let ann = self.ann.lock().unwrap();
+        let Some(index) = &ann.index else {
+            return Vec::new();
+        };
+        let eligible = match namespaces {
+            None => index.len(),
+            Some(_) => chunks.keys().filter(|id| accept(**id)).count(),
+        };
+        let mut found = Vec::new();
+        if index.len() >= ANN_MIN_VECTORS && eligible * ANN_MAX_SELECTIVITY >= index.len() {
+            found = index.search(query_embedding, k, ANN_EF_SEARCH, accept);
+        }
+        if found.len() < k.min(eligible) {
+            found = index.exact(query_embedding, k, accept);
+        }
+        found
+            .into_iter()
+            .filter_map(|(id, score)| chunks.get(&id).map(|meta| (id, meta.clone(), score)))
+            .collect()
+    }
+
+    /// The stored chunk most similar to `text` within `namespaces`, with its
+    /// cosine similarity and content. Used to spot near-duplicates before
+    /// storing something new.
+    pub async fn nearest(
+        self: &Arc<Self>,
+        text: &str,
+        namespaces: Vec<String>,
+    ) -> Result<Option<(i64, f32, String)>, Box<dyn std::error::Error + Send + Sync>> {
+        let store = self.clone();
+        let text = text.to_string();
+        tokio::task::spawn_blocking(move || {
+            let embedding = store
+                .embedder
+                .embed(vec![text])?
+                .into_iter()
+                .next()
+                .ok_or("Embedder returned no vector")?;
+            let Some((id, _, similarity)) = store
+                .vector_candidates(&embedding, 1, Some(&namespaces))
+                .into_iter()
+                .next()
+            else {
+                return Ok(None);
+            };
+            let conn = store.conn.lock().unwrap();
+            let content: String = conn.query_row(
+                "SELECT content FROM chunks WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )?;
+            Ok(Some((id, similarity, content)))
+        })
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?
+    }
+
+    /// Search every namespace with hybrid BM25 + semantic scoring.
+    pub async fn search(
+        self: &Arc<Self>,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<crate::evidence::Evidence>, Box<dyn std::error::Error + Send + Sync>> {
+        let store = self.clone();
+        let query = query.to_string();
+        tokio::task::spawn_blocking(move || store.search_blocking(&query, limit, None, None))
+            .await
+            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?
+    }
+
+    /// Search only the given namespaces.
+    /// Runs embedding computation and SQLite queries on a blocking thread
+    /// to avoid stalling the tokio runtime.
+    pub async fn search_in(
+        self: &Arc<Self>,
+        query: &str,
+        limit: usize,
+        namespaces: Vec<String>,
+    ) -> Result<Vec<crate::evidence::Evidence>, Box<dyn std::error::Error + Send + Sync>> {
+        let store = self.clone();
+        let query = query.to_string();
+        tokio::task::spawn_blocking(move || {
+            store.search_blocking(&query, limit, Some(&namespaces), None)
+        })
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?
+    }
+
+    /// Like [`Self::search_in`], but only chunks whose cosine similarity to
+    /// the query reaches `min_similarity`. For retrieval nobody asked for,
+    /// where nothing beats something unrelated.
+    pub async fn search_relevant(
+        self: &Arc<Self>,
+        query: &str,
+        limit: usize,
+        namespaces: Vec<String>,
+        min_similarity: f32,
+    ) -> Result<Vec<crate::evidence::Evidence>, Box<dyn std::error::Error + Send + Sync>> {
+        let store = self.clone();
+        let query = query.to_string();
+        tokio::task::spawn_blocking(move || {
+            store.search_blocking(&query, limit, Some(&namespaces), Some(min_similarity))
+        })
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { Box::new(e) })?
+    }
+
+    fn search_blocking(
+        &self,
+        query: &str,
+        limit: usize,
+        namespaces: Option<&[String]>,
+        min_similarity: Option<f32>,
+    ) -> Result<Vec<crate::evidence::Evidence>, Box<dyn std::error::Error + Send + Sync>> {
+        // 1. Generate query embedding (CPU-intensive)
+        let query_embedding = self
+            .embedder
+            .embed(vec![query.to_string()])?
+            .into_iter()
+            .next()
+            .ok_or("Embedder returned no vector")?;
+        let linear = self.retrieval.linear_fusion();
+
+        // 2. Keyword Search (FTS5), best first
+        // Lock ordering rule: always conn -> chunks -> ann to avoid deadlocks with writers.
+        let mut keyword_hits: Vec<(i64, f64)> = {
+            let fts_query = if linear {
+                let safe_query = query.replace("\"", "").replace("'", "");
+                Some(format!("\"{}\"", safe_query))
+            } else {
+                ranking::fts_query(query)
+            };
+            match fts_query {
+                Some(fts_query) => {
+                    let conn = self.conn.lock().unwrap();
+                    let mut stmt = conn.prepare(
+                        "SELECT content_rowid, bm25(chunks_fts) FROM chunks_fts WHERE chunks_fts MATCH ?1 ORDER BY bm25(chunks_fts) LIMIT ?2"
+                    )?;
+                    // SQLite BM25 is negative (more negative = better). We invert it.
+                    stmt.query_map(params![fts_query, KEYWORD_CANDIDATES as i64], |row| {
+                        Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?.abs()))
+                    })
+                    .map(|rows| rows.flatten().collect())
+                    .unwrap_or_default()
+                }
+                None => Vec::new(),
+            }
+        };
+
+        // 3. Vector Search
+        let mut vector_hits =
+            self.vector_candidates(&query_embedding, SEARCH_CANDIDATES, namespaces);
+        if let Some(min_similarity) = min_similarity {
+            // Keyword hits outside the vector candidates are further away.
+            vector_hits.retain(|(_, _, similarity)| *similarity >= min_similarity);
+            keyword_hits.retain(|(id, _)| vector_hits.iter().any(|(hit, _, _)| hit == id));
+        }
+
+        // 4. Fusion
+        let now = chrono::Utc::now().timestamp();
+        if linear {
+            let mut results = linear_fusion(vector_hits, &keyword_hits);
+            for (_, meta, score) in results.iter_mut() {
+                *score *= self.usage_weight(meta, now);
+            }
+            results.sort_by(|a, b| b.2.total_cmp(&a.2));
+            results.truncate(limit);
+            return self.to_evidence(results);
+        }
+        let (vector_ids, mut metas): (Vec<i64>, HashMap<i64, ChunkMeta>) = vector_hits
+            .into_iter()
+            .map(|(id, meta, _)| (id, (id, meta)))
+            .unzip();
+        let keyword_ids: Vec<i64> = {
+            let chunks = self.chunks.read().unwrap();
+            keyword_hits
+                .iter()
+                .filter_map(|(id, _)| {
+                    let meta = chunks.get(id)?;
+                    let eligible = self.searchable(meta, namespaces, now);
+                    if eligible {
+                        metas.entry(*id).or_insert_with(|| meta.clone());
+                    }
+                    eligible.then_some(*id)
+                })
+                .collect()
+        };
+        let mut fused = ranking::reciprocal_rank_fusion(&[vector_ids, keyword_ids]);
+        fused.truncate(limit.max(self.retrieval.rerank_candidates()));
+
+        // 5. Cross-encoder rescoring of the head
+        let mut contents = self.contents(fused.iter().map(|(id, _)| *id))?;
+        fused.retain(|(id, _)| contents.contains_key(id));
+        if let Some(reranker) = &self.reranker {
+            let documents = fused
+                .iter()
+                .map(|(id, _)| {
+                    let path = metas[id].span.as_ref().map(|span| span.path.as_str());
+                    embedding_text(path, &contents[id])
+                })
+                .collect();
+            match reranker.score(query, documents) {
+                Ok(scores) => {
+                    for ((_, score), rerank_score) in fused.iter_mut().zip(scores) {
+                        *score = rerank_score;
+                    }
+                    fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+                }
+                Err(e) => tracing::warn!("[RAG] Reranking failed, keeping fused order: {}", e),
+            }
+        }
+        for (id, score) in fused.iter_mut() {
+            *score *= self.usage_weight(&metas[id], now);
+        }
+        fused.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        // 6. Diversity: near-duplicate chunks should not fill every slot.
+        let selected = {
+            let ann = self.ann.lock().unwrap();
+            let vector = |id: i64| ann.index.as_ref().and_then(|index| index.vector_of(id));
+            ranking::mmr(&fused, limit, self.retrieval.mmr_lambda(), |a, b| {
+                match (vector(a), vector(b)) {
+                    // Graph vectors are normalised.
+                    (Some(a), Some(b)) => a.iter().zip(b).map(|(x, y)| x * y).sum(),
+                    _ => 0.0,
+                }
+            })
+        };
+
+        let results = selected
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let meta = metas.remove(&id)?;
+                Some((id, meta, score, contents.remove(&id)?))
+            })
+            .collect();
+        Ok(self.record_hits(results))
+    }
+
+    /// Whether a search may return `chunk`: embedded with the active model,
+    /// in an allowed namespace and not expired.
+    fn searchable(&self, chunk: &ChunkMeta, namespaces: Option<&[String]>, now: i64) -> bool {
+        chunk.model_id == self.embedder.model_id()
+            && namespaces.is_none_or(|allowed| allowed.contains(&chunk.namespace))
+            && !chunk.usage.is_expired(now)
+    }
+
+    /// Recency and usage weight of a memory; indexed code is always 1.0
+    /// since the indexer keeps it current.
+    fn usage_weight(&self, chunk: &ChunkMeta, now: i64) -> f32 {
+        match chunk.span {
+            Some(_) => 1.0,
+            None => chunk.usage.weight(now, self.retention.half_life_days()),
+        }
+    }
+
+    /// Count a hit for every returned chunk, then wrap them as evidence.
+    fn record_hits(
+        &self,
+        results: Vec<(i64, ChunkMeta, f32, String)>,
+    ) -> Vec<crate::evidence::Evidence> {
+        let now = chrono::Utc::now().timestamp();
+        let conn = self.conn.lock().unwrap();
+        let updated = conn
+            .prepare("UPDATE chunks SET last_hit_at = ?1, hit_count = hit_count + 1 WHERE id = ?2")
+            .and_then(|mut stmt| {
+                for (id, _, _, _) in &results {
+                    stmt.execute(params![now, id])?;
+                }
+                Ok(())
+            });
+        match updated {
+            Ok(()) => {
+                let mut chunks = self.chunks.write().unwrap();
+                for (id, _, _, _) in &results {
+                    if let Some(chunk) = chunks.get_mut(id) {
+                        chunk.usage.last_hit_at = Some(now);
+                        chunk.usage.hit_count += 1;
+                    }
+                }
+            }
+            Err(e) => tracing::warn!("[RAG] Failed to record search hits: {}", e),
+        }
+        evidence(results)
+    }
+
+    /// Content of the chunks among `ids` that still exist.
+    fn contents(
+        &self,
+        ids: impl Iterator<Item = i64>,
+    ) -> Result<HashMap<i64, String>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT content FROM chunks WHERE id = ?1")?;
+        let mut contents = HashMap::new();
+        for id in ids {
+            if let Ok(content) = stmt.query_row(params![id], |row| row.get::<_, String>(0)) {
+                contents.insert(id, content);
+            }
+        }
+        Ok(contents)
+    }
+
+    /// Fetch content for ranked results and wrap them as evidence.
+    fn to_evidence(
+        &self,
+        results: Vec<(i64, ChunkMeta, f32)>,
+    ) -> Result<Vec<crate::evidence::Evidence>, Box<dyn std::error::Error + Send + Sync>> {
+        // Content is only read for the chunks actually returned.
+        let mut contents = self.contents(results.iter().map(|(id, _, _)| *id))?;
+        let results = results
+            .into_iter()
+            // Deleted between ranking and fetching.
+            .filter_map(|(id, meta, score)| Some((id, meta, score, contents.remove(&id)?)))
+            .collect();
+        Ok(self.record_hits(results))
+    }
+}
+
+/// Search results as evidence for the model.
+fn evidence(results: Vec<(i64, ChunkMeta, f32, String)>) -> Vec<crate::evidence::Evidence> {
+    let mut structured_evidence = Vec::new();
+    for (id, chunk, score, content) in results {
+        let evidence_id = format!("rag_{}", id);
+        let mut ev = match &chunk.span {
+            Some(span) => crate::evidence::Evidence::new(
+                evidence_id,
+                "code".to_string(),
+                chunk.source.clone(),
+                score,
+                match &span.symbol {
+                    Some(symbol) => format!(
+                        "Code from {} lines {}-{} ({})",
+                        span.path, span.start_line, span.end_line, symbol
+                    ),
+                    None => format!(
+                        "Code from {} lines {}-{}",
+                        span.path, span.start_line, span.end_line
+                    ),
+                },
+                content,
+            ),
+            None => crate::evidence::Evidence::new(
+                evidence_id,
+                "memory".to_string(),
+                chunk.source.clone(),
+                score,
+                format!("RAG chunk from {}", chunk.source),
+                content,
+            ),
+        };
+        if let Some(span) = &chunk.span {
+            ev.source_version = Some(format!("sha256:{}", span.content_hash));
+        }
+        structured_evidence.push(ev);
+    }
+
+    structured_evidence
+}
+
+/// The original hybrid scoring: a weighted sum of normalised cosine and BM25
+/// over the vector candidates. Keyword-only hits are not surfaced.
+fn linear_fusion(
+    vector_hits: Vec<(i64, ChunkMeta, f32)>,
+    keyword_hits: &[(i64, f64)],
+) -> Vec<(i64, ChunkMeta, f32)> {
+    let vector_weight = 0.7;
+    let keyword_weight = 0.3;
+    let fts_scores: HashMap<i64, f64> = keyword_hits.iter().copied().collect();
+
+    // Find max scores for normalization
+    let max_vector_score = vector_hits.first().map(|s| s.2).unwrap_or(1.0).max(0.001);
+    let max_keyword_score = fts_scores
+        .values()
+        .fold(0.0f64, |a, &b| a.max(b))
+        .max(0.001);
+
+    let mut results: Vec<(i64, ChunkMeta, f32)> = vector_hits
+        .into_iter()
+        .map(|(id, meta, v_score)| {
+            let normalized_v = v_score.max(0.0) / max_vector_score;
+            let normalized_k = fts_scores.get(&id).copied().unwrap_or(0.0) / max_keyword_score;
+            let final_score =
+                (normalized_v * vector_weight) + (normalized_k as f32 * keyword_weight);
+            (id, meta, final_score)
+        })
+        .collect();
+    results.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    results
+}
+
+impl Drop for VectorStore {
+    fn drop(&mut self) {
+        self.save_index();
+    }
+}
+
+/// Convert `embedding_json` rows to little-endian f32 blobs, clearing the
+/// JSON to reclaim space.
+fn migrate_embeddings_to_blobs(conn: &mut Connection) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut select =
+            tx.prepare("SELECT id, embedding_json FROM chunks WHERE embedding IS NULL")?;
+        let mut update =
+            tx.prepare("UPDATE chunks SET embedding = ?1, embedding_json = '' WHERE id = ?2")?;
+        let rows: Vec<(i64, String)> = select
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        for (id, json) in rows {
+            let embedding: Vec<f32> = serde_json::from_str(&json).unwrap_or_default();
+            update.execute(params![to_blob(&embedding), id])?;
+        }
+    }
+    tx.commit()
+}
+
+fn to_blob(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn generation(conn: &Connection) -> rusqlite::Result<u64> {
+    match conn.query_row(
+        "SELECT value FROM rag_meta WHERE key = 'generation'",
+        [],
+        |row| row.get::<_, i64>(0),
+    ) {
+        Ok(value) => Ok(value as u64),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+fn bump_generation(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO rag_meta (key, value) VALUES ('generation', 1)
+         ON CONFLICT(key) DO UPDATE SET value = value + 1",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Remove the cached chunks of an indexed file, returning their ids.
+fn remove_path(chunks: &mut BTreeMap<i64, ChunkMeta>, path: &str) -> Vec<i64> {
+    let ids: Vec<i64> = chunks
+        .iter()
+        .filter(|(_, chunk)| chunk.span.as_ref().is_some_and(|span| span.path == path))
+        .map(|(id, _)| *id)
+        .collect();
+    for id in &ids {
+        chunks.remove(id);
+    }
+    ids
+}
+
+/// Whether `source` names a chunk of the ingested document `path`.
+fn is_document_source(source: &str, path: &str) -> bool {
+    source
+        .strip_prefix(path)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('#'))
+}
+
+/// Attack phrasings (one per line) the embedding detector compares against.
+const INJECTION_CORPUS: &str = include_str!("../injection_corpus.txt");
+/// Cosine similarity at which a line counts as a paraphrase of the corpus.
+const INJECTION_LINE_SIMILARITY: f32 = 0.72;
+/// Similarities at or below this floor map to a score of zero.
+const INJECTION_SIMILARITY_FLOOR: f32 = 0.45;
+/// Upper bound on lines embedded per document, to keep screening cheap.
+const INJECTION_MAX_LINES: usize = 200;
+const INJECTION_MAX_LINE_CHARS: usize = 400;
+
+/// Local prompt-injection classifier: embeds each line of the content with
+/// the RAG model and scores it by nearest-neighbour similarity to a bundled
+/// attack corpus.  Catches paraphrases and non-English attacks the phrase
+/// list misses; the heuristic score is kept as a floor.
+pub struct EmbeddingInjectionDetector {
+    embedder: Arc<dyn Embedder>,
+    corpus: Vec<(String, Vec<f32>)>,
+}
+
+impl EmbeddingInjectionDetector {
+    pub fn new(
+        embedder: Arc<dyn Embedder>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let phrases: Vec<String> = INJECTION_CORPUS
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        let embeddings = embedder.embed(phrases.clone())?;
+        Ok(Self {
+            embedder,
+            corpus: phrases.into_iter().zip(embeddings).collect(),
+        })
+    }
+
+    fn nearest(&self, embedding: &[f32]) -> (f32, &str) {
+        self.corpus
+            .iter()
+            .map(|(phrase, corpus_embedding)| {
+                (
+                    cosine_similarity(corpus_embedding, embedding),
+                    phrase.as_str(),
+                )
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap_or((0.0, ""))
+    }
+}
+
+impl InjectionDetector for EmbeddingInjectionDetector {
+    fn name(&self) -> &'static str {
+        "embedding"
+    }
+
+    fn score(&self, text: &str) -> crate::security::InjectionScore {
+        let mut result = crate::security::HeuristicDetector.score(text);
+
+        let candidates: Vec<(usize, String)> = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.trim().chars().count() >= 12)
+            .take(INJECTION_MAX_LINES)
+            .map(|(idx, line)| {
+                (
+                    idx,
+                    line.trim().chars().take(INJECTION_MAX_LINE_CHARS).collect(),
+                )
+            })
+            .collect();
+        if candidates.is_empty() {
+            return result;
+        }
+
+        let documents: Vec<String> = candidates.iter().map(|(_, line)| line.clone()).collect();
+        let embeddings = match self.embedder.embed(documents) {
+            Ok(embeddings) => embeddings,
+            Err(e) => {
+                tracing::warn!("[Security] Embedding injection detector failed: {}", e);
+                return result;
+            }
+        };
+
+        let mut best = 0.0f32;
+        for ((idx, _), embedding) in candidates.iter().zip(embeddings.iter()) {
+            let (similarity, phrase) = self.nearest(embedding);
+            best = best.max(similarity);
+            if similarity >= INJECTION_LINE_SIMILARITY {
+                if !result.lines.contains(idx) {
+                    result.lines.push(*idx);
+                }
+                let label = format!("~\"{}\"", phrase);
+                if !result.matched.contains(&label) {
+                    result.matched.push(label);
+                }
+            }
+        }
+        result.lines.sort_unstable();
+
+        let semantic = ((best - INJECTION_SIMILARITY_FLOOR) / (1.0 - INJECTION_SIMILARITY_FLOOR))
+            .clamp(0.0, 1.0);
+        result.score = result.score.max(semantic);
+        result
+    }
+}
+
+/// The text embedded for a chunk. Indexed code carries its path so that
+/// queries naming a file land on it.
+fn embedding_text(path: Option<&str>, content: &str) -> String {
+    match path {
+        Some(path) => format!("{}\n{}", path, content),
+        None => content.to_string(),
+    }
+}
+
+// Helper to calculate cosine similarity
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let mut dot_product = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+
+    for i in 0..a.len() {
+        dot_product += a[i] * b[i];
+        norm_a += a[i] * a[i];
+        norm_b += b[i] * b[i];
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot_product / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::embedder::HashedWordsEmbedder;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rag_hybrid_search() {
+        let _ = std::fs::remove_file(".rusty_claw_memory.db");
+        let store = Arc::new(VectorStore::new().expect("Failed to init"));
+
+        store
+            .insert_chunk("Rust is fast.".to_string(), "doc1".to_string())
+            .await
+            .unwrap();
+        store
+            .insert_chunk("Python is easy.".to_string(), "doc2".to_string())
+            .await
+            .unwrap();
+        store
+            .insert_chunk("The rusty claw grabs.".to_string(), "doc3".to_string())
+            .await
+            .unwrap();
+
+        // 1. Vector match (semantic)
+        let res = store.search("speed performance", 1).await.unwrap();
+        assert_eq!(res[0].source_path, "doc1");
+
+        // 2. Keyword match (exact)
+        let res2 = store.search("rusty claw", 1).await.unwrap();
+        assert_eq!(res2[0].source_path, "doc3");
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_upsert_and_delete_keep_search_consistent() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(
+            VectorStore::open(&dir.path().join("ns.db"), Arc::new(HashedWordsEmbedder)).unwrap(),
+        );
+        store
+            .insert_chunk("Python is easy.".to_string(), "doc2".to_string())
+            .await
+            .unwrap();
+        store
+            .insert_chunk_in("chat:1".into(), "Tea is brewed.".into(), "pref".into())
+            .await
+            .unwrap();
+        let (id, replaced) = store
+            .upsert_chunk("chat:1".into(), "Coffee is brewed.".into(), "pref".into())
+            .await
+            .unwrap();
+        assert_eq!(replaced, 1);
+        let scoped = store
+            .search_in("brewed", 5, vec!["chat:1".into()])
+            .await
+            .unwrap();
+        assert_eq!(scoped.len(), 1);
+        assert!(scoped[0].content.contains("Coffee"));
+        assert_eq!(store.list_chunks("chat:1", 10).len(), 1);
+        assert!(store.delete_by_id(id).unwrap());
+        assert!(store
+            .search_in("brewed", 5, vec!["chat:1".into()])
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.delete_by_source(DEFAULT_NAMESPACE, "doc2").unwrap(),
+            1
+        );
+        assert!(store.namespaces().iter().all(|(ns, _)| ns != "chat:1"));
+    }
+
+    #[tokio::test]
+    async fn test_hits_expiry_and_gc() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(
+            VectorStore::open(&dir.path().join("gc.db"), Arc::new(HashedWordsEmbedder))
+                .unwrap()
+                .with_retention(RetentionConfig {
+                    max_chunks: Some(1),
+                    min_age_days: Some(0),
+                    ..Default::default()
+                }),
+        );
+        let kept = store
+            .insert_chunk_in("chat:1".into(), "The build uses cargo.".into(), "a".into())
+            .await
+            .unwrap();
+        let unused = store
+            .insert_chunk_in(
+                "chat:1".into(),
+                "Deploys happen on Fridays.".into(),
+                "b".into(),
+            )
+            .await
+            .unwrap();
+        let temporary = store
+            .insert_chunk_in(
+                "chat:1".into(),
+                "The build is broken today.".into(),
+                "c".into(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(store.search("cargo build", 1).await.unwrap().len(), 1);
+        assert!(store.set_expiry(temporary, Some(0)).unwrap());
+        let found = store.search("build", 1).await.unwrap();
+        assert_eq!(found[0].evidence_id, format!("rag_{}", kept));
+
+        let stats = store.stats();
+        assert_eq!(stats.memory_chunks, 3);
+        assert_eq!(stats.namespaces[0].expiring, 1);
+        assert!(stats.namespaces[0].hits >= 2);
+
+        // Expired goes; then one over budget, and only `unused` was never hit.
+        let report = store.gc().unwrap();
+        assert_eq!(
+            report,
+            GcReport {
+                expired: 1,
+                pruned: 1
+            }
+        );
+        assert!(store.chunk_namespace(temporary).is_none());
+        assert!(store.chunk_namespace(unused).is_none());
+        assert!(store.chunk_namespace(kept).is_some());
+        assert_eq!(store.stats().last_gc.unwrap().1, report);
+
+        // Usage survives a reopen.
+        drop(store);
+        let store =
+            VectorStore::open(&dir.path().join("gc.db"), Arc::new(HashedWordsEmbedder)).unwrap();
+        assert!(store.stats().namespaces[0].hits >= 2);
+    }
+
+    #[derive(Deserialize)]
+    struct EvalFixture {
+        documents: Vec<EvalDocument>,
+        queries: Vec<EvalQuery>,
+    }
+
+    #[derive(Deserialize)]
+    struct EvalDocument {
+        id: String,
+        text: String,
+    }
+
+    #[derive(Deserialize)]
+    struct EvalQuery {
+        query: String,
+        relevant: Vec<String>,
+    }
+
+    /// Recall@3 and MRR over the fixture with the given fusion mode.
+    async fn evaluate(fixture: &EvalFixture, fusion: &str) -> (f32, f32) {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RetrievalConfig {
+            fusion: Some(fusion.to_string()),
+            ..Default::default()
+        };
+        let store = Arc::new(
+            VectorStore::open(&dir.path().join("eval.db"), Arc::new(HashedWordsEmbedder))
+                .unwrap()
+                .with_retrieval(config, None),
+        );
+        for doc in &fixture.documents {
+            store
+                .insert_chunk(doc.text.clone(), doc.id.clone())
+                .await
+                .unwrap();
+        }
+        let (mut recall, mut reciprocal_rank) = (0.0, 0.0);
+        for query in &fixture.queries {
+            let results = store.search(&query.query, 10).await.unwrap();
+            let rank = results
+                .iter()
+                .position(|ev| query.relevant.contains(&ev.source_path));
+            if rank.is_some_and(|rank| rank < 3) {
+                recall += 1.0;
+            }
+            if let Some(rank) = rank {
+                reciprocal_rank += 1.0 / (rank as f32 + 1.0);
+            }
+        }
+        let n = fixture.queries.len() as f32;
+        (recall / n, reciprocal_rank / n)
+    }
+
+    #[tokio::test]
+    async fn test_retrieval_eval_fixture() {
+        let fixture: EvalFixture =
+            serde_json::from_str(include_str!("retrieval_eval.json")).unwrap();
+        let (linear_recall, linear_mrr) = evaluate(&fixture, "linear").await;
+        let (rrf_recall, rrf_mrr) = evaluate(&fixture, "rrf").await;
+        println!(
+            "linear: recall@3 {:.2} MRR {:.2} / rrf: recall@3 {:.2} MRR {:.2}",
+            linear_recall, linear_mrr, rrf_recall, rrf_mrr
+        );
+        assert!(rrf_recall > linear_recall);
+        assert!(rrf_mrr > linear_mrr);
+    }
+}
@@ -0,0 +1,230 @@
+//! Query construction and result fusion for hybrid search.
+//!
+//! Keyword and vector hits are ranked independently and merged with
+//! reciprocal-rank fusion, which needs no score calibration between BM25 and
+//! cosine. An optional cross-encoder ([`super::rerank`]) then rescores the
+//! head of the list, and maximal marginal relevance picks the final results
+//! so near-duplicate chunks do not crowd out everything else.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Rank offset in RRF; 60 is the value from the original paper and damps
+/// the influence of the very top ranks.
+pub const RRF_K: f32 = 60.0;
+const DEFAULT_RERANK_CANDIDATES: usize = 20;
+const DEFAULT_MMR_LAMBDA: f32 = 0.7;
+const DEFAULT_AUTO_TOP_K: usize = 5;
+const DEFAULT_AUTO_MIN_SIMILARITY: f32 = 0.5;
+/// Fits the default 3000-character `retrieved_memory` section cap.
+const DEFAULT_AUTO_MAX_TOKENS: usize = 700;
+/// Most terms put in one FTS query.
+const MAX_QUERY_TERMS: usize = 16;
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "do", "does", "for", "from", "how", "in",
+    "is", "it", "of", "on", "or", "that", "the", "this", "to", "was", "what", "when", "where",
+    "which", "who", "why", "with",
+];
+
+/// `[retrieval]` in `config.toml`.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct RetrievalConfig {
+    /// fastembed cross-encoder such as "BAAI/bge-reranker-base"; no
+    /// reranking when unset.
+    pub reranker: Option<String>,
+    /// Fused candidates the reranker rescores (default 20).
+    pub rerank_candidates: Option<usize>,
+    /// MMR trade-off: 1.0 ranks by relevance only, lower values favour
+    /// diversity (default 0.7).
+    pub mmr_lambda: Option<f32>,
+    /// "rrf" (default) or "linear", the earlier weighted sum over a phrase
+    /// query, kept for comparison.
+    pub fusion: Option<String>,
+    /// Search memories and code with each user message and put the hits
+    /// in the prompt (default true).
+    pub auto: Option<bool>,
+    /// Most chunks retrieved per message (default 5).
+    pub auto_top_k: Option<usize>,
+    /// Cosine similarity a chunk needs to be retrieved (default 0.5).
+    pub auto_min_similarity: Option<f32>,
+    /// Token budget of the retrieved section (default 700).
+    pub auto_max_tokens: Option<usize>,
+}
+
+impl RetrievalConfig {
+    pub fn rerank_candidates(&self) -> usize {
+        self.rerank_candidates
+            .unwrap_or(DEFAULT_RERANK_CANDIDATES)
+            .max(1)
+    }
+
+    pub fn mmr_lambda(&self) -> f32 {
+        self.mmr_lambda
+            .unwrap_or(DEFAULT_MMR_LAMBDA)
+            .clamp(0.0, 1.0)
+    }
+
+    pub fn auto(&self) -> bool {
+        self.auto.unwrap_or(true)
+    }
+
+    pub fn auto_top_k(&self) -> usize {
+        self.auto_top_k.unwrap_or(DEFAULT_AUTO_TOP_K)
+    }
+
+    pub fn auto_min_similarity(&self) -> f32 {
+        self.auto_min_similarity
+            .unwrap_or(DEFAULT_AUTO_MIN_SIMILARITY)
+    }
+
+    pub fn auto_max_tokens(&self) -> usize {
+        self.auto_max_tokens.unwrap_or(DEFAULT_AUTO_MAX_TOKENS)
+    }
+
+    pub fn linear_fusion(&self) -> bool {
+        self.fusion
+            .as_deref()
+            .is_some_and(|fusion| fusion.eq_ignore_ascii_case("linear"))
+    }
+}
+
+/// FTS5 query matching any term of `query` by prefix. Identifiers are also
+/// split into their words (`VectorStore`, `vector_store` → `vector`,
+/// `store`) so code and prose find each other. `None` when nothing
+/// searchable is left.
+pub fn fts_query(query: &str) -> Option<String> {
+    let mut terms: Vec<String> = Vec::new();
+    let mut push = |term: String| {
+        if term.chars().count() >= 2
+            && !STOPWORDS.contains(&term.as_str())
+            && !terms.contains(&term)
+        {
+            terms.push(term);
+        }
+    };
+    for token in query.split(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        let parts = identifier_words(token);
+        push(token.replace('_', "").to_lowercase());
+        if parts.len() > 1 {
+            for part in parts {
+                push(part);
+            }
+        }
+    }
+    terms.truncate(MAX_QUERY_TERMS);
+    if terms.is_empty() {
+        return None;
+    }
+    Some(
+        terms
+            .iter()
+            .map(|term| format!("\"{}\"*", term))
+            .collect::<Vec<_>>()
+            .join(" OR "),
+    )
+}
+
+/// Lowercase words of a snake_case or camelCase identifier.
+fn identifier_words(token: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for piece in token.split('_').filter(|piece| !piece.is_empty()) {
+        let mut word = String::new();
+        let mut prev_lower = false;
+        for c in piece.chars() {
+            if c.is_uppercase() && prev_lower && !word.is_empty() {
+                words.push(std::mem::take(&mut word).to_lowercase());
+            }
+            prev_lower = c.is_lowercase() || c.is_ascii_digit();
+            word.push(c);
+        }
+        if !word.is_empty() {
+            words.push(word.to_lowercase());
+        }
+    }
+    words
+}
+
+/// Merge ranked id lists by reciprocal rank, normalised so an id ranked
+/// first in every list scores 1.0. Sorted best first.
+pub fn reciprocal_rank_fusion(lists: &[Vec<i64>]) -> Vec<(i64, f32)> {
+    let mut scores: HashMap<i64, f32> = HashMap::new();
+    for list in lists {
+        for (rank, id) in list.iter().enumerate() {
+            *scores.entry(*id).or_insert(0.0) += 1.0 / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+    let best = lists.len().max(1) as f32 / (RRF_K + 1.0);
+    let mut fused: Vec<(i64, f32)> = scores
+        .into_iter()
+        .map(|(id, score)| (id, score / best))
+        .collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    fused
+}
+
+/// Pick `limit` of `candidates` (id, relevance in 0..=1, best first) by
+/// maximal marginal relevance. `similarity` compares two candidates.
+pub fn mmr(
+    candidates: &[(i64, f32)],
+    limit: usize,
+    lambda: f32,
+    similarity: impl Fn(i64, i64) -> f32,
+) -> Vec<(i64, f32)> {
+    let mut remaining: Vec<(i64, f32)> = candidates.to_vec();
+    let mut selected: Vec<(i64, f32)> = Vec::new();
+    while selected.len() < limit && !remaining.is_empty() {
+        let (best, _) = remaining
+            .iter()
+            .enumerate()
+            .map(|(idx, (id, relevance))| {
+                let redundancy = selected
+                    .iter()
+                    .map(|(chosen, _)| similarity(*id, *chosen))
+                    .fold(0.0f32, f32::max);
+                (idx, lambda * relevance - (1.0 - lambda) * redundancy)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+            .expect("remaining is not empty");
+        selected.push(remaining.remove(best));
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_fusion_and_diversity() {
+        assert_eq!(
+            fts_query("How does the VectorStore's upsert_chunk work?").unwrap(),
+            "\"vectorstore\"* OR \"vector\"* OR \"store\"* OR \"upsertchunk\"* OR \"upsert\"* OR \"chunk\"* OR \"work\"*"
+        );
+        assert_eq!(fts_query("\"it\" -- is; a"), None);
+        assert_eq!(
+            fts_query("HTTPServer v2"),
+            Some("\"httpserver\"* OR \"v2\"*".to_string())
+        );
+
+        let fused = reciprocal_rank_fusion(&[vec![1, 2, 3], vec![3, 4]]);
+        assert_eq!(fused[0].0, 3); // in both lists
+        assert_eq!(fused[1].0, 1);
+        assert!(fused[0].1 <= 1.0);
+        assert_eq!(reciprocal_rank_fusion(&[vec![7], vec![7]])[0].1, 1.0);
+
+        // 1 and 2 are near-duplicates; MMR swaps 2 for the distinct 3.
+        let candidates = [(1, 1.0), (2, 0.95), (3, 0.8)];
+        let same = |a: i64, b: i64| if a + b == 3 { 0.99 } else { 0.1 };
+        let picked: Vec<i64> = mmr(&candidates, 2, 0.7, same)
+            .iter()
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(picked, vec![1, 3]);
+        let picked: Vec<i64> = mmr(&candidates, 2, 1.0, same)
+            .iter()
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(picked, vec![1, 2]);
+    }
+}
@@ -0,0 +1,70 @@
+//! Cross-encoder reranking for the head of a search.
+//!
+//! A cross-encoder reads query and document together, which ranks far
+//! better than comparing two independently made vectors but costs a model
+//! call per document, so the store only applies it to the top fused
+//! candidates.
+
+use std::sync::Mutex;
+
+use fastembed::{RerankInitOptions, RerankerModel, TextRerank};
+
+type EmbedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Scores how well each document answers a query. Calls block; the store
+/// runs them on blocking threads.
+pub trait Reranker: Send + Sync {
+    fn model_id(&self) -> &str;
+    /// One relevance score in 0..=1 per document, in input order.
+    fn score(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>, EmbedError>;
+}
+
+/// Local ONNX cross-encoder through fastembed.
+pub struct FastReranker {
+    model: Mutex<TextRerank>,
+    model_id: String,
+}
+
+impl FastReranker {
+    /// Load `model` by model code (e.g. `BAAI/bge-reranker-base`), defaulting
+    /// to bge-reranker-base.
+    pub fn new(model: Option<&str>) -> Result<Self, EmbedError> {
+        let choice: RerankerModel = match model {
+            None => RerankerModel::BGERerankerBase,
+            Some(name) => name.parse()?,
+        };
+        let code = TextRerank::get_model_info(&choice).model_code;
+        let model =
+            TextRerank::try_new(RerankInitOptions::new(choice).with_show_download_progress(false))
+                .map_err(|e| format!("Failed to load reranker {}: {}", code, e))?;
+        Ok(Self {
+            model: Mutex::new(model),
+            model_id: format!("fastembed:{}", code),
+        })
+    }
+}
+
+impl Reranker for FastReranker {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn score(&self, query: &str, documents: Vec<String>) -> Result<Vec<f32>, EmbedError> {
+        let docs: Vec<&str> = documents.iter().map(String::as_str).collect();
+        let results = self
+            .model
+            .lock()
+            .unwrap()
+            .rerank(query, docs.as_slice(), false, None)
+            .map_err(|e| e.to_string())?;
+        // Results come back sorted by score; put them back in input order
+        // and squash the raw logits into 0..=1.
+        let mut scores = vec![0.0; documents.len()];
+        for result in results {
+            if let Some(slot) = scores.get_mut(result.index) {
+                *slot = 1.0 / (1.0 + (-result.score).exp());
+            }
+        }
+        Ok(scores)
+    }
+}
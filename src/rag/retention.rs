@@ -0,0 +1,186 @@
+//! Ageing and pruning of remembered chunks.
+//!
+//! Every chunk records when it was stored, when a search last returned it
+//! and how often, plus an optional expiry. Search weights memories by how
+//! recently and how often they were useful, and a periodic garbage
+//! collection drops expired chunks and, when the store is over budget,
+//! old ones that were never retrieved. Indexed code is left alone: the
+//! indexer already replaces it whenever the file changes.
+
+use serde::{Deserialize, Serialize};
+
+const DAY_SECS: f32 = 86_400.0;
+const DEFAULT_MAX_CHUNKS: usize = 20_000;
+const DEFAULT_MIN_AGE_DAYS: u32 = 30;
+const DEFAULT_HALF_LIFE_DAYS: f32 = 90.0;
+const DEFAULT_GC_INTERVAL_HOURS: u64 = 6;
+/// Hits after which more use no longer raises a chunk's weight.
+const HIT_SATURATION: f32 = 10.0;
+/// Weight of a memory that is old and was never retrieved; fresh or often
+/// used ones approach 1.0.
+const MIN_WEIGHT: f32 = 0.8;
+
+/// `[retention]` in `config.toml`.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct RetentionConfig {
+    /// Memory chunks (everything outside the `code` namespace) kept before
+    /// never-retrieved ones are pruned (default 20000).
+    pub max_chunks: Option<usize>,
+    /// Never prune chunks younger than this (default 30 days).
+    pub min_age_days: Option<u32>,
+    /// Days after which an unused memory has lost half its recency weight
+    /// (default 90).
+    pub half_life_days: Option<f32>,
+    /// Hours between garbage collections; 0 disables them (default 6).
+    pub gc_interval_hours: Option<u64>,
+}
+
+impl RetentionConfig {
+    pub fn max_chunks(&self) -> usize {
+        self.max_chunks.unwrap_or(DEFAULT_MAX_CHUNKS)
+    }
+
+    pub fn min_age_secs(&self) -> i64 {
+        self.min_age_days.unwrap_or(DEFAULT_MIN_AGE_DAYS) as i64 * DAY_SECS as i64
+    }
+
+    pub fn half_life_days(&self) -> f32 {
+        self.half_life_days
+            .unwrap_or(DEFAULT_HALF_LIFE_DAYS)
+            .max(1.0)
+    }
+
+    pub fn gc_interval(&self) -> Option<std::time::Duration> {
+        match self.gc_interval_hours.unwrap_or(DEFAULT_GC_INTERVAL_HOURS) {
+            0 => None,
+            hours => Some(std::time::Duration::from_secs(hours * 3600)),
+        }
+    }
+}
+
+/// Usage bookkeeping of one chunk; times are Unix seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub created_at: i64,
+    pub last_hit_at: Option<i64>,
+    pub hit_count: u32,
+    pub expires_at: Option<i64>,
+}
+
+impl Usage {
+    pub fn new(created_at: i64) -> Self {
+        Self {
+            created_at,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Search weight in `MIN_WEIGHT..=1.0`: decays with the time since the
+    /// chunk was stored or last retrieved, and grows with its hit count.
+    pub fn weight(&self, now: i64, half_life_days: f32) -> f32 {
+        let last_used = self.last_hit_at.unwrap_or(self.created_at);
+        let idle_days = (now - last_used).max(0) as f32 / DAY_SECS;
+        let recency = 0.5f32.powf(idle_days / half_life_days);
+        let usage = ((1.0 + self.hit_count as f32).ln() / (1.0 + HIT_SATURATION).ln()).min(1.0);
+        MIN_WEIGHT + (1.0 - MIN_WEIGHT) * (0.75 * recency + 0.25 * usage)
+    }
+}
+
+/// What one garbage collection removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct GcReport {
+    pub expired: usize,
+    pub pruned: usize,
+}
+
+/// Ids to delete from `memories` (id and usage of every chunk outside the
+/// `code` namespace): all expired ones, then never-retrieved ones older
+/// than the minimum age, oldest first, until at most `max_chunks` remain.
+pub fn plan_gc(
+    memories: &[(i64, Usage)],
+    now: i64,
+    config: &RetentionConfig,
+) -> (Vec<i64>, GcReport) {
+    let mut doomed: Vec<i64> = memories
+        .iter()
+        .filter(|(_, usage)| usage.is_expired(now))
+        .map(|(id, _)| *id)
+        .collect();
+    let expired = doomed.len();
+
+    let excess = (memories.len() - expired).saturating_sub(config.max_chunks());
+    let mut unused: Vec<&(i64, Usage)> = memories
+        .iter()
+        .filter(|(_, usage)| {
+            !usage.is_expired(now)
+                && usage.hit_count == 0
+                && now - usage.created_at >= config.min_age_secs()
+        })
+        .collect();
+    unused.sort_by_key(|(id, usage)| (usage.created_at, *id));
+    doomed.extend(unused.iter().take(excess).map(|(id, _)| *id));
+
+    let report = GcReport {
+        expired,
+        pruned: doomed.len() - expired,
+    };
+    (doomed, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights_and_gc_plan() {
+        let now = 1_000 * DAY_SECS as i64;
+        let day = DAY_SECS as i64;
+        let fresh = Usage::new(now);
+        let stale = Usage::new(now - 180 * day);
+        let used = Usage {
+            last_hit_at: Some(now - day),
+            hit_count: 20,
+            ..stale
+        };
+        assert!((fresh.weight(now, 90.0) - 0.95).abs() < 1e-3);
+        assert!(stale.weight(now, 90.0) < 0.85);
+        assert!(stale.weight(now, 90.0) >= MIN_WEIGHT);
+        assert!(used.weight(now, 90.0) > 0.99);
+
+        let config = RetentionConfig {
+            max_chunks: Some(2),
+            ..Default::default()
+        };
+        let expired = Usage {
+            expires_at: Some(now - 1),
+            ..fresh
+        };
+        let memories = [
+            (1, stale),
+            (2, Usage::new(now - 200 * day)),
+            (3, used),
+            (4, fresh),
+            (5, expired),
+        ];
+        // 4 left after expiry, 2 over budget: the two oldest never-used
+        // ones go; the used and the fresh one stay.
+        let (doomed, report) = plan_gc(&memories, now, &config);
+        assert_eq!(doomed, vec![5, 2, 1]);
+        assert_eq!(
+            report,
+            GcReport {
+                expired: 1,
+                pruned: 2
+            }
+        );
+
+        // Within budget only expired chunks go.
+        let (doomed, report) = plan_gc(&memories, now, &RetentionConfig::default());
+        assert_eq!(doomed, vec![5]);
+        assert_eq!(report.pruned, 0);
+    }
+}
@@ -2,14 +2,16 @@
 //!
 //! Implements `ExecutionExtension` to inject `SandboxEnforcer` into every
 //! `ToolContext` via the `enrich_tool_context` hook and inject sandbox
-//! constraints into the system prompt via `before_prompt_build`.
+//! constraints into the system prompt via `before_prompt_build`. The
+//! enforcer is read from a shared `SessionSandbox`, so policies tightened by
+//! an active skill take effect on the next tool call.
 
 use async_trait::async_trait;
 use std::sync::Arc;
 
 use crate::core::extensions::{ExecutionExtension, ExtensionDecision, FinishDecision, PromptDraft};
 use crate::tools::protocol::ToolExecutionEnvelope;
-use crate::tools::sandbox::SandboxEnforcer;
+use crate::tools::sandbox::SessionSandbox;
 use crate::tools::{Tool, ToolContext};
 
 /// An `ExecutionExtension` that wires sandbox enforcement into the agent loop.
@@ -18,12 +20,12 @@ use crate::tools::{Tool, ToolContext};
 /// - Injects sandbox constraint summary into the system prompt (so the LLM
 ///   knows its boundaries upfront).
 pub struct SandboxExtension {
-    enforcer: Arc<SandboxEnforcer>,
+    sandbox: Arc<SessionSandbox>,
 }
 
 impl SandboxExtension {
-    pub fn new(enforcer: Arc<SandboxEnforcer>) -> Self {
-        Self { enforcer }
+    pub fn new(sandbox: Arc<SessionSandbox>) -> Self {
+        Self { sandbox }
     }
 }
 
@@ -34,7 +36,13 @@ impl ExecutionExtension for SandboxExtension {
     }
 
     async fn before_prompt_build(&self, mut draft: PromptDraft) -> PromptDraft {
-        let summary = self.enforcer.prompt_summary();
+        let Some(enforcer) = self.sandbox.current() else {
+            return draft;
+        };
+        let mut summary = enforcer.prompt_summary();
+        if let Some(owner) = self.sandbox.overlay_owner() {
+            summary.push_str(&format!("\n- Tightened by active skill: {}", owner));
+        }
         if !summary.is_empty() {
             let sandbox_notice = format!(
                 "<environment_constraints>\n{}\n</environment_constraints>",
@@ -55,7 +63,7 @@ impl ExecutionExtension for SandboxExtension {
     }
 
     async fn enrich_tool_context(&self, mut ctx: ToolContext) -> ToolContext {
-        ctx.sandbox = Some(self.enforcer.clone());
+        ctx.sandbox = self.sandbox.current();
         ctx
     }
 
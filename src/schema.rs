@@ -30,6 +30,35 @@ impl StoragePaths {
         Self::session_dir(session_id).join("task_state.json")
     }
 
+    pub fn pins_file(session_id: &str) -> PathBuf {
+        Self::session_dir(session_id).join("pins.json")
+    }
+
+    /// Evidence store: the latest read of each file or directory.
+    pub fn evidence_file(session_id: &str) -> PathBuf {
+        Self::session_dir(session_id).join("evidence.json")
+    }
+
+    /// Session override of the context budget policy (`/context policy set`).
+    pub fn context_policy_file(session_id: &str) -> PathBuf {
+        Self::session_dir(session_id).join("context_policy.json")
+    }
+
+    /// Code-mode stored values waiting to be restored into the session the
+    /// next time it is loaded (written by session import).
+    pub fn code_mode_values_file(session_id: &str) -> PathBuf {
+        Self::session_dir(session_id).join("code_mode_values.json")
+    }
+
+    /// Facts extracted from finished runs, awaiting `/memory review`.
+    pub fn memory_candidates_file() -> PathBuf {
+        PathBuf::from("rusty_claw").join("memory_candidates.json")
+    }
+
+    pub fn exports_dir() -> PathBuf {
+        PathBuf::from("rusty_claw").join("exports")
+    }
+
     pub fn trace_root_dir() -> PathBuf {
         PathBuf::from("rusty_claw").join("trace_center")
     }
@@ -42,6 +71,11 @@ impl StoragePaths {
         Self::trace_root_dir().join("runs")
     }
 
+    /// Content-addressed prompt parts; see `trace::prompt`.
+    pub fn trace_blobs_dir() -> PathBuf {
+        Self::trace_root_dir().join("blobs")
+    }
+
     pub fn trace_index_file() -> PathBuf {
         Self::trace_root_dir().join("index.sqlite")
     }
@@ -0,0 +1,463 @@
+//! Portable session archives (`.tar.gz`) for handing a session to someone
+//! else: transcript, per-session files, trace runs and code-mode values,
+//! described by a versioned manifest.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::repository::{unix_now, ForkInfo};
+use crate::schema::StoragePaths;
+use crate::trace::{RecordQuery, RunQuery, RunSummary, TraceRecord};
+
+pub const ARCHIVE_SCHEMA_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const TRANSCRIPT_ENTRY: &str = "transcript.jsonl";
+const SESSION_PREFIX: &str = "session/";
+const TRACE_RUNS_PREFIX: &str = "trace/runs/";
+const TRACE_RECORDS_PREFIX: &str = "trace/records/";
+const CODE_MODE_ENTRY: &str = "code_mode/stored_values.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionArchiveManifest {
+    pub schema_version: u32,
+    pub app_version: String,
+    pub source_session_id: String,
+    pub exported_at_unix: u64,
+    pub transcript_turns: usize,
+    /// Files copied from the session directory (events, task state, ...).
+    pub session_files: Vec<String>,
+    pub trace_runs: Vec<String>,
+    pub code_mode_values: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork: Option<ForkInfo>,
+}
+
+/// Everything needed to export one session.
+pub struct SessionExport {
+    pub session_id: String,
+    pub transcript_path: PathBuf,
+    pub code_mode_values: HashMap<String, Value>,
+    pub fork: Option<ForkInfo>,
+}
+
+pub struct ImportedSession {
+    pub manifest: SessionArchiveManifest,
+    pub transcript_turns: usize,
+    pub trace_runs: usize,
+}
+
+pub fn default_export_path(session_id: &str) -> PathBuf {
+    let stem = crate::context::transcript_path_for_session(Path::new(""), session_id)
+        .with_extension("")
+        .display()
+        .to_string();
+    StoragePaths::exports_dir().join(format!("{}-{}.tar.gz", stem, unix_now()))
+}
+
+pub fn export_session(
+    export: &SessionExport,
+    dest: &Path,
+) -> Result<SessionArchiveManifest, String> {
+    let mut entries: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+
+    let transcript_turns = crate::context::transcript::load_turns(&export.transcript_path)
+        .map(|turns| turns.len())
+        .unwrap_or(0);
+    if export.transcript_path.exists() {
+        let bytes = std::fs::read(&export.transcript_path)
+            .map_err(|e| format!("Failed to read transcript: {}", e))?;
+        entries.insert(TRANSCRIPT_ENTRY.to_string(), bytes);
+    }
+
+    let mut session_files = Vec::new();
+    let session_dir = StoragePaths::session_dir(&export.session_id);
+    if let Ok(dir) = std::fs::read_dir(&session_dir) {
+        for entry in dir.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let bytes =
+                std::fs::read(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+            entries.insert(format!("{}{}", SESSION_PREFIX, name), bytes);
+            session_files.push(name);
+        }
+    }
+    session_files.sort();
+
+    let runs = crate::trace::list_runs(&RunQuery {
+        session_id: Some(export.session_id.clone()),
+        ..RunQuery::default()
+    });
+    let mut trace_runs = Vec::new();
+    for run in &runs {
+        let records = crate::trace::get_records(&run.run_id, &RecordQuery::default());
+        entries.insert(
+            format!("{}{}.json", TRACE_RUNS_PREFIX, run.run_id),
+            serde_json::to_vec_pretty(run).map_err(|e| e.to_string())?,
+        );
+        entries.insert(
+            format!("{}{}.jsonl", TRACE_RECORDS_PREFIX, run.run_id),
+            records_to_jsonl(&records)?,
+        );
+        trace_runs.push(run.run_id.clone());
+    }
+
+    if !export.code_mode_values.is_empty() {
+        entries.insert(
+            CODE_MODE_ENTRY.to_string(),
+            serde_json::to_vec_pretty(&export.code_mode_values).map_err(|e| e.to_string())?,
+        );
+    }
+
+    let manifest = SessionArchiveManifest {
+        schema_version: ARCHIVE_SCHEMA_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        source_session_id: export.session_id.clone(),
+        exported_at_unix: unix_now(),
+        transcript_turns,
+        session_files,
+        trace_runs,
+        code_mode_values: export.code_mode_values.len(),
+        fork: export.fork.clone(),
+    };
+    entries.insert(
+        MANIFEST_ENTRY.to_string(),
+        serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?,
+    );
+
+    write_archive(dest, &entries).map_err(|e| format!("Failed to write {:?}: {}", dest, e))?;
+    Ok(manifest)
+}
+
+/// Unpack `archive` into `session_id`, which must not exist yet. Trace runs
+/// are re-keyed to the new session; code-mode values are parked in
+/// [`StoragePaths::code_mode_values_file`] until the session is loaded.
+pub fn import_session(
+    archive: &Path,
+    session_id: &str,
+    transcript_path: &Path,
+) -> Result<ImportedSession, String> {
+    let mut entries = read_archive(archive)?;
+    let manifest: SessionArchiveManifest = entries
+        .remove(MANIFEST_ENTRY)
+        .ok_or_else(|| format!("{:?} has no {}", archive, MANIFEST_ENTRY))
+        .and_then(|bytes| {
+            serde_json::from_slice(&bytes).map_err(|e| format!("Invalid manifest: {}", e))
+        })?;
+    if manifest.schema_version > ARCHIVE_SCHEMA_VERSION {
+        return Err(format!(
+            "Archive schema v{} is newer than supported v{}. Upgrade rusty-claw to import it.",
+            manifest.schema_version, ARCHIVE_SCHEMA_VERSION
+        ));
+    }
+
+    let session_dir = StoragePaths::session_dir(session_id);
+    std::fs::create_dir_all(&session_dir).map_err(|e| e.to_string())?;
+    if let Some(parent) = transcript_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(
+        transcript_path,
+        entries.remove(TRANSCRIPT_ENTRY).unwrap_or_default(),
+    )
+    .map_err(|e| format!("Failed to write transcript: {}", e))?;
+    let transcript_turns = crate::context::transcript::load_turns(transcript_path)
+        .map_err(|e| format!("Archive transcript is unreadable: {}", e))?
+        .len();
+
+    if let Some(values) = entries.remove(CODE_MODE_ENTRY) {
+        std::fs::write(StoragePaths::code_mode_values_file(session_id), values)
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut run_summaries = HashMap::new();
+    let mut run_records = HashMap::new();
+    for (name, bytes) in entries {
+        if let Some(file) = name.strip_prefix(SESSION_PREFIX) {
+            std::fs::write(session_dir.join(file), bytes).map_err(|e| e.to_string())?;
+        } else if let Some(run_id) = name
+            .strip_prefix(TRACE_RUNS_PREFIX)
+            .and_then(|file| file.strip_suffix(".json"))
+        {
+            let summary: RunSummary = serde_json::from_slice(&bytes)
+                .map_err(|e| format!("Invalid trace run {}: {}", run_id, e))?;
+            run_summaries.insert(run_id.to_string(), summary);
+        } else if let Some(run_id) = name
+            .strip_prefix(TRACE_RECORDS_PREFIX)
+            .and_then(|file| file.strip_suffix(".jsonl"))
+        {
+            run_records.insert(run_id.to_string(), parse_records(&bytes));
+        }
+    }
+
+    let trace_runs = run_summaries.len();
+    for (run_id, mut summary) in run_summaries {
+        let mut records = run_records.remove(&run_id).unwrap_or_default();
+        rekey_run(
+            &mut summary,
+            &mut records,
+            &manifest.source_session_id,
+            session_id,
+        );
+        crate::trace::import_run(&summary, &records)
+            .map_err(|e| format!("Failed to import trace run {}: {}", run_id, e))?;
+    }
+
+    Ok(ImportedSession {
+        manifest,
+        transcript_turns,
+        trace_runs,
+    })
+}
+
+/// Point an imported run at `new_session_id`, renaming it when a run with
+/// the same id already exists locally (re-importing into the source tree).
+/// A renamed run also gets fresh record ids: the trace index is keyed on
+/// `record_id`, so reusing them would overwrite the original run's records.
+fn rekey_run(
+    summary: &mut RunSummary,
+    records: &mut [TraceRecord],
+    old_session_id: &str,
+    new_session_id: &str,
+) {
+    let renamed = crate::trace::get_run(&summary.run_id).is_some();
+    if renamed {
+        summary.run_id = format!(
+            "{}-{}",
+            summary.run_id,
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        );
+    }
+    let swap = |id: &mut String| {
+        if id == old_session_id {
+            *id = new_session_id.to_string();
+        }
+    };
+    swap(&mut summary.session_id);
+    swap(&mut summary.root_session_id);
+    for record in records.iter_mut() {
+        record.run_id = summary.run_id.clone();
+        if renamed {
+            record.record_id = format!("trc_{}", uuid::Uuid::new_v4().simple());
+        }
+        swap(&mut record.session_id);
+        if let Some(Value::String(root)) = record.attrs.get_mut("root_session_id") {
+            swap(root);
+        }
+    }
+}
+
+fn records_to_jsonl(records: &[TraceRecord]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    for record in records {
+        out.extend(serde_json::to_vec(record).map_err(|e| e.to_string())?);
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+fn parse_records(bytes: &[u8]) -> Vec<TraceRecord> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn write_archive(dest: &Path, entries: &BTreeMap<String, Vec<u8>>) -> std::io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = std::fs::File::create(dest)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, flate2::Compression::default()));
+    let mtime = unix_now();
+    for (name, bytes) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        builder.append_data(&mut header, name, bytes.as_slice())?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn read_archive(archive: &Path) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let file =
+        std::fs::File::open(archive).map_err(|e| format!("Cannot open {:?}: {}", archive, e))?;
+    let mut tar = tar::Archive::new(GzDecoder::new(file));
+    let mut entries = BTreeMap::new();
+    for entry in tar
+        .entries()
+        .map_err(|e| format!("{:?} is not a session archive: {}", archive, e))?
+    {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path().map_err(|e| e.to_string())?.into_owned();
+        // Only plain relative names; never let an archive write outside the
+        // session it is imported into.
+        if path
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(format!("Archive entry {:?} has an unsafe path", path));
+        }
+        let name = path.to_string_lossy().replace('\\', "/");
+        if name.starts_with(SESSION_PREFIX) && name[SESSION_PREFIX.len()..].contains('/') {
+            return Err(format!("Archive entry {:?} has an unsafe path", path));
+        }
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        entries.insert(name, bytes);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_round_trip_keeps_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("session.tar.gz");
+        let mut entries = BTreeMap::new();
+        entries.insert(MANIFEST_ENTRY.to_string(), b"{}".to_vec());
+        entries.insert(
+            format!("{}task_state.json", SESSION_PREFIX),
+            b"{\"plan\":[]}".to_vec(),
+        );
+
+        write_archive(&dest, &entries).unwrap();
+        assert_eq!(read_archive(&dest).unwrap(), entries);
+    }
+
+    #[test]
+    fn test_import_rejects_newer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("future.tar.gz");
+        let manifest = SessionArchiveManifest {
+            schema_version: ARCHIVE_SCHEMA_VERSION + 1,
+            app_version: "9.9.9".to_string(),
+            source_session_id: "cli".to_string(),
+            exported_at_unix: 0,
+            transcript_turns: 0,
+            session_files: Vec::new(),
+            trace_runs: Vec::new(),
+            code_mode_values: 0,
+            fork: None,
+        };
+        let mut entries = BTreeMap::new();
+        entries.insert(
+            MANIFEST_ENTRY.to_string(),
+            serde_json::to_vec(&manifest).unwrap(),
+        );
+        write_archive(&dest, &entries).unwrap();
+
+        let err = import_session(&dest, "cli~import-test", &dir.path().join("t.jsonl"))
+            .err()
+            .unwrap();
+        assert!(err.contains("newer than supported"));
+    }
+
+    #[test]
+    fn test_importing_twice_keeps_both_runs_records() {
+        use crate::schema::CURRENT_SCHEMA_VERSION;
+        use crate::trace::{TraceActor, TraceKind, TraceLevel, TraceStatus};
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("twice.tar.gz");
+        let source = format!("archive-src-{}", uuid::Uuid::new_v4().simple());
+        let run_id = format!("run_archive_{}", uuid::Uuid::new_v4().simple());
+        let summary = RunSummary::new(&run_id, &run_id, &source, &source);
+        let records: Vec<TraceRecord> = (0..3)
+            .map(|i| TraceRecord {
+                schema_version: CURRENT_SCHEMA_VERSION,
+                record_id: format!("trc_{}_{}", run_id, i),
+                trace_id: run_id.clone(),
+                run_id: run_id.clone(),
+                span_id: None,
+                parent_span_id: None,
+                session_id: source.clone(),
+                task_id: None,
+                turn_id: None,
+                iteration: None,
+                actor: TraceActor::MainAgent,
+                kind: TraceKind::Event,
+                name: format!("step_{}", i),
+                status: TraceStatus::Ok,
+                ts_unix_ms: 1_000 + i,
+                duration_ms: None,
+                level: TraceLevel::Normal,
+                summary: None,
+                attrs: serde_json::json!({ "root_session_id": source }),
+            })
+            .collect();
+        let manifest = SessionArchiveManifest {
+            schema_version: ARCHIVE_SCHEMA_VERSION,
+            app_version: "test".to_string(),
+            source_session_id: source.clone(),
+            exported_at_unix: 0,
+            transcript_turns: 0,
+            session_files: Vec::new(),
+            trace_runs: vec![run_id.clone()],
+            code_mode_values: 0,
+            fork: None,
+        };
+        let mut entries = BTreeMap::new();
+        entries.insert(
+            MANIFEST_ENTRY.to_string(),
+            serde_json::to_vec(&manifest).unwrap(),
+        );
+        entries.insert(
+            format!("{}{}.json", TRACE_RUNS_PREFIX, run_id),
+            serde_json::to_vec(&summary).unwrap(),
+        );
+        entries.insert(
+            format!("{}{}.jsonl", TRACE_RECORDS_PREFIX, run_id),
+            records_to_jsonl(&records).unwrap(),
+        );
+        write_archive(&dest, &entries).unwrap();
+
+        let first = format!("{}~a", source);
+        let second = format!("{}~b", source);
+        import_session(&dest, &first, &dir.path().join("a.jsonl")).unwrap();
+        import_session(&dest, &second, &dir.path().join("b.jsonl")).unwrap();
+
+        let runs = crate::trace::list_runs(&RunQuery {
+            session_id: Some(second.clone()),
+            ..RunQuery::default()
+        });
+        assert_eq!(runs.len(), 1);
+        let copy_id = runs[0].run_id.clone();
+        assert_ne!(copy_id, run_id);
+
+        let original = crate::trace::get_records(&run_id, &RecordQuery::default());
+        let copy = crate::trace::get_records(&copy_id, &RecordQuery::default());
+        assert_eq!(original.len(), 3);
+        assert_eq!(copy.len(), 3);
+        assert!(original.iter().all(|r| r.session_id == first));
+        assert!(copy
+            .iter()
+            .all(|r| r.session_id == second && r.attrs["root_session_id"] == second.as_str()));
+
+        for (id, session) in [(&run_id, &first), (&copy_id, &second)] {
+            let _ = std::fs::remove_file(StoragePaths::trace_run_summary_file(id));
+            let _ = std::fs::remove_file(StoragePaths::trace_run_records_file(id));
+            let _ = std::fs::remove_dir_all(StoragePaths::session_dir(session));
+        }
+    }
+}
@@ -378,11 +378,25 @@ pub fn build_subagent_session(
             parent_span_id: trace.parent_span_id.clone(),
         });
     }
+    // Subagents inherit the parent's effective (possibly skill-tightened)
+    // policy; a delegated skill can only tighten it further.
+    let session_sandbox = Arc::new(crate::tools::sandbox::SessionSandbox::new(
+        parent_ctx.sandbox.clone().unwrap_or_else(|| {
+            Arc::new(crate::tools::sandbox::SandboxEnforcer::detect(
+                crate::tools::sandbox::SandboxPolicy::default(),
+            ))
+        }),
+    ));
+    agent_loop.add_extension(Arc::new(crate::sandbox_extension::SandboxExtension::new(
+        session_sandbox.clone(),
+    )));
+    agent_loop.set_session_sandbox(session_sandbox.clone());
     agent_loop.add_extension(Arc::new(
         crate::skills::runtime::SkillRuntime::with_call_chain_seed(
             sub_session_id.clone(),
             call_chain_seed,
-        ),
+        )
+        .with_session_sandbox(session_sandbox),
     ));
     agent_loop.cancelled = cancelled;
     agent_loop.cancel_token = cancel_notify;
@@ -411,7 +425,13 @@ pub fn build_agent_session(
     output: Arc<dyn AgentOutput>,
     code_mode_format: crate::code_mode::description::CodeModeFormat,
 ) -> Result<Arc<AsyncMutex<AgentLoop>>, String> {
-    let mut context = AgentContext::new().with_transcript_path(transcript_path);
+    let mut context = AgentContext::new()
+        .with_transcript_path(transcript_path)
+        .with_pins_path(crate::schema::StoragePaths::pins_file(session_id))
+        .with_policy_path(crate::schema::StoragePaths::context_policy_file(session_id))
+        .with_evidence_store(Arc::new(crate::evidence_store::EvidenceStore::open(
+            crate::schema::StoragePaths::evidence_file(session_id),
+        )));
     context.max_history_tokens = llm.context_window();
     let _ = context.load_transcript().map_err(|e| e.to_string())?;
 
@@ -440,45 +460,58 @@ pub fn build_agent_session(
         task_state_store,
     );
     agent_loop.set_code_mode_format(code_mode_format);
-    agent_loop.add_extension(Arc::new(
-        crate::skills::runtime::SkillRuntime::new_for_session(session_id.to_string()),
-    ));
-    agent_loop.add_extension(Arc::new(
-        crate::subagent_notification::SubagentNotificationExtension::new(
-            session_id,
-            subagent_runtime.clone(),
-        ),
-    ));
 
     // ── Sandbox extension ──
-    {
+    let session_sandbox = {
         let config = crate::config::AppConfig::load();
         let sandbox_config = config.sandbox.unwrap_or_default();
         let level = sandbox_config.parsed_level();
-        if level != crate::tools::sandbox::SandboxLevel::Unrestricted {
-            let work_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-            let policy = sandbox_config.build_default_policy(&work_dir);
-            let enforcer = crate::tools::sandbox::SandboxEnforcer::detect(policy);
-
-            if sandbox_config.require_os_sandbox.unwrap_or(false) && !enforcer.is_available() {
-                let hint = if cfg!(target_os = "linux") {
-                    "Install with: apt install bubblewrap"
-                } else if cfg!(target_os = "macos") {
-                    "sandbox-exec should be present at /usr/bin/sandbox-exec on macOS"
-                } else {
-                    "OS-level sandboxing is not supported on this platform"
-                };
-                return Err(format!(
-                    "Sandbox: OS-level isolation is required but not available. {hint}"
-                ));
-            }
+        let work_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let policy = sandbox_config.build_default_policy(&work_dir);
+        let enforcer = crate::tools::sandbox::SandboxEnforcer::detect(policy);
 
-            agent_loop.add_extension(Arc::new(crate::sandbox_extension::SandboxExtension::new(
-                std::sync::Arc::new(enforcer),
-            )));
-            tracing::info!("Sandbox extension registered (level={:?})", level);
+        if level != crate::tools::sandbox::SandboxLevel::Unrestricted
+            && sandbox_config.require_os_sandbox.unwrap_or(false)
+            && !enforcer.is_available()
+        {
+            let hint = if cfg!(target_os = "linux") {
+                "Install with: apt install bubblewrap"
+            } else if cfg!(target_os = "macos") {
+                "sandbox-exec should be present at /usr/bin/sandbox-exec on macOS"
+            } else {
+                "OS-level sandboxing is not supported on this platform"
+            };
+            return Err(format!(
+                "Sandbox: OS-level isolation is required but not available. {hint}"
+            ));
         }
-    }
+
+        // Registered even when unrestricted so skills can still tighten it.
+        let session_sandbox = Arc::new(crate::tools::sandbox::SessionSandbox::new(Arc::new(
+            enforcer,
+        )));
+        agent_loop.add_extension(Arc::new(crate::sandbox_extension::SandboxExtension::new(
+            session_sandbox.clone(),
+        )));
+        agent_loop.set_session_sandbox(session_sandbox.clone());
+        tracing::info!("Sandbox extension registered (level={:?})", level);
+        session_sandbox
+    };
+    let skill_runtime = Arc::new(
+        crate::skills::runtime::SkillRuntime::new_for_session(session_id.to_string())
+            .with_session_sandbox(session_sandbox),
+    );
+    agent_loop.add_extension(skill_runtime.clone());
+    agent_loop.set_skill_runtime(skill_runtime);
+    agent_loop.add_extension(Arc::new(
+        crate::subagent_notification::SubagentNotificationExtension::new(
+            session_id,
+            subagent_runtime.clone(),
+        ),
+    ));
+    agent_loop.add_extension(Arc::new(
+        crate::evidence_store::EvidenceStoreExtension::new(agent_loop.context.evidence_store()),
+    ));
 
     Ok(Arc::new(AsyncMutex::new(agent_loop)))
 }
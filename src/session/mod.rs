@@ -1,2 +1,3 @@
+pub mod archive;
 pub mod factory;
 pub mod repository;
@@ -17,6 +17,22 @@ struct SessionEntry {
     transcript_path: String,
     updated_at_unix: u64,
     loaded_turns: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fork: Option<ForkInfo>,
+}
+
+/// Where a forked session branched off.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ForkInfo {
+    pub name: String,
+    /// Frontend session the fork belongs to (e.g. `cli`, `telegram:42`).
+    pub root_session_id: String,
+    pub parent_session_id: String,
+    /// Number of parent turns copied into the fork.
+    pub parent_turn: usize,
+    pub parent_turn_id: Option<String>,
+    pub parent_turn_preview: String,
+    pub created_at_unix: u64,
 }
 
 pub struct SessionRegistryStore {
@@ -76,6 +92,7 @@ impl SessionRegistryStore {
                     .unwrap_or_default(),
                 updated_at_unix: unix_now(),
                 loaded_turns: loaded_turns.unwrap_or(0),
+                fork: None,
             });
 
         if let Some(path) = transcript_path {
@@ -100,6 +117,62 @@ impl SessionRegistryStore {
         list
     }
 
+    pub fn record_fork(&self, session_id: &str, transcript_path: &Path, fork: ForkInfo) {
+        let mut cache = self.cache.write().unwrap();
+        cache.sessions.insert(
+            session_id.to_string(),
+            SessionEntry {
+                transcript_path: transcript_path.display().to_string(),
+                updated_at_unix: unix_now(),
+                loaded_turns: fork.parent_turn,
+                fork: Some(fork),
+            },
+        );
+        drop(cache);
+        self.persist_async();
+    }
+
+    pub fn fork_info(&self, session_id: &str) -> Option<ForkInfo> {
+        let cache = self.cache.read().unwrap();
+        cache
+            .sessions
+            .get(session_id)
+            .and_then(|entry| entry.fork.clone())
+    }
+
+    pub fn is_known(&self, session_id: &str) -> bool {
+        self.cache.read().unwrap().sessions.contains_key(session_id)
+    }
+
+    /// Forks under `root_session_id`, oldest first.
+    pub fn list_forks(&self, root_session_id: &str) -> Vec<(String, ForkInfo)> {
+        let cache = self.cache.read().unwrap();
+        let mut forks: Vec<_> = cache
+            .sessions
+            .iter()
+            .filter_map(|(id, entry)| {
+                entry
+                    .fork
+                    .as_ref()
+                    .filter(|fork| fork.root_session_id == root_session_id)
+                    .map(|fork| (id.clone(), fork.clone()))
+            })
+            .collect();
+        forks.sort_by(|a, b| (a.1.created_at_unix, &a.0).cmp(&(b.1.created_at_unix, &b.0)));
+        forks
+    }
+
+    /// Write the registry to disk now, for short-lived callers that may
+    /// exit before a background persist finishes.
+    pub fn flush(&self) -> std::io::Result<()> {
+        if let Some(parent) = self.registry_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let snapshot = self.cache.read().unwrap().clone();
+        let serialized = serde_json::to_string_pretty(&snapshot).map_err(std::io::Error::other)?;
+        fs::write(&self.registry_path, serialized)
+    }
+
     fn persist_async(&self) {
         let registry_path = self.registry_path.clone();
         let snapshot = self.cache.read().unwrap().clone();
@@ -115,9 +188,54 @@ impl SessionRegistryStore {
     }
 }
 
-fn unix_now() -> u64 {
+pub(crate) fn unix_now() -> u64 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|duration| duration.as_secs())
         .unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fork(name: &str, root: &str, created_at_unix: u64) -> ForkInfo {
+        ForkInfo {
+            name: name.to_string(),
+            root_session_id: root.to_string(),
+            parent_session_id: root.to_string(),
+            parent_turn: 3,
+            parent_turn_id: None,
+            parent_turn_preview: "refactor the parser".to_string(),
+            created_at_unix,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_forks_filters_by_root_and_orders_by_creation() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionRegistryStore::new(dir.path().to_path_buf());
+        store.record_fork(
+            "cli~b",
+            &store.transcript_path("cli~b"),
+            fork("b", "cli", 20),
+        );
+        store.record_fork(
+            "cli~a",
+            &store.transcript_path("cli~a"),
+            fork("a", "cli", 10),
+        );
+        store.record_fork(
+            "telegram:1~c",
+            &store.transcript_path("telegram:1~c"),
+            fork("c", "telegram:1", 5),
+        );
+
+        let forks = store.list_forks("cli");
+        let ids: Vec<_> = forks.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["cli~a", "cli~b"]);
+        assert_eq!(store.fork_info("cli~a").unwrap().parent_turn, 3);
+        assert!(store.is_known("telegram:1~c"));
+        assert!(store.fork_info("cli").is_none());
+    }
+}
@@ -1,7 +1,10 @@
 use crate::core::{AgentLoop, AgentOutput, OutputRouter};
 use crate::llm_client::LlmClient;
+use crate::session::archive::{ImportedSession, SessionArchiveManifest};
+use crate::session::repository::ForkInfo;
 use crate::tools::Tool;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use tokio::sync::Mutex as AsyncMutex;
 use tokio_util::sync::CancellationToken;
@@ -17,6 +20,9 @@ type SessionEntryMap = AsyncMutex<
     >,
 >;
 
+/// Branch name that refers to a frontend's own (unforked) session.
+pub const MAIN_BRANCH: &str = "main";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ForegroundTaskKind {
     Agent,
@@ -68,6 +74,8 @@ impl Drop for ForegroundGuard {
 
 pub struct SessionManager {
     scheduler: std::sync::RwLock<Option<Arc<crate::scheduler::Scheduler>>>,
+    code_indexer: std::sync::RwLock<Option<Arc<crate::rag::indexer::CodeIndexer>>>,
+    memory_extractor: std::sync::RwLock<Option<Arc<crate::memory::extraction::MemoryExtractor>>>,
 
     llm: Arc<RwLock<Option<Arc<dyn LlmClient>>>>,
     tools: RwLock<Vec<Arc<dyn Tool>>>,
@@ -77,6 +85,8 @@ pub struct SessionManager {
     sessions: SessionEntryMap,
     foreground_tasks: ForegroundTaskMap,
     registry: crate::session::repository::SessionRegistryStore,
+    /// Frontend session id -> fork currently checked out in its place.
+    active_branches: std::sync::Mutex<HashMap<String, String>>,
 }
 
 impl SessionManager {
@@ -105,9 +115,12 @@ impl SessionManager {
             sessions: AsyncMutex::new(HashMap::new()),
             foreground_tasks: Arc::new(std::sync::Mutex::new(HashMap::new())),
             scheduler: std::sync::RwLock::new(None),
+            code_indexer: std::sync::RwLock::new(None),
+            memory_extractor: std::sync::RwLock::new(None),
             registry: crate::session::repository::SessionRegistryStore::new(
                 std::path::PathBuf::from("rusty_claw"),
             ),
+            active_branches: std::sync::Mutex::new(HashMap::new()),
         }
     }
 
@@ -119,6 +132,23 @@ impl SessionManager {
         self.scheduler.read().unwrap().clone()
     }
 
+    pub fn set_code_indexer(&self, indexer: Arc<crate::rag::indexer::CodeIndexer>) {
+        *self.code_indexer.write().unwrap() = Some(indexer);
+    }
+
+    pub fn code_indexer(&self) -> Option<Arc<crate::rag::indexer::CodeIndexer>> {
+        self.code_indexer.read().unwrap().clone()
+    }
+
+    /// Sessions created after this run memory extraction after each turn.
+    pub fn set_memory_extractor(&self, extractor: Arc<crate::memory::extraction::MemoryExtractor>) {
+        *self.memory_extractor.write().unwrap() = Some(extractor);
+    }
+
+    pub fn memory_extractor(&self) -> Option<Arc<crate::memory::extraction::MemoryExtractor>> {
+        self.memory_extractor.read().unwrap().clone()
+    }
+
     pub fn add_output_router(&self, router: Arc<dyn OutputRouter>) {
         let mut routers = self.routers.write().unwrap();
         routers.push(router);
@@ -152,16 +182,21 @@ impl SessionManager {
 
     pub async fn reset_session(&self, session_id: &str) {
         self.cancel_session(session_id).await;
+        // Forks survive a reset; the frontend returns to its own session.
+        self.active_branches.lock().unwrap().remove(session_id);
 
         let mut sessions = self.sessions.lock().await;
 
         // Remove from memory
         sessions.remove(session_id);
+        crate::security::forget_session_canary(session_id);
+        crate::security::forget_session_secrets(session_id);
 
         self.registry.remove_session_artifacts(session_id);
     }
 
     pub async fn cancel_session(&self, session_id: &str) {
+        let session_id = &self.active_session_id(session_id);
         if let Some(task) = {
             let tasks = self.foreground_tasks.lock().unwrap();
             tasks.get(session_id).cloned()
@@ -237,11 +272,29 @@ impl SessionManager {
         })
     }
 
+    /// Look up an already-loaded session without creating one.
+    pub async fn get_session(&self, session_id: &str) -> Option<Arc<AsyncMutex<AgentLoop>>> {
+        let session_id = &self.active_session_id(session_id);
+        let sessions = self.sessions.lock().await;
+        sessions.get(session_id).map(|(a, _, _)| a.clone())
+    }
+
     pub async fn get_or_create_session(
         &self,
         session_id: &str,
         reply_to: &str,
         output: Arc<dyn AgentOutput>,
+    ) -> Result<Arc<AsyncMutex<AgentLoop>>, String> {
+        let session_id = self.active_session_id(session_id);
+        self.load_session(&session_id, reply_to, output).await
+    }
+
+    /// Like [`Self::get_or_create_session`], without following a checked-out fork.
+    async fn load_session(
+        &self,
+        session_id: &str,
+        reply_to: &str,
+        output: Arc<dyn AgentOutput>,
     ) -> Result<Arc<AsyncMutex<AgentLoop>>, String> {
         let existing = {
             let sessions = self.sessions.lock().await;
@@ -279,6 +332,30 @@ impl SessionManager {
                 .expect("subagent runtime should be initialized")
                 .clone()
         };
+        let extraction = self
+            .memory_extractor()
+            .filter(|extractor| extractor.enabled())
+            .map(|extractor| {
+                crate::memory::extraction::MemoryExtractionExtension::new(
+                    session_id,
+                    llm.clone(),
+                    extractor,
+                    crate::app::commands::is_local(reply_to),
+                )
+            });
+        let retrieval_config = crate::config::AppConfig::load()
+            .retrieval
+            .unwrap_or_default();
+        let retrieval = self
+            .code_indexer()
+            .filter(|_| retrieval_config.auto())
+            .map(|indexer| {
+                crate::memory::retrieval::RetrievalExtension::new(
+                    session_id,
+                    indexer.store().clone(),
+                    retrieval_config,
+                )
+            });
         let agent = crate::session::factory::build_agent_session(
             session_id,
             reply_to,
@@ -289,6 +366,23 @@ impl SessionManager {
             output,
             self.code_mode_format,
         )?;
+        if let Some(extension) = extraction {
+            agent.lock().await.add_extension(Arc::new(extension));
+        }
+        if let Some(extension) = retrieval {
+            agent.lock().await.add_extension(Arc::new(extension));
+        }
+        let pending_values = crate::schema::StoragePaths::code_mode_values_file(session_id);
+        if let Ok(bytes) = std::fs::read(&pending_values) {
+            if let Ok(values) = serde_json::from_slice(&bytes) {
+                agent
+                    .lock()
+                    .await
+                    .restore_code_mode_stored_values(values)
+                    .await;
+            }
+            let _ = std::fs::remove_file(&pending_values);
+        }
         let loaded_turns = agent.lock().await.context.dialogue_history.len();
         self.registry
             .touch_session(session_id, Some(&transcript_path), Some(loaded_turns));
@@ -325,10 +419,7 @@ impl SessionManager {
                     ));
                 }
 
-                let agent_mutex = {
-                    let sessions = self.sessions.lock().await;
-                    sessions.get(session_id).map(|(a, _, _)| a.clone())
-                };
+                let agent_mutex = self.get_session(session_id).await;
                 if let Some(agent_mutex) = agent_mutex {
                     let mut agent = agent_mutex.lock().await;
                     agent.update_llm(new_llm);
@@ -345,6 +436,269 @@ impl SessionManager {
         }
     }
 
+    /// The session that actually serves `session_id`: a checked-out fork,
+    /// or `session_id` itself.
+    pub fn active_session_id(&self, session_id: &str) -> String {
+        self.active_branches
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .cloned()
+            .unwrap_or_else(|| session_id.to_string())
+    }
+
+    /// Validate a requested branch name under `session_id` (or pick
+    /// `<prefix>-N`) and return it with the session id it would get.
+    async fn new_branch_id(
+        &self,
+        session_id: &str,
+        name: Option<&str>,
+        prefix: &str,
+    ) -> Result<(String, String), String> {
+        let name = match name.map(str::trim).filter(|name| !name.is_empty()) {
+            Some(name) => {
+                if !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    return Err(
+                        "Branch names may only contain letters, digits, '-' and '_'.".to_string(),
+                    );
+                }
+                name.to_string()
+            }
+            None => {
+                let taken: Vec<String> = self
+                    .registry
+                    .list_forks(session_id)
+                    .into_iter()
+                    .map(|(_, fork)| fork.name)
+                    .collect();
+                (1..)
+                    .map(|n| format!("{}-{}", prefix, n))
+                    .find(|name| !taken.contains(name))
+                    .expect("unbounded range yields a free name")
+            }
+        };
+        if name == MAIN_BRANCH {
+            return Err(format!(
+                "'{}' is reserved for the original session.",
+                MAIN_BRANCH
+            ));
+        }
+        let fork_id = format!("{}~{}", session_id, name);
+        if self.registry.is_known(&fork_id) || self.get_session(&fork_id).await.is_some() {
+            return Err(format!("A branch named '{}' already exists.", name));
+        }
+        Ok((name, fork_id))
+    }
+
+    /// Bundle the active session behind `session_id` into a portable archive
+    /// at `dest` (or under `rusty_claw/exports/`).
+    pub async fn export_session(
+        &self,
+        session_id: &str,
+        dest: Option<PathBuf>,
+    ) -> Result<(PathBuf, SessionArchiveManifest), String> {
+        let active = self.active_session_id(session_id);
+        let code_mode_values = match self.get_session(session_id).await {
+            Some(agent) => agent.lock().await.code_mode_stored_values().await,
+            None => std::fs::read(crate::schema::StoragePaths::code_mode_values_file(&active))
+                .ok()
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+                .unwrap_or_default(),
+        };
+        let export = crate::session::archive::SessionExport {
+            transcript_path: self.registry.transcript_path(&active),
+            fork: self.registry.fork_info(&active),
+            session_id: active.clone(),
+            code_mode_values,
+        };
+        let dest = dest.unwrap_or_else(|| crate::session::archive::default_export_path(&active));
+        let manifest = crate::session::archive::export_session(&export, &dest)?;
+        Ok((dest, manifest))
+    }
+
+    /// Restore an archive as a new branch of `session_id` (named `name`, or
+    /// `import-N`). The branch is recorded but not checked out.
+    pub async fn import_session(
+        &self,
+        session_id: &str,
+        archive: &Path,
+        name: Option<&str>,
+    ) -> Result<(String, ForkInfo, ImportedSession), String> {
+        let (name, import_id) = self.new_branch_id(session_id, name, "import").await?;
+        let transcript_path = self.registry.transcript_path(&import_id);
+        let imported =
+            crate::session::archive::import_session(archive, &import_id, &transcript_path)?;
+        let fork = ForkInfo {
+            name,
+            root_session_id: session_id.to_string(),
+            parent_session_id: imported.manifest.source_session_id.clone(),
+            parent_turn: imported.transcript_turns,
+            parent_turn_id: None,
+            parent_turn_preview: format!("imported from {}", archive.display()),
+            created_at_unix: crate::session::repository::unix_now(),
+        };
+        self.registry
+            .record_fork(&import_id, &transcript_path, fork.clone());
+        self.registry
+            .flush()
+            .map_err(|e| format!("Failed to save session registry: {}", e))?;
+        Ok((import_id, fork, imported))
+    }
+
+    /// Clone the active session behind `session_id` (history, task plan,
+    /// skill state) into a new fork and check it out.
+    pub async fn fork_session(
+        &self,
+        session_id: &str,
+        name: Option<&str>,
+        reply_to: &str,
+        output: Arc<dyn AgentOutput>,
+    ) -> Result<(String, ForkInfo), String> {
+        let (name, fork_id) = self.new_branch_id(session_id, name, "fork").await?;
+
+        let parent_id = self.active_session_id(session_id);
+        let parent = self
+            .load_session(&parent_id, reply_to, output.clone())
+            .await?;
+        let (history, invocation) = {
+            let parent = parent.lock().await;
+            let invocation = match parent.skill_runtime() {
+                Some(runtime) => runtime.snapshot_invocation().await,
+                None => None,
+            };
+            (parent.context.dialogue_history.clone(), invocation)
+        };
+
+        let transcript_path = self.registry.transcript_path(&fork_id);
+        let _ = std::fs::remove_file(&transcript_path);
+        for turn in &history {
+            crate::context::transcript::append_turn(Some(&transcript_path), turn)
+                .map_err(|e| format!("Failed to write fork transcript: {}", e))?;
+        }
+        let task_state = crate::task_state::TaskStateStore::new(&parent_id)
+            .load()
+            .map_err(|e| e.to_string())?;
+        crate::task_state::TaskStateStore::new(&fork_id)
+            .save(&task_state)
+            .map_err(|e| format!("Failed to copy task plan: {}", e))?;
+        for session_file in [
+            crate::schema::StoragePaths::pins_file,
+            crate::schema::StoragePaths::context_policy_file,
+        ] {
+            let parent_file = session_file(&parent_id);
+            if parent_file.exists() {
+                let fork_file = session_file(&fork_id);
+                if let Some(dir) = fork_file.parent() {
+                    let _ = std::fs::create_dir_all(dir);
+                }
+                std::fs::copy(&parent_file, &fork_file)
+                    .map_err(|e| format!("Failed to copy {}: {}", parent_file.display(), e))?;
+            }
+        }
+
+        let branch_point = history
+            .iter()
+            .rev()
+            .find(|turn| !crate::context::history::is_pinned_turn(turn));
+        let fork = ForkInfo {
+            name,
+            root_session_id: session_id.to_string(),
+            parent_session_id: parent_id,
+            parent_turn: history.len(),
+            parent_turn_id: branch_point.map(|turn| turn.turn_id.clone()),
+            parent_turn_preview: branch_point
+                .map(|turn| crate::context::AgentContext::truncate_chars(&turn.user_message, 80))
+                .unwrap_or_default(),
+            created_at_unix: crate::session::repository::unix_now(),
+        };
+        self.registry
+            .record_fork(&fork_id, &transcript_path, fork.clone());
+
+        let agent = self.load_session(&fork_id, reply_to, output).await?;
+        if let Some(invocation) = invocation {
+            if let Some(runtime) = agent.lock().await.skill_runtime() {
+                runtime.restore_invocation(invocation).await;
+            }
+        }
+
+        self.active_branches
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), fork_id.clone());
+        Ok((fork_id, fork))
+    }
+
+    /// Forks of `session_id`, oldest first.
+    pub fn list_branches(&self, session_id: &str) -> Vec<(String, ForkInfo)> {
+        self.registry.list_forks(session_id)
+    }
+
+    /// Serve `session_id` from the fork called `name` (or from itself for
+    /// [`MAIN_BRANCH`]). Returns the now-active session id.
+    pub fn checkout_branch(&self, session_id: &str, name: &str) -> Result<String, String> {
+        let mut active = self.active_branches.lock().unwrap();
+        if name == MAIN_BRANCH || name == session_id {
+            active.remove(session_id);
+            return Ok(session_id.to_string());
+        }
+        let (fork_id, _) = self
+            .registry
+            .list_forks(session_id)
+            .into_iter()
+            .find(|(_, fork)| fork.name == name)
+            .ok_or_else(|| format!("No branch named '{}'. See /branches.", name))?;
+        active.insert(session_id.to_string(), fork_id.clone());
+        Ok(fork_id)
+    }
+
+    /// Pin the final reply of a fork (the named one, or the checked-out one)
+    /// into its parent as a note. Returns the parent session id.
+    pub async fn merge_fork_notes(
+        &self,
+        session_id: &str,
+        name: Option<&str>,
+        reply_to: &str,
+        output: Arc<dyn AgentOutput>,
+    ) -> Result<String, String> {
+        let (fork_id, fork) = match name.map(str::trim).filter(|name| !name.is_empty()) {
+            Some(name) => self
+                .registry
+                .list_forks(session_id)
+                .into_iter()
+                .find(|(_, fork)| fork.name == name)
+                .ok_or_else(|| format!("No branch named '{}'. See /branches.", name))?,
+            None => {
+                let active = self.active_session_id(session_id);
+                let fork = self.registry.fork_info(&active).ok_or_else(|| {
+                    "No fork is checked out. Usage: /merge-notes <branch>".to_string()
+                })?;
+                (active, fork)
+            }
+        };
+
+        let summary = {
+            let agent = self
+                .load_session(&fork_id, reply_to, output.clone())
+                .await?;
+            let agent = agent.lock().await;
+            agent.context.last_model_reply()
+        }
+        .ok_or_else(|| format!("Branch '{}' has no final reply to merge yet.", fork.name))?;
+
+        let parent = self
+            .load_session(&fork.parent_session_id, reply_to, output)
+            .await?;
+        parent
+            .lock()
+            .await
+            .context
+            .pin_note(&format!("outcome of fork '{}'", fork.name), &summary);
+        Ok(fork.parent_session_id)
+    }
+
     #[cfg(feature = "acp")]
     pub fn tools(&self) -> Vec<Arc<dyn Tool>> {
         self.tools.read().unwrap().clone()
@@ -5,6 +5,9 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::tools::sandbox::{SandboxLevel, SandboxPolicy, DEFAULT_KEEP_ENV};
 
 /// Top-level unified skill definition.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +19,9 @@ pub struct SkillDef {
     pub parameters: Option<Value>,
     /// Runtime constraints and policies.
     pub constraints: SkillConstraints,
+    /// Optional sandbox policy applied while the skill is active.
+    #[serde(default)]
+    pub sandbox: Option<SkillSandbox>,
 }
 
 /// Skill metadata extracted from YAML frontmatter.
@@ -71,6 +77,68 @@ pub enum ArtifactKind {
     ReviewReport,
 }
 
+/// Sandbox requirements declared in a skill's frontmatter. They can only
+/// tighten the session policy, never relax it.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SkillSandbox {
+    /// Minimum isolation level; defaults to `restricted`.
+    #[serde(default)]
+    pub level: Option<SandboxLevel>,
+    /// Writable directories, relative paths resolved against the work dir.
+    /// When omitted the session's writable paths are kept.
+    #[serde(default)]
+    pub writable_paths: Option<Vec<String>>,
+    /// Domain allowlist for web tools.
+    #[serde(default)]
+    pub allowed_domains: Option<Vec<String>>,
+    /// Take shell commands offline (implied by `strict`).
+    #[serde(default)]
+    pub isolate_network: Option<bool>,
+}
+
+impl SkillSandbox {
+    /// Build the child policy to be merged into `base` via
+    /// `SandboxPolicy::tighten`.
+    pub fn child_policy(&self, base: &SandboxPolicy, work_dir: &Path) -> SandboxPolicy {
+        let level = self.level.unwrap_or(SandboxLevel::Restricted);
+        let writable_paths = match &self.writable_paths {
+            Some(paths) => paths
+                .iter()
+                .map(|p| {
+                    let path = PathBuf::from(p.strip_prefix("./").unwrap_or(p));
+                    if path.is_absolute() {
+                        path
+                    } else {
+                        work_dir.join(path)
+                    }
+                })
+                .collect(),
+            None if base.writable_paths.is_empty() => vec![work_dir.to_path_buf()],
+            None => base.writable_paths.clone(),
+        };
+        let keep_env = if base.keep_env.is_empty() {
+            DEFAULT_KEEP_ENV.iter().map(|k| k.to_string()).collect()
+        } else {
+            base.keep_env.clone()
+        };
+
+        SandboxPolicy {
+            level,
+            writable_paths,
+            readonly_paths: Vec::new(),
+            isolate_network: self
+                .isolate_network
+                .unwrap_or(level == SandboxLevel::Strict),
+            isolate_pid: level == SandboxLevel::Strict,
+            clear_env: level == SandboxLevel::Strict,
+            keep_env,
+            allowed_domains: self.allowed_domains.clone().unwrap_or_default(),
+            hidden_paths: Vec::new(),
+        }
+    }
+}
+
 fn default_version() -> String {
     "0.1.0".to_string()
 }
@@ -85,6 +153,24 @@ mod tests {
         assert!(c.required_artifact_kind.is_none());
     }
 
+    #[test]
+    fn test_skill_sandbox_child_policy_resolves_relative_paths() {
+        let sandbox = SkillSandbox {
+            writable_paths: Some(vec!["./out".to_string()]),
+            allowed_domains: Some(vec!["generativelanguage.googleapis.com".to_string()]),
+            ..Default::default()
+        };
+        let work_dir = Path::new("/work");
+        let child = sandbox.child_policy(&SandboxPolicy::default(), work_dir);
+        assert_eq!(child.level, SandboxLevel::Restricted);
+        assert_eq!(child.writable_paths, vec![PathBuf::from("/work/out")]);
+        assert_eq!(
+            child.allowed_domains,
+            vec!["generativelanguage.googleapis.com"]
+        );
+        assert!(!child.isolate_network);
+    }
+
     #[test]
     fn test_default_trigger() {
         let t = SkillTrigger::default();
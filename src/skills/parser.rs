@@ -12,7 +12,7 @@
 //! Markdown body...
 //! ```
 
-use super::definition::{SkillConstraints, SkillDef, SkillMeta};
+use super::definition::{SkillConstraints, SkillDef, SkillMeta, SkillSandbox};
 use serde::Deserialize;
 
 /// Raw YAML frontmatter before conversion to `SkillDef`.
@@ -34,6 +34,8 @@ struct RawFrontmatter {
     constraints: Option<SkillConstraints>,
     #[serde(default)]
     parameters: Option<serde_yaml::Value>,
+    #[serde(default)]
+    sandbox: Option<SkillSandbox>,
 }
 
 fn default_version() -> String {
@@ -116,6 +118,7 @@ pub fn parse_skill_md(content: &str) -> Option<SkillDef> {
         parameters: parameters_json
             .or_else(|| raw.parameters.and_then(|p| serde_json::to_value(p).ok())),
         constraints: raw.constraints.unwrap_or_default(),
+        sandbox: raw.sandbox,
     })
 }
 
@@ -182,6 +185,31 @@ Test.
         assert_eq!(def.meta.allowed_tools, vec!["read_file", "execute_bash"]);
     }
 
+    #[test]
+    fn test_parse_skill_with_sandbox_block() {
+        let md = r#"---
+name: sandboxed
+description: Declares its own sandbox
+sandbox:
+  level: restricted
+  writable_paths: [./out]
+  allowed_domains: [generativelanguage.googleapis.com]
+---
+body
+"#;
+        let def = parse_skill_md(md).expect("Should parse successfully");
+        let sandbox = def.sandbox.expect("sandbox block");
+        assert_eq!(
+            sandbox.level,
+            Some(crate::tools::sandbox::SandboxLevel::Restricted)
+        );
+        assert_eq!(sandbox.writable_paths, Some(vec!["./out".to_string()]));
+        assert_eq!(
+            sandbox.allowed_domains,
+            Some(vec!["generativelanguage.googleapis.com".to_string()])
+        );
+    }
+
     #[test]
     fn test_parse_invalid_returns_none() {
         assert!(parse_skill_md("no frontmatter here").is_none());
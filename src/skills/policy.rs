@@ -135,6 +135,7 @@ mod tests {
             instructions: String::new(),
             parameters: None,
             constraints: SkillConstraints::default(),
+            sandbox: None,
         }
     }
 
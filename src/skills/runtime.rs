@@ -9,6 +9,7 @@ use tokio::sync::RwLock;
 use crate::call_chain::{CallChainContext, CallChainSeed, MAX_CALL_CHAIN_DEPTH};
 use crate::core::extensions::{ExecutionExtension, ExtensionDecision, FinishDecision, PromptDraft};
 use crate::tools::protocol::ToolExecutionEnvelope;
+use crate::tools::sandbox::SessionSandbox;
 use crate::tools::Tool;
 
 use super::arguments::{
@@ -25,6 +26,7 @@ pub struct SkillRuntime {
     policy: SkillToolPolicy,
     registry: SkillRegistry,
     call_chain_seed: CallChainSeed,
+    sandbox: Option<Arc<SessionSandbox>>,
 }
 
 impl SkillRuntime {
@@ -71,6 +73,28 @@ impl SkillRuntime {
             policy: SkillToolPolicy::new(),
             registry,
             call_chain_seed,
+            sandbox: None,
+        }
+    }
+
+    /// Let skills with a `sandbox` frontmatter block tighten this session's
+    /// sandbox while they are active.
+    pub fn with_session_sandbox(mut self, sandbox: Arc<SessionSandbox>) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    fn apply_skill_sandbox(&self, def: &SkillDef) {
+        let Some(sandbox) = self.sandbox.as_ref() else {
+            return;
+        };
+        match def.sandbox.as_ref() {
+            Some(spec) => {
+                let work_dir = std::env::current_dir().unwrap_or_else(|_| ".".into());
+                let child = spec.child_policy(sandbox.base_policy(), &work_dir);
+                sandbox.tighten(def.meta.name.clone(), &child);
+            }
+            None => sandbox.restore(),
         }
     }
 
@@ -177,6 +201,7 @@ impl SkillRuntime {
             compatibility_notes,
         };
 
+        self.apply_skill_sandbox(def);
         *self.invocation.write().await = Some(invocation);
         tracing::info!("Skill '{}' activated", def.meta.name);
         Ok(())
@@ -190,11 +215,27 @@ impl SkillRuntime {
             .as_ref()
             .map(|invocation| invocation.skill_name.clone());
         *self.invocation.write().await = None;
+        if let Some(sandbox) = self.sandbox.as_ref() {
+            sandbox.restore();
+        }
         if let Some(skill_name) = skill_name {
             tracing::info!("Skill '{}' deactivated", skill_name);
         }
     }
 
+    /// Copy of the active invocation, carried over when a session is forked.
+    pub async fn snapshot_invocation(&self) -> Option<SkillInvocation> {
+        self.invocation.read().await.clone()
+    }
+
+    /// Resume an invocation copied from another session.
+    pub async fn restore_invocation(&self, invocation: SkillInvocation) {
+        if let Some(def) = self.registry.get(&invocation.skill_name) {
+            self.apply_skill_sandbox(def);
+        }
+        *self.invocation.write().await = Some(invocation);
+    }
+
     pub async fn is_active(&self) -> bool {
         self.invocation.read().await.is_some()
     }
@@ -470,6 +511,7 @@ mod tests {
             instructions: "Do the thing.".to_string(),
             parameters: None,
             constraints: SkillConstraints::default(),
+            sandbox: None,
         }
     }
 
@@ -486,6 +528,34 @@ mod tests {
         assert!(!rt.is_active().await);
     }
 
+    #[tokio::test]
+    async fn test_skill_sandbox_tightens_and_restores_session_policy() {
+        use crate::skills::definition::SkillSandbox;
+        use crate::tools::sandbox::{SandboxEnforcer, SandboxLevel};
+
+        let sandbox = Arc::new(SessionSandbox::new(Arc::new(SandboxEnforcer::disabled())));
+        let rt = SkillRuntime::new().with_session_sandbox(sandbox.clone());
+        let mut skill = make_test_skill(&["web_fetch"]);
+        skill.sandbox = Some(SkillSandbox {
+            allowed_domains: Some(vec!["example.com".to_string()]),
+            ..Default::default()
+        });
+        assert!(sandbox.current().is_none());
+
+        rt.activate_skill(&skill, None, None).await.unwrap();
+        let enforcer = sandbox.current().expect("skill policy should be active");
+        assert_eq!(enforcer.default_policy().level, SandboxLevel::Restricted);
+        assert_eq!(
+            enforcer.default_policy().allowed_domains,
+            vec!["example.com"]
+        );
+        assert_eq!(sandbox.overlay_owner().as_deref(), Some("test_skill"));
+
+        rt.deactivate_skill().await;
+        assert!(sandbox.current().is_none());
+        assert!(sandbox.overlay_owner().is_none());
+    }
+
     #[tokio::test]
     async fn test_activate_with_raw_and_json_args_injects_prompt_sections() {
         let rt = SkillRuntime::new();
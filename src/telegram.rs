@@ -37,6 +37,30 @@ enum Command {
     Autopilot(String),
     #[command(description = "switch to manual mode")]
     Manual,
+    #[command(description = "show sandbox policy and recent violations.")]
+    Sandbox,
+    #[command(description = "review withheld content: /quarantine [list|show|release|drop] [id]")]
+    Quarantine(String),
+    #[command(description = "branch the conversation: /fork [name]")]
+    Fork(String),
+    #[command(description = "list forks or switch: /branches [checkout <name|main>]")]
+    Branches(String),
+    #[command(description = "pin a fork's final reply into its parent: /mergenotes [name]")]
+    MergeNotes(String),
+    #[command(description = "bundle this session into an archive: /export [path]")]
+    Export(String),
+    #[command(description = "restore an exported session as a branch: /import <archive> [name]")]
+    Import(String),
+    #[command(description = "keep a note in every prompt: /pin [note]")]
+    Pin(String),
+    #[command(description = "drop pinned context: /unpin <id|path|all>")]
+    Unpin(String),
+    #[command(description = "code index: /index [status|refresh|reindex]")]
+    Index(String),
+    #[command(description = "read documents into memory: /ingest <path|glob>")]
+    Ingest(String),
+    #[command(description = "memories: /memory [review|approve <id|all>|reject <id|all>|stats]")]
+    Memory(String),
 }
 
 pub struct TelegramOutputRouter {
@@ -291,6 +291,18 @@ pub(super) async fn handle_command(
         TgCommand::Context(args) => Command::Context(args),
         TgCommand::Autopilot(args) => Command::Autopilot(args),
         TgCommand::Manual => Command::Manual,
+        TgCommand::Sandbox => Command::Sandbox,
+        TgCommand::Quarantine(args) => Command::Quarantine(args),
+        TgCommand::Fork(name) => Command::Fork(name),
+        TgCommand::Branches(args) => Command::Branches(args),
+        TgCommand::MergeNotes(name) => Command::MergeNotes(name),
+        TgCommand::Export(path) => Command::Export(path),
+        TgCommand::Import(args) => Command::Import(args),
+        TgCommand::Pin(target) => Command::Pin(target),
+        TgCommand::Unpin(target) => Command::Unpin(target),
+        TgCommand::Index(args) => Command::Index(args),
+        TgCommand::Ingest(args) => Command::Ingest(args),
+        TgCommand::Memory(args) => Command::Memory(args),
     };
 
     let mut autopilot_goal = None;
@@ -182,6 +182,7 @@ impl Tool for BashTool {
 
         let timeout_secs = parsed_args.timeout.unwrap_or(30);
         let cmd_str = parsed_args.command;
+        ctx.guard_outgoing("bash", &cmd_str)?;
         let start = Instant::now();
 
         tracing::info!("Executing bash via PTY: {}", cmd_str);
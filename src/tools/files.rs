@@ -53,9 +53,8 @@ impl Tool for PatchFileTool {
 
         // Sandbox path guard
         if let Some(sandbox) = &ctx.sandbox {
-            let policy = sandbox.default_policy();
             sandbox
-                .check_path_access(std::path::Path::new(&parsed.path), true, policy)
+                .guard_path_access(ctx, "patch_file", std::path::Path::new(&parsed.path), true)
                 .map_err(|v| crate::tools::ToolError::ExecutionFailed(v.to_string()))?;
         }
 
@@ -164,9 +163,8 @@ impl Tool for WriteFileTool {
 
         // Sandbox path guard
         if let Some(sandbox) = &ctx.sandbox {
-            let policy = sandbox.default_policy();
             sandbox
-                .check_path_access(std::path::Path::new(&parsed.path), true, policy)
+                .guard_path_access(ctx, "write_file", std::path::Path::new(&parsed.path), true)
                 .map_err(|v| ToolError::ExecutionFailed(v.to_string()))?;
         }
 
@@ -208,6 +206,10 @@ pub struct ReadFileArgs {
     pub thought: Option<String>,
     /// Path to the file to read
     pub path: String,
+    /// First line to return (1-based, inclusive). Omit to start at the top.
+    pub start_line: Option<usize>,
+    /// Last line to return (1-based, inclusive). Omit to read to the end.
+    pub end_line: Option<usize>,
 }
 
 pub struct ReadFileTool;
@@ -219,7 +221,7 @@ impl Tool for ReadFileTool {
     }
 
     fn description(&self) -> String {
-        "Reads the exact contents of a file from disk.".to_string()
+        "Reads the exact contents of a file from disk. Pass start_line/end_line to read only a line range, e.g. a span returned by search_knowledge_base.".to_string()
     }
 
     fn parameters_schema(&self) -> serde_json::Value {
@@ -240,14 +242,53 @@ impl Tool for ReadFileTool {
             serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
 
         if let Some(sandbox) = &ctx.sandbox {
-            let policy = sandbox.default_policy();
             sandbox
-                .check_path_access(std::path::Path::new(&parsed.path), false, policy)
+                .guard_path_access(ctx, "read_file", std::path::Path::new(&parsed.path), false)
                 .map_err(|v| ToolError::ExecutionFailed(v.to_string()))?;
         }
 
         match std::fs::read_to_string(&parsed.path) {
             Ok(content) => {
+                let ranged = parsed.start_line.is_some() || parsed.end_line.is_some();
+                let mut range = None;
+                let content = if ranged {
+                    let total = content.lines().count();
+                    let first = parsed.start_line.unwrap_or(1).max(1);
+                    let last = parsed.end_line.unwrap_or(total).min(total);
+                    if first > last {
+                        return serialize_tool_envelope(
+                            "read_file",
+                            false,
+                            format!(
+                                "Invalid line range {}-{} for {} ({} lines)",
+                                first, last, parsed.path, total
+                            ),
+                            Some(1),
+                            Some(start.elapsed().as_millis()),
+                            false,
+                        );
+                    }
+                    range = Some(format!("{}-{}", first, last));
+                    content
+                        .lines()
+                        .skip(first - 1)
+                        .take(last + 1 - first)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                } else {
+                    content
+                };
+                let summary = match (parsed.start_line, parsed.end_line) {
+                    (None, None) => format!("Direct read of {}", parsed.path),
+                    (start_line, end_line) => format!(
+                        "Direct read of {} lines {}-{}",
+                        parsed.path,
+                        start_line.unwrap_or(1),
+                        end_line
+                            .map(|n| n.to_string())
+                            .unwrap_or_else(|| "end".into())
+                    ),
+                };
                 let truncated_content = crate::utils::truncate_tool_output(&content);
                 let truncated = truncated_content.len() != content.len();
                 StructuredToolOutput::new(
@@ -259,11 +300,8 @@ impl Tool for ReadFileTool {
                     truncated,
                 )
                 .mark_verbatim()
-                .with_evidence(
-                    "file",
-                    parsed.path.clone(),
-                    format!("Direct read of {}", parsed.path),
-                )
+                .with_evidence("file", parsed.path.clone(), summary)
+                .with_evidence_range(range)
                 .to_json_string()
             }
             Err(e) => serialize_tool_envelope(
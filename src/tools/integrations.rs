@@ -41,7 +41,7 @@ impl Tool for SendTelegramMessageTool {
     async fn execute(
         &self,
         args: serde_json::Value,
-        _ctx: &crate::tools::ToolContext,
+        ctx: &crate::tools::ToolContext,
     ) -> Result<String, crate::tools::ToolError> {
         let parsed: SendTelegramMessageArgs =
             serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
@@ -62,6 +62,9 @@ impl Tool for SendTelegramMessageTool {
             ));
         }
 
+        ctx.guard_outgoing("send_telegram_message", &parsed.chat_id)?;
+        ctx.guard_outgoing("send_telegram_message", &parsed.text)?;
+
         let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
         let resp = self
             .client
@@ -7,7 +7,7 @@ use serde_json::Value;
 use tokio::sync::Notify;
 
 use crate::core::extensions::ExecutionExtension;
-use crate::core::{ExecutionGuardSignal, ExecutionGuardState};
+use crate::core::{AgentOutput, ExecutionGuardSignal, ExecutionGuardState};
 use crate::tools::{Tool, ToolContext};
 use crate::trace::TraceContext;
 
@@ -126,6 +126,7 @@ pub(crate) struct UnifiedToolExecutorConfig {
     pub(crate) is_autopilot: bool,
     pub(crate) todos_path: PathBuf,
     pub(crate) execution_guard_state: Arc<Mutex<ExecutionGuardState>>,
+    pub(crate) output: Option<Arc<dyn AgentOutput>>,
 }
 
 #[derive(Clone)]
@@ -143,6 +144,7 @@ pub(crate) struct UnifiedToolExecutor {
     is_autopilot: bool,
     todos_path: PathBuf,
     execution_guard_state: Arc<Mutex<ExecutionGuardState>>,
+    output: Option<Arc<dyn AgentOutput>>,
 }
 
 impl UnifiedToolExecutor {
@@ -159,6 +161,7 @@ impl UnifiedToolExecutor {
             is_autopilot: config.is_autopilot,
             todos_path: config.todos_path,
             execution_guard_state: config.execution_guard_state,
+            output: config.output,
         }
     }
 
@@ -222,6 +225,7 @@ impl UnifiedToolExecutor {
         ctx.visible_tools = self.visible_tools.clone();
         ctx.call_chain_budget.remaining_steps = Some(self.remaining_steps());
         ctx.call_chain_budget.remaining_timeout_sec = self.remaining_session_timeout_sec();
+        ctx.output = self.output.clone().map(crate::tools::protocol::ToolOutput);
         if let Some(trace_ctx) = trace_ctx {
             ctx.trace = Some(crate::tools::protocol::ToolTraceContext {
                 trace_id: trace_ctx.trace_id.clone(),
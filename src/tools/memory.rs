@@ -40,11 +40,12 @@ impl Tool for ReadMemoryTool {
         _ctx: &crate::tools::ToolContext,
     ) -> Result<String, crate::tools::ToolError> {
         let start = Instant::now();
-        let mem = self.workspace.read_memory().await?;
+        let (mem, version) = self.workspace.read_versioned().await?;
         let output = if mem.is_empty() {
-            "Memory is empty.".to_string()
+            format!("Memory is empty. (version {})", version)
         } else {
-            mem
+            // The version lets a later write detect edits made in between.
+            format!("Version: {}\n\n{}", version, mem)
         };
         serialize_tool_envelope(
             "read_workspace_memory",
@@ -61,9 +62,30 @@ pub struct WriteMemoryTool {
     pub workspace: Arc<crate::memory::WorkspaceMemory>,
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteMemoryAction {
+    /// Replace the whole file with `content`.
+    #[default]
+    Overwrite,
+    /// Add `content` as a bullet at the end of `section`.
+    Append,
+    /// Replace everything under the `section` heading with `content`.
+    ReplaceSection,
+    /// Remove the bullet in `section` matching `content`.
+    RemoveEntry,
+}
+
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct WriteMemoryArgs {
+    #[serde(default)]
+    pub action: WriteMemoryAction,
+    /// `## ` section name; required for every action except overwrite. Created if missing.
+    pub section: Option<String>,
+    #[serde(default)]
     pub content: String,
+    /// Version from read_workspace_memory; the write fails if MEMORY.md changed since.
+    pub expected_version: Option<String>,
 }
 
 impl WriteMemoryTool {
@@ -79,7 +101,7 @@ impl Tool for WriteMemoryTool {
     }
 
     fn description(&self) -> String {
-        "Overwrites the entire workspace long-term memory (MEMORY.md) with new content.".to_string()
+        "Edits the workspace long-term memory (MEMORY.md), a markdown document of `## ` sections. Prefer `append`, `replace_section` or `remove_entry` on one section over `overwrite`, which replaces the whole file. Pass `expected_version` from read_workspace_memory to avoid overwriting concurrent edits.".to_string()
     }
 
     fn parameters_schema(&self) -> Value {
@@ -95,11 +117,55 @@ impl Tool for WriteMemoryTool {
         let parsed: WriteMemoryArgs =
             serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
 
-        self.workspace.write_memory(&parsed.content).await?;
+        let section = parsed.section.as_deref().map(str::trim).unwrap_or("");
+        if parsed.action != WriteMemoryAction::Overwrite && section.is_empty() {
+            return Err(ToolError::InvalidArguments(
+                "`section` is required for this action".to_string(),
+            ));
+        }
+        let expected = parsed.expected_version.as_deref();
+        let result = match parsed.action {
+            WriteMemoryAction::Overwrite => {
+                let content = parsed.content.clone();
+                self.workspace
+                    .edit(expected, move |doc| {
+                        *doc = crate::memory::MemoryDocument::parse(&content);
+                        Ok(())
+                    })
+                    .await
+                    .map(|(_, version)| format!("Memory overwritten (version {}).", version))
+            }
+            WriteMemoryAction::Append => self
+                .workspace
+                .append(section, &parsed.content, expected)
+                .await
+                .map(|version| format!("Appended to '{}' (version {}).", section, version)),
+            WriteMemoryAction::ReplaceSection => self
+                .workspace
+                .replace_section(section, &parsed.content, expected)
+                .await
+                .map(|version| format!("Replaced section '{}' (version {}).", section, version)),
+            WriteMemoryAction::RemoveEntry => self
+                .workspace
+                .remove_entry(section, &parsed.content, expected)
+                .await
+                .map(|(removed, version)| {
+                    format!(
+                        "Removed '{}' from '{}' (version {}).",
+                        removed, section, version
+                    )
+                }),
+        };
+        let (ok, output) = match result {
+            Ok(message) => (true, message),
+            Err(crate::memory::MemoryError::Io(e)) => return Err(e.into()),
+            // Conflicts and missing entries are for the model to resolve.
+            Err(e) => (false, e.to_string()),
+        };
         serialize_tool_envelope(
             "write_workspace_memory",
-            true,
-            "Memory updated successfully.".to_string(),
+            ok,
+            output,
             None,
             Some(start.elapsed().as_millis()),
             false,
@@ -107,6 +173,64 @@ impl Tool for WriteMemoryTool {
     }
 }
 
+/// Resolves a namespace argument. `session` is the current chat or CLI
+/// session, `project` the repository the agent runs in; anything else is
+/// taken literally. Another session's namespace is never addressable.
+fn resolve_namespace(
+    namespace: Option<&str>,
+    ctx: &crate::tools::protocol::ToolContext,
+) -> Result<String, ToolError> {
+    let namespace = namespace.map(str::trim).unwrap_or("");
+    let resolved = match namespace {
+        "" => crate::rag::DEFAULT_NAMESPACE.to_string(),
+        "session" | "chat" => session_namespace(&ctx.session_id),
+        "project" => project_namespace(),
+        other => other.to_string(),
+    };
+    if resolved.len() > 128
+        || !resolved
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ':' | '_' | '-' | '.' | '/'))
+    {
+        return Err(ToolError::InvalidArguments(format!(
+            "Invalid namespace '{}'",
+            resolved
+        )));
+    }
+    if resolved.starts_with("session:") && resolved != session_namespace(&ctx.session_id) {
+        return Err(ToolError::InvalidArguments(
+            "Other sessions' namespaces are private; use 'session' for this one".to_string(),
+        ));
+    }
+    Ok(resolved)
+}
+
+pub(crate) fn session_namespace(session_id: &str) -> String {
+    format!("session:{}", session_id)
+}
+
+pub(crate) fn project_namespace() -> String {
+    let root = crate::context::instructions::project_root();
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string());
+    format!("project:{}", name)
+}
+
+/// Namespaces searched when the caller does not name one.
+fn visible_namespaces(ctx: &crate::tools::protocol::ToolContext) -> Vec<String> {
+    vec![
+        crate::rag::DEFAULT_NAMESPACE.to_string(),
+        crate::rag::CODE_NAMESPACE.to_string(),
+        crate::rag::DOCS_NAMESPACE.to_string(),
+        project_namespace(),
+        session_namespace(&ctx.session_id),
+    ]
+}
+
+const NAMESPACE_HELP: &str = "Namespace: 'global' (default), 'project' (this repository), 'session' (this chat only), 'code' (the indexed source tree), 'docs' (ingested documents), or a custom name.";
+
 pub struct RagSearchTool {
     pub store: Arc<crate::rag::VectorStore>,
 }
@@ -115,6 +239,8 @@ pub struct RagSearchTool {
 pub struct RagSearchArgs {
     pub query: String,
     pub limit: Option<usize>,
+    /// Only search this namespace. Omit to search global, project, session, code and docs together.
+    pub namespace: Option<String>,
 }
 
 impl RagSearchTool {
@@ -130,7 +256,7 @@ impl Tool for RagSearchTool {
     }
 
     fn description(&self) -> String {
-        "Semantically searches the long-term knowledge base for related information using vector embeddings. Use this when you need to recall past lessons, code snippets, or project guidelines.".to_string()
+        "Semantically searches the long-term knowledge base for related information using vector embeddings. Use this when you need to recall past lessons, code snippets, or project guidelines. The workspace source tree is indexed too: code results carry a `path:start-end` source_path you can pass to read_file as path, start_line and end_line.".to_string()
     }
 
     fn parameters_schema(&self) -> Value {
@@ -144,15 +270,19 @@ impl Tool for RagSearchTool {
     async fn execute(
         &self,
         args: Value,
-        _ctx: &crate::tools::protocol::ToolContext,
+        ctx: &crate::tools::protocol::ToolContext,
     ) -> Result<String, ToolError> {
         let parsed: RagSearchArgs =
             serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
 
         let limit = parsed.limit.unwrap_or(3);
+        let namespaces = match parsed.namespace.as_deref() {
+            Some(namespace) => vec![resolve_namespace(Some(namespace), ctx)?],
+            None => visible_namespaces(ctx),
+        };
         let results = self
             .store
-            .search(&parsed.query, limit)
+            .search_in(&parsed.query, limit, namespaces)
             .await
             .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
 
@@ -174,7 +304,14 @@ pub struct RagInsertTool {
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct RagInsertArgs {
     pub content: String,
+    /// Short key for where this knowledge came from; with `upsert`, the key to replace.
     pub source: String,
+    /// 'global' (default), 'project', 'session', or a custom name.
+    pub namespace: Option<String>,
+    /// Replace existing entries with the same source in this namespace instead of adding another.
+    pub upsert: Option<bool>,
+    /// Forget the entry after this many days; for facts that are only true for a while.
+    pub expires_in_days: Option<u32>,
 }
 
 impl RagInsertTool {
@@ -190,7 +327,7 @@ impl Tool for RagInsertTool {
     }
 
     fn description(&self) -> String {
-        "Saves important concepts, code patterns, or project lessons into the vector knowledge base so it can be semantically retrieved in the future.".to_string()
+        format!("Saves important concepts, code patterns, or project lessons into the vector knowledge base so it can be semantically retrieved in the future. Set upsert to correct an earlier entry with the same source. {}", NAMESPACE_HELP)
     }
 
     fn parameters_schema(&self) -> Value {
@@ -200,16 +337,287 @@ impl Tool for RagInsertTool {
     async fn execute(
         &self,
         args: Value,
-        _ctx: &crate::tools::protocol::ToolContext,
+        ctx: &crate::tools::protocol::ToolContext,
     ) -> Result<String, ToolError> {
         let parsed: RagInsertArgs =
             serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+        let namespace = resolve_namespace(parsed.namespace.as_deref(), ctx)?;
+        if namespace == crate::rag::CODE_NAMESPACE {
+            return Err(ToolError::InvalidArguments(
+                "The 'code' namespace is maintained by the indexer; use /index refresh".to_string(),
+            ));
+        }
 
-        self.store
-            .insert_chunk(parsed.content, parsed.source)
-            .await
-            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let (id, replaced) = if parsed.upsert.unwrap_or(false) {
+            let (id, replaced) = self
+                .store
+                .upsert_chunk(namespace.clone(), parsed.content, parsed.source)
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+            (id, Some(replaced))
+        } else {
+            let id = self
+                .store
+                .insert_chunk_in(namespace.clone(), parsed.content, parsed.source)
+                .await
+                .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+            (id, None)
+        };
+
+        let expiry = match parsed.expires_in_days {
+            Some(days) => {
+                let expires_at = chrono::Utc::now() + chrono::Duration::days(days as i64);
+                self.store
+                    .set_expiry(id, Some(expires_at.timestamp()))
+                    .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+                format!(" It expires on {}.", expires_at.format("%Y-%m-%d"))
+            }
+            None => String::new(),
+        };
+
+        Ok(match replaced {
+            Some(replaced) => format!(
+                "Knowledge saved as #{} in '{}' (replaced {} earlier entr{}).{}",
+                id,
+                namespace,
+                replaced,
+                if replaced == 1 { "y" } else { "ies" },
+                expiry
+            ),
+            None => format!(
+                "Knowledge successfully embedded and saved into long-term vector memory as #{} in '{}'.{}",
+                id, namespace, expiry
+            ),
+        })
+    }
+}
+
+pub struct RagDeleteTool {
+    pub store: Arc<crate::rag::VectorStore>,
+}
 
-        Ok("Knowledge successfully embedded and saved into long-term vector memory.".to_string())
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RagDeleteArgs {
+    /// Id of a single entry, as shown by rag_list.
+    pub id: Option<i64>,
+    /// Delete every entry with this source key instead.
+    pub source: Option<String>,
+    /// Namespace the source key lives in; defaults to 'global'.
+    pub namespace: Option<String>,
+}
+
+impl RagDeleteTool {
+    pub fn new(store: Arc<crate::rag::VectorStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for RagDeleteTool {
+    fn name(&self) -> String {
+        "rag_delete".to_string()
+    }
+
+    fn description(&self) -> String {
+        format!("Removes stale or wrong entries from the vector knowledge base, either one entry by id or every entry with a source key. {}", NAMESPACE_HELP)
+    }
+
+    fn parameters_schema(&self) -> Value {
+        clean_schema(serde_json::to_value(schema_for!(RagDeleteArgs)).unwrap())
+    }
+
+    async fn execute(
+        &self,
+        args: Value,
+        ctx: &crate::tools::protocol::ToolContext,
+    ) -> Result<String, ToolError> {
+        let parsed: RagDeleteArgs =
+            serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+
+        match (parsed.id, parsed.source) {
+            (Some(id), None) => {
+                let Some(namespace) = self.store.chunk_namespace(id) else {
+                    return Ok(format!("No knowledge entry #{}.", id));
+                };
+                // Checks the entry is addressable from this session.
+                resolve_namespace(Some(&namespace), ctx)?;
+                reject_code_namespace(&namespace)?;
+                self.store
+                    .delete_by_id(id)
+                    .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+                Ok(format!(
+                    "Deleted knowledge entry #{} from '{}'.",
+                    id, namespace
+                ))
+            }
+            (None, Some(source)) => {
+                let namespace = resolve_namespace(parsed.namespace.as_deref(), ctx)?;
+                reject_code_namespace(&namespace)?;
+                let deleted = self
+                    .store
+                    .delete_by_source(&namespace, &source)
+                    .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+                Ok(format!(
+                    "Deleted {} entr{} with source '{}' from '{}'.",
+                    deleted,
+                    if deleted == 1 { "y" } else { "ies" },
+                    source,
+                    namespace
+                ))
+            }
+            _ => Err(ToolError::InvalidArguments(
+                "Pass exactly one of id or source".to_string(),
+            )),
+        }
+    }
+}
+
+/// The `code` namespace mirrors the workspace; only the indexer edits it.
+fn reject_code_namespace(namespace: &str) -> Result<(), ToolError> {
+    if namespace == crate::rag::CODE_NAMESPACE {
+        return Err(ToolError::InvalidArguments(
+            "Indexed code is removed by /index refresh when the file changes".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub struct RagListTool {
+    pub store: Arc<crate::rag::VectorStore>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RagListArgs {
+    /// Namespace to list entries from; defaults to 'global'.
+    pub namespace: Option<String>,
+    /// Maximum entries to show (default 20).
+    pub limit: Option<usize>,
+}
+
+impl RagListTool {
+    pub fn new(store: Arc<crate::rag::VectorStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for RagListTool {
+    fn name(&self) -> String {
+        "rag_list".to_string()
+    }
+
+    fn description(&self) -> String {
+        format!("Lists namespaces in the vector knowledge base and the most recent entries of one, with the ids rag_delete takes. {}", NAMESPACE_HELP)
+    }
+
+    fn parameters_schema(&self) -> Value {
+        clean_schema(serde_json::to_value(schema_for!(RagListArgs)).unwrap())
+    }
+
+    fn has_side_effects(&self) -> bool {
+        false
+    }
+
+    async fn execute(
+        &self,
+        args: Value,
+        ctx: &crate::tools::protocol::ToolContext,
+    ) -> Result<String, ToolError> {
+        let parsed: RagListArgs =
+            serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+        let namespace = resolve_namespace(parsed.namespace.as_deref(), ctx)?;
+        let own_session = session_namespace(&ctx.session_id);
+
+        let namespaces: Vec<Value> = self
+            .store
+            .namespaces()
+            .into_iter()
+            .filter(|(name, _)| !name.starts_with("session:") || *name == own_session)
+            .map(|(name, count)| serde_json::json!({"namespace": name, "entries": count}))
+            .collect();
+        let entries = self
+            .store
+            .list_chunks(&namespace, parsed.limit.unwrap_or(20));
+
+        Ok(serde_json::to_string_pretty(&serde_json::json!({
+            "namespaces": namespaces,
+            "namespace": namespace,
+            "entries": entries,
+        }))
+        .unwrap_or_else(|_| "{}".to_string()))
+    }
+}
+
+pub struct IngestTool {
+    pub store: Arc<crate::rag::VectorStore>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct IngestArgs {
+    /// A file, a directory, or a glob such as `docs/**/*.pdf`, relative to the project root.
+    pub path: String,
+    /// Namespace to store the chunks in; defaults to 'docs'.
+    pub namespace: Option<String>,
+}
+
+impl IngestTool {
+    pub fn new(store: Arc<crate::rag::VectorStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Tool for IngestTool {
+    fn name(&self) -> String {
+        "ingest".to_string()
+    }
+
+    fn description(&self) -> String {
+        "Reads PDF, HTML, Markdown and text documents into the vector knowledge base so search_knowledge_base can find them. Each chunk's source records the file and its page or section. Files unchanged since they were last ingested are skipped, so re-running on a folder only picks up edits.".to_string()
+    }
+
+    fn parameters_schema(&self) -> Value {
+        clean_schema(serde_json::to_value(schema_for!(IngestArgs)).unwrap())
+    }
+
+    async fn execute(
+        &self,
+        args: Value,
+        ctx: &crate::tools::protocol::ToolContext,
+    ) -> Result<String, ToolError> {
+        let parsed: IngestArgs =
+            serde_json::from_value(args).map_err(|e| ToolError::InvalidArguments(e.to_string()))?;
+        let namespace = match parsed.namespace.as_deref() {
+            Some(namespace) => resolve_namespace(Some(namespace), ctx)?,
+            None => crate::rag::DOCS_NAMESPACE.to_string(),
+        };
+        if namespace == crate::rag::CODE_NAMESPACE {
+            return Err(ToolError::InvalidArguments(
+                "The 'code' namespace is maintained by the indexer; use /index refresh".to_string(),
+            ));
+        }
+
+        let root = crate::context::instructions::project_root();
+        let files = crate::rag::ingest::resolve(&root, &parsed.path)
+            .map_err(ToolError::InvalidArguments)?;
+        if files.is_empty() {
+            return Err(ToolError::InvalidArguments(format!(
+                "No PDF, HTML, Markdown or text files match '{}'",
+                parsed.path
+            )));
+        }
+        crate::rag::ingest::guard_files(&files, ctx).map_err(ToolError::ExecutionFailed)?;
+
+        let progress = ctx.output.as_ref().map(|output| output.0.as_ref());
+        let report = crate::rag::ingest::ingest_files(
+            &self.store,
+            &root,
+            files,
+            &namespace,
+            ctx.sandbox.clone(),
+            progress,
+        )
+        .await;
+        Ok(report.render())
     }
 }
@@ -22,7 +22,10 @@ pub use lsp::{
     LspFindReferencesTool, LspGetDiagnosticsTool, LspGetSymbolsTool, LspGotoDefinitionTool,
     LspHoverTool,
 };
-pub use memory::{RagInsertTool, RagSearchTool, ReadMemoryTool, WriteMemoryTool};
+pub use memory::{
+    IngestTool, RagDeleteTool, RagInsertTool, RagListTool, RagSearchTool, ReadMemoryTool,
+    WriteMemoryTool,
+};
 pub use protocol::{clean_schema, Tool, ToolContext, ToolDefinition, ToolError};
 pub use scheduler::ManageScheduleTool;
 pub use subagent::SubagentTool;
@@ -70,6 +70,22 @@ pub struct ToolContext {
     /// Sandbox enforcer for OS-level and application-level isolation.
     /// `None` means sandbox is disabled (backward compatible).
     pub sandbox: Option<Arc<super::sandbox::SandboxEnforcer>>,
+    /// Where long-running tools report progress; `None` outside an agent
+    /// loop.
+    pub output: Option<ToolOutput>,
+}
+
+/// The session's output, for [`AgentOutput::on_waiting`] progress from
+/// inside a tool.
+///
+/// [`AgentOutput::on_waiting`]: crate::core::AgentOutput::on_waiting
+#[derive(Clone)]
+pub struct ToolOutput(pub Arc<dyn crate::core::AgentOutput>);
+
+impl std::fmt::Debug for ToolOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ToolOutput")
+    }
 }
 
 impl ToolContext {
@@ -83,8 +99,17 @@ impl ToolContext {
             call_chain_budget: CallChainBudget::default(),
             trace: None,
             sandbox: None,
+            output: None,
         }
     }
+
+    /// Refuse to send `payload` out of the process if it carries a canary.
+    /// The leak is also queued for the session's agent loop, which aborts.
+    pub fn guard_outgoing(&self, tool: &str, payload: &str) -> Result<(), ToolError> {
+        crate::security::scan_outgoing(&self.session_id, &format!("tool:{tool}"), payload).map_err(
+            |leak| ToolError::ExecutionFailed(format!("[Security] {leak}. Request blocked.")),
+        )
+    }
 }
 
 #[async_trait]
@@ -149,6 +174,10 @@ pub struct ToolEffects {
     pub evidence_kind: Option<String>,
     pub evidence_source_path: Option<String>,
     pub evidence_summary: Option<String>,
+    /// Line range (`first-last`) when only part of a file was read; reads
+    /// of different ranges are different evidence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence_range: Option<String>,
     pub payload_kind: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub output_security: Option<ToolOutputSecurity>,
@@ -158,6 +187,10 @@ pub struct ToolEffects {
     /// If set, the tool is requesting that execution pause for user input.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub await_user: Option<UserPromptRequest>,
+    /// Warning from the configured injection detector; replaces the
+    /// phrase-list warning when the output is fenced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub injection_warning: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -195,14 +228,16 @@ impl ToolExecutionEnvelope {
                 }
             });
 
+        let redacted = crate::security::redact_secrets(&self.result.output);
+        let warning = self.effects.injection_warning.as_deref();
         self.result.output = match self.effects.output_security {
             Some(ToolOutputSecurity::Untrusted) => {
-                crate::security::fence_untrusted(source, &self.result.output)
+                crate::security::fence_untrusted_with_warning(source, &redacted, warning)
             }
             Some(ToolOutputSecurity::Verbatim) => {
-                crate::security::fence_verbatim(source, &self.result.output)
+                crate::security::fence_verbatim_with_warning(source, &redacted, warning)
             }
-            None => self.result.output,
+            None => redacted,
         };
 
         self
@@ -252,6 +287,11 @@ impl StructuredToolOutput {
         self
     }
 
+    pub fn with_evidence_range(mut self, range: Option<String>) -> Self {
+        self.effects.evidence_range = range;
+        self
+    }
+
     pub fn with_invalidated_diagnostics(mut self) -> Self {
         self.effects.invalidate_diagnostic_evidence = true;
         self
@@ -5,13 +5,17 @@
 //! `execute_bash`, plus application-level path/network guards for file
 //! and web tools.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Env vars preserved inside the sandbox when `clear_env` is set.
+pub const DEFAULT_KEEP_ENV: &[&str] = &["PATH", "TERM", "HOME", "USER", "LANG"];
 
 // ── SandboxLevel ──────────────────────────────────────────────────────
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum SandboxLevel {
     /// No isolation at all (legacy behavior, full backward compat).
@@ -86,6 +90,24 @@ impl fmt::Display for SandboxViolation {
     }
 }
 
+impl SandboxViolation {
+    /// Short category used in the audit log (`path` or `domain`).
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::PathDenied { .. } => "path",
+            Self::DomainDenied { .. } => "domain",
+        }
+    }
+
+    /// The path or domain that was refused.
+    pub fn target(&self) -> String {
+        match self {
+            Self::PathDenied { path, .. } => path.display().to_string(),
+            Self::DomainDenied { domain, .. } => domain.clone(),
+        }
+    }
+}
+
 // ── SandboxPolicy ─────────────────────────────────────────────────────
 
 /// Describes the isolation constraints for a single tool execution.
@@ -288,6 +310,14 @@ impl OsSandbox {
         Self::Unavailable
     }
 
+    fn describe(&self) -> String {
+        match self {
+            Self::Unavailable => format!("none ({})", Self::unavailable_description()),
+            Self::Bwrap(path) => format!("bwrap ({})", path.display()),
+            Self::SeatbeltExec(path) => format!("sandbox-exec ({})", path.display()),
+        }
+    }
+
     fn platform_name() -> &'static str {
         if cfg!(target_os = "linux") {
             "Linux/bwrap"
@@ -379,11 +409,25 @@ impl SandboxEnforcer {
         }
     }
 
+    /// Clone this enforcer (reusing the detected backend) with a different
+    /// default policy.
+    pub fn with_policy(&self, default_policy: SandboxPolicy) -> Self {
+        Self {
+            os_sandbox: self.os_sandbox.clone(),
+            default_policy,
+        }
+    }
+
     /// Whether OS-level isolation is available on this system.
     pub fn is_available(&self) -> bool {
         self.os_sandbox.is_available()
     }
 
+    /// Human-readable name of the OS-level backend, e.g. `bwrap (/usr/bin/bwrap)`.
+    pub fn backend_description(&self) -> String {
+        self.os_sandbox.describe()
+    }
+
     /// The global default policy (from config).
     pub fn default_policy(&self) -> &SandboxPolicy {
         &self.default_policy
@@ -770,6 +814,38 @@ impl SandboxEnforcer {
         lines.join("\n")
     }
 
+    // ── Audited guards ────────────────────────────────────────────
+
+    /// [`Self::check_path_access`] against the default policy, recording any
+    /// violation in the audit log on behalf of `tool`.
+    pub fn guard_path_access(
+        &self,
+        ctx: &super::ToolContext,
+        tool: &str,
+        path: &Path,
+        write: bool,
+    ) -> Result<(), SandboxViolation> {
+        let policy = self.default_policy();
+        self.check_path_access(path, write, policy)
+            .inspect_err(|violation| {
+                let access = if write { "write" } else { "read" };
+                record_violation(ctx, tool, access, policy, violation);
+            })
+    }
+
+    /// [`Self::check_network_access`] against the default policy, recording
+    /// any violation in the audit log on behalf of `tool`.
+    pub fn guard_network_access(
+        &self,
+        ctx: &super::ToolContext,
+        tool: &str,
+        url: &str,
+    ) -> Result<(), SandboxViolation> {
+        let policy = self.default_policy();
+        self.check_network_access(url, policy)
+            .inspect_err(|violation| record_violation(ctx, tool, "network", policy, violation))
+    }
+
     // ── Path guard ────────────────────────────────────────────────
 
     /// Check whether a file access is permitted by the policy.
@@ -901,6 +977,286 @@ impl SandboxEnforcer {
     }
 }
 
+// ── SessionSandbox ────────────────────────────────────────────────────
+
+/// Per-session sandbox state: the enforcer built from config plus an
+/// optional tightened overlay installed while a skill with its own
+/// `sandbox` frontmatter block is active.
+#[derive(Debug)]
+pub struct SessionSandbox {
+    base: Arc<SandboxEnforcer>,
+    overlay: RwLock<Option<SandboxOverlay>>,
+}
+
+#[derive(Debug, Clone)]
+struct SandboxOverlay {
+    owner: String,
+    enforcer: Arc<SandboxEnforcer>,
+}
+
+impl SessionSandbox {
+    pub fn new(base: Arc<SandboxEnforcer>) -> Self {
+        Self {
+            base,
+            overlay: RwLock::new(None),
+        }
+    }
+
+    /// The policy built from config, before any skill overlay.
+    pub fn base_policy(&self) -> &SandboxPolicy {
+        self.base.default_policy()
+    }
+
+    /// The enforcer currently in effect, ignoring whether it restricts anything.
+    pub fn effective(&self) -> Arc<SandboxEnforcer> {
+        self.overlay
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|overlay| overlay.enforcer.clone())
+            .unwrap_or_else(|| self.base.clone())
+    }
+
+    /// The enforcer tools should see, or `None` when the effective policy is
+    /// unrestricted (keeps unsandboxed sessions on the legacy code path).
+    pub fn current(&self) -> Option<Arc<SandboxEnforcer>> {
+        let enforcer = self.effective();
+        (enforcer.default_policy().level != SandboxLevel::Unrestricted).then_some(enforcer)
+    }
+
+    /// Name of the skill whose policy is currently layered on top, if any.
+    pub fn overlay_owner(&self) -> Option<String> {
+        self.overlay
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|overlay| overlay.owner.clone())
+    }
+
+    /// Install `child` on top of the base policy. The result can only be
+    /// more restrictive than the base; a previous overlay is replaced.
+    pub fn tighten(&self, owner: impl Into<String>, child: &SandboxPolicy) -> SandboxPolicy {
+        let policy = self.base.default_policy().tighten(child);
+        let owner = owner.into();
+        tracing::info!(
+            "Sandbox: tightened session policy for '{}' (level={:?})",
+            owner,
+            policy.level
+        );
+        *self.overlay.write().unwrap_or_else(|e| e.into_inner()) = Some(SandboxOverlay {
+            owner,
+            enforcer: Arc::new(self.base.with_policy(policy.clone())),
+        });
+        policy
+    }
+
+    /// Drop any overlay and return to the base policy.
+    pub fn restore(&self) {
+        if let Some(overlay) = self
+            .overlay
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            tracing::info!("Sandbox: restored session policy after '{}'", overlay.owner);
+        }
+    }
+}
+
+// ── Violation audit log ───────────────────────────────────────────────
+
+/// Trace event name under which sandbox violations are persisted.
+pub const SANDBOX_VIOLATION_EVENT: &str = "sandbox_violation";
+
+/// Persist a violation into the trace store. Tool contexts without a trace
+/// (direct invocations outside an agent run) only get the `tracing` warning
+/// already emitted by the check itself.
+fn record_violation(
+    ctx: &super::ToolContext,
+    tool: &str,
+    access: &str,
+    policy: &SandboxPolicy,
+    violation: &SandboxViolation,
+) {
+    let Some(trace) = ctx.trace.as_ref() else {
+        return;
+    };
+    let trace_ctx = crate::trace::TraceContext {
+        trace_id: trace.trace_id.clone(),
+        run_id: trace.run_id.clone(),
+        session_id: ctx.session_id.clone(),
+        root_session_id: trace.root_session_id.clone(),
+        task_id: trace.task_id.clone(),
+        turn_id: trace.turn_id.clone(),
+        iteration: trace.iteration,
+        parent_span_id: trace.parent_span_id.clone(),
+    };
+    crate::trace::shared_bus().record_event(
+        &trace_ctx,
+        crate::trace::TraceActor::Tool,
+        SANDBOX_VIOLATION_EVENT,
+        crate::trace::TraceStatus::Error,
+        Some(format!(
+            "{} denied {} access to {}",
+            tool,
+            access,
+            violation.target()
+        )),
+        serde_json::json!({
+            "tool": tool,
+            "kind": violation.kind(),
+            "target": violation.target(),
+            "access": access,
+            "level": policy.level,
+            "skill": ctx.active_skill_name,
+        }),
+    );
+}
+
+/// One audited violation, as read back from the trace store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxViolationEntry {
+    pub ts_unix_ms: u64,
+    pub session_id: String,
+    pub run_id: String,
+    pub tool: String,
+    pub kind: String,
+    pub target: String,
+    pub access: String,
+    pub level: String,
+    pub skill: Option<String>,
+}
+
+impl SandboxViolationEntry {
+    fn from_record(record: &crate::trace::TraceRecord) -> Self {
+        let attr = |key: &str| {
+            record
+                .attrs
+                .get(key)
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        };
+        Self {
+            ts_unix_ms: record.ts_unix_ms,
+            session_id: record.session_id.clone(),
+            run_id: record.run_id.clone(),
+            tool: attr("tool").unwrap_or_default(),
+            kind: attr("kind").unwrap_or_default(),
+            target: attr("target").unwrap_or_default(),
+            access: attr("access").unwrap_or_default(),
+            level: attr("level").unwrap_or_default(),
+            skill: attr("skill"),
+        }
+    }
+}
+
+/// The most recent violations for `session_id` (including its subagents),
+/// newest first.
+pub fn recent_violations(session_id: &str, limit: usize) -> Vec<SandboxViolationEntry> {
+    crate::trace::recent_records_by_name(SANDBOX_VIOLATION_EVENT, Some(session_id), limit)
+        .iter()
+        .map(SandboxViolationEntry::from_record)
+        .collect()
+}
+
+/// Snapshot of a session's sandbox state, served by `/sandbox` and ACP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxReport {
+    pub session_id: String,
+    pub level: SandboxLevel,
+    pub backend: String,
+    pub os_isolation_available: bool,
+    pub active_skill_overlay: Option<String>,
+    pub writable_paths: Vec<PathBuf>,
+    pub allowed_domains: Vec<String>,
+    pub isolate_network: bool,
+    pub hidden_paths: usize,
+    pub recent_violations: Vec<SandboxViolationEntry>,
+}
+
+impl SandboxReport {
+    /// Build a report for `session_id`. `sandbox` is `None` when the session
+    /// is not loaded; the policy then falls back to the config defaults.
+    pub fn build(session_id: &str, sandbox: Option<&SessionSandbox>, limit: usize) -> Self {
+        let (enforcer, overlay) = match sandbox {
+            Some(sandbox) => (sandbox.effective(), sandbox.overlay_owner()),
+            None => {
+                let config = crate::config::AppConfig::load().sandbox.unwrap_or_default();
+                let work_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+                let policy = config.build_default_policy(&work_dir);
+                (Arc::new(SandboxEnforcer::detect(policy)), None)
+            }
+        };
+        let policy = enforcer.default_policy();
+        Self {
+            session_id: session_id.to_string(),
+            level: policy.level,
+            backend: enforcer.backend_description(),
+            os_isolation_available: enforcer.is_available(),
+            active_skill_overlay: overlay,
+            writable_paths: policy.writable_paths.clone(),
+            allowed_domains: policy.allowed_domains.clone(),
+            isolate_network: policy.isolate_network,
+            hidden_paths: policy.hidden_paths.len(),
+            recent_violations: recent_violations(session_id, limit),
+        }
+    }
+
+    /// Plain-text rendering shared by the chat frontends.
+    pub fn render(&self) -> String {
+        let mut lines = vec![
+            format!("Sandbox for session {}", self.session_id),
+            format!("- Level: {:?}", self.level),
+            format!("- Backend: {}", self.backend),
+        ];
+        if let Some(owner) = &self.active_skill_overlay {
+            lines.push(format!("- Tightened by active skill: {}", owner));
+        }
+        if !self.writable_paths.is_empty() {
+            let paths: Vec<String> = self
+                .writable_paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            lines.push(format!("- Writable: {}", paths.join(", ")));
+        }
+        if !self.allowed_domains.is_empty() {
+            lines.push(format!(
+                "- Allowed domains: {}",
+                self.allowed_domains.join(", ")
+            ));
+        }
+        lines.push(format!(
+            "- Network isolated: {}",
+            if self.isolate_network { "yes" } else { "no" }
+        ));
+        lines.push(format!("- Hidden paths: {}", self.hidden_paths));
+
+        if self.recent_violations.is_empty() {
+            lines.push("No recent violations.".to_string());
+        } else {
+            lines.push(format!(
+                "Recent violations ({}):",
+                self.recent_violations.len()
+            ));
+            for entry in &self.recent_violations {
+                let time = chrono::DateTime::from_timestamp_millis(entry.ts_unix_ms as i64)
+                    .map(|dt| {
+                        dt.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                lines.push(format!(
+                    "  {} {} {} {} `{}` (level={})",
+                    time, entry.tool, entry.access, entry.kind, entry.target, entry.level
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
 // ── SBPL helpers ──────────────────────────────────────────────────────
 
 /// Escape a path string for embedding in an SBPL profile (double-quoted).
@@ -985,19 +1341,9 @@ impl SandboxConfig {
         PathBuf::from(path)
     }
 
-    /// Build the default `SandboxPolicy` from this config, using the given
-    /// working directory as the primary writable path.
-    pub fn build_default_policy(&self, work_dir: &Path) -> SandboxPolicy {
-        let level = self.parsed_level();
-        if level == SandboxLevel::Unrestricted {
-            return SandboxPolicy::default();
-        }
-
-        let mut writable_paths = vec![work_dir.to_path_buf()];
-        if let Some(extra) = &self.writable_paths {
-            writable_paths.extend(extra.iter().map(|p| Self::expand_tilde(p)));
-        }
-
+    /// `hidden_paths` with `~` expanded, or the built-in list of credential
+    /// and history locations when unset. Applies at every sandbox level.
+    pub fn resolved_hidden_paths(&self) -> Vec<PathBuf> {
         let default_hidden = [
             // Private keys / SSH / GPG / cloud credentials
             "~/.ssh",
@@ -1037,8 +1383,7 @@ impl SandboxConfig {
             "/etc/gshadow",
             "/etc/sudoers",
         ];
-        let hidden_paths: Vec<PathBuf> = self
-            .hidden_paths
+        self.hidden_paths
             .as_deref()
             .unwrap_or(
                 &default_hidden
@@ -1048,7 +1393,23 @@ impl SandboxConfig {
             )
             .iter()
             .map(|p| Self::expand_tilde(p))
-            .collect();
+            .collect()
+    }
+
+    /// Build the default `SandboxPolicy` from this config, using the given
+    /// working directory as the primary writable path.
+    pub fn build_default_policy(&self, work_dir: &Path) -> SandboxPolicy {
+        let level = self.parsed_level();
+        if level == SandboxLevel::Unrestricted {
+            return SandboxPolicy::default();
+        }
+
+        let mut writable_paths = vec![work_dir.to_path_buf()];
+        if let Some(extra) = &self.writable_paths {
+            writable_paths.extend(extra.iter().map(|p| Self::expand_tilde(p)));
+        }
+
+        let hidden_paths = self.resolved_hidden_paths();
 
         let allowed_domains = self.allowed_domains.clone().unwrap_or_else(|| {
             vec![
@@ -1071,13 +1432,7 @@ impl SandboxConfig {
                 .and_then(|b| b.isolate_pid)
                 .unwrap_or(true),
             clear_env: level == SandboxLevel::Strict,
-            keep_env: vec![
-                "PATH".into(),
-                "TERM".into(),
-                "HOME".into(),
-                "USER".into(),
-                "LANG".into(),
-            ],
+            keep_env: DEFAULT_KEEP_ENV.iter().map(|k| k.to_string()).collect(),
             allowed_domains,
             hidden_paths,
         }
@@ -1632,4 +1987,46 @@ mod tests {
         assert!(msg.contains("/workspace"));
         assert!(msg.contains("Action needed"));
     }
+
+    #[test]
+    fn test_violation_entry_from_trace_record() {
+        let violation = SandboxViolation::DomainDenied {
+            domain: "evil.com".into(),
+            allowed: vec!["github.com".into()],
+        };
+        let record = crate::trace::TraceRecord {
+            schema_version: crate::schema::CURRENT_SCHEMA_VERSION,
+            record_id: "rec".into(),
+            trace_id: "trace".into(),
+            run_id: "run".into(),
+            span_id: None,
+            parent_span_id: None,
+            session_id: "sess".into(),
+            task_id: None,
+            turn_id: None,
+            iteration: None,
+            actor: crate::trace::TraceActor::Tool,
+            kind: crate::trace::TraceKind::Event,
+            name: SANDBOX_VIOLATION_EVENT.into(),
+            status: crate::trace::TraceStatus::Error,
+            ts_unix_ms: 0,
+            duration_ms: None,
+            level: crate::trace::TraceLevel::Normal,
+            summary: None,
+            attrs: serde_json::json!({
+                "tool": "web_fetch",
+                "kind": violation.kind(),
+                "target": violation.target(),
+                "access": "network",
+                "level": SandboxLevel::Strict,
+            }),
+        };
+
+        let entry = SandboxViolationEntry::from_record(&record);
+        assert_eq!(entry.tool, "web_fetch");
+        assert_eq!(entry.kind, "domain");
+        assert_eq!(entry.target, "evil.com");
+        assert_eq!(entry.level, "strict");
+        assert!(entry.skill.is_none());
+    }
 }
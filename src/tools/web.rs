@@ -96,20 +96,19 @@ impl Tool for WebFetchTool {
                 "url must start with http:// or https://".to_string(),
             ));
         }
+        ctx.guard_outgoing("web_fetch", url)?;
 
         // Sandbox network guard
         if let Some(sandbox) = &ctx.sandbox {
-            let policy = sandbox.default_policy();
             sandbox
-                .check_network_access(url, policy)
+                .guard_network_access(ctx, "web_fetch", url)
                 .map_err(|v| ToolError::ExecutionFailed(v.to_string()))?;
         }
 
         let output_path = if let Some(path) = &parsed.output_path {
             if let Some(sandbox) = &ctx.sandbox {
-                let policy = sandbox.default_policy();
                 sandbox
-                    .check_path_access(std::path::Path::new(path), true, policy)
+                    .guard_path_access(ctx, "web_fetch", std::path::Path::new(path), true)
                     .map_err(|v| ToolError::ExecutionFailed(v.to_string()))?;
             }
             Some(std::path::PathBuf::from(path))
@@ -273,11 +272,11 @@ impl Tool for TavilySearchTool {
                 "query cannot be empty".to_string(),
             ));
         }
+        ctx.guard_outgoing("web_search", query)?;
 
         if let Some(sandbox) = &ctx.sandbox {
-            let policy = sandbox.default_policy();
             sandbox
-                .check_network_access("https://api.tavily.com/search", policy)
+                .guard_network_access(ctx, "web_search", "https://api.tavily.com/search")
                 .map_err(|v| ToolError::ExecutionFailed(v.to_string()))?;
         }
 
@@ -342,7 +341,7 @@ impl Tool for TavilySearchTool {
 /// 1. Strip noise tags (script, style, nav, header, footer, aside, svg, noscript)
 /// 2. Convert remaining HTML → Markdown via `fast_html2md`
 /// 3. Collapse excessive blank lines
-fn html_to_clean_markdown(html: &str) -> String {
+pub(crate) fn html_to_clean_markdown(html: &str) -> String {
     static RE_NOISE: once_cell::sync::Lazy<Vec<regex::Regex>> = once_cell::sync::Lazy::new(|| {
         [
             "script", "style", "nav", "header", "footer", "aside", "svg", "noscript", "iframe",
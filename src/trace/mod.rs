@@ -1,6 +1,8 @@
 mod bus;
 mod model;
+pub mod prompt;
 mod query;
+pub mod retention;
 mod sqlite;
 
 use std::sync::Arc;
@@ -15,7 +17,7 @@ pub use model::{
 };
 pub use query::{
     find_run_for_subsession, get_artifacts, get_records, get_run, get_run_overview, get_tree,
-    list_runs, RecordQuery, RunQuery,
+    import_run, list_runs, recent_records_by_name, RecordQuery, RunQuery,
 };
 
 static TRACE_BUS: Lazy<Arc<TraceBus>> = Lazy::new(|| Arc::new(TraceBus::new()));
@@ -135,6 +135,43 @@ pub fn get_records(run_id: &str, query: &RecordQuery) -> Vec<TraceRecord> {
     records
 }
 
+/// Most recent records named `name` across all runs, newest first. When
+/// `session_id` is set, records from its subagent sessions are included.
+pub fn recent_records_by_name(
+    name: &str,
+    session_id: Option<&str>,
+    limit: usize,
+) -> Vec<TraceRecord> {
+    super::sqlite::get_recent_records_by_name(name, session_id, limit).unwrap_or_default()
+}
+
+/// Write a run captured elsewhere (e.g. a session archive) into the local
+/// trace center, both as JSON files and in the SQLite index.
+pub fn import_run(summary: &RunSummary, records: &[TraceRecord]) -> std::io::Result<()> {
+    let summary_path = StoragePaths::trace_run_summary_file(&summary.run_id);
+    let records_path = StoragePaths::trace_run_records_file(&summary.run_id);
+    for dir in [summary_path.parent(), records_path.parent()]
+        .into_iter()
+        .flatten()
+    {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut lines = String::new();
+    for record in records {
+        lines.push_str(&serde_json::to_string(record).map_err(std::io::Error::other)?);
+        lines.push('\n');
+        super::sqlite::persist_record(record);
+    }
+    std::fs::write(records_path, lines)?;
+    std::fs::write(
+        summary_path,
+        serde_json::to_string_pretty(summary).map_err(std::io::Error::other)?,
+    )?;
+    super::sqlite::persist_run(summary);
+    Ok(())
+}
+
 fn json_records(run_id: &str) -> Vec<TraceRecord> {
     let path = StoragePaths::trace_run_records_file(run_id);
     let Ok(content) = std::fs::read_to_string(path) else {
@@ -0,0 +1,65 @@
+//! Trace retention: runs whose last activity is older than
+//! `[trace] retention_days` are deleted (JSON files and index rows), then
+//! the prompt blobs no remaining request refers to.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::query::{list_runs, RunQuery};
+use crate::schema::StoragePaths;
+
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+/// Unreferenced blobs younger than this are kept: a request writes its
+/// blobs before its event reaches the index.
+const BLOB_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// `[trace]` in `config.toml`.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct TraceConfig {
+    /// Days a run is kept after its last activity; 0 keeps runs forever
+    /// (default 30).
+    pub retention_days: Option<u64>,
+}
+
+impl TraceConfig {
+    pub fn retention(&self) -> Option<Duration> {
+        match self.retention_days.unwrap_or(DEFAULT_RETENTION_DAYS) {
+            0 => None,
+            days => Some(Duration::from_secs(days * 24 * 60 * 60)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TraceGcReport {
+    pub runs: usize,
+    pub blobs: usize,
+}
+
+/// Apply `config`'s retention to the trace store.
+pub fn gc(config: &TraceConfig) -> TraceGcReport {
+    let Some(retention) = config.retention() else {
+        return TraceGcReport::default();
+    };
+    let cutoff = super::unix_ms_now().saturating_sub(retention.as_millis() as u64);
+    let mut report = TraceGcReport::default();
+    for run in list_runs(&RunQuery::default()) {
+        let last_active = run
+            .updated_at_unix_ms
+            .max(run.finished_at_unix_ms.unwrap_or(0))
+            .max(run.started_at_unix_ms);
+        if last_active >= cutoff {
+            continue;
+        }
+        let _ = std::fs::remove_file(StoragePaths::trace_run_summary_file(&run.run_id));
+        let _ = std::fs::remove_file(StoragePaths::trace_run_records_file(&run.run_id));
+        super::sqlite::delete_run(&run.run_id);
+        report.runs += 1;
+    }
+    if let Some(referenced) = super::prompt::referenced_blobs() {
+        report.blobs =
+            super::prompt::sweep_blobs(&StoragePaths::trace_blobs_dir(), &referenced, BLOB_GRACE);
+    }
+    report
+}
@@ -9,6 +9,13 @@ use crate::schema::StoragePaths;
 static TRACE_DB: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
 
 pub(crate) fn persist_record(record: &TraceRecord) {
+    let summary = record
+        .summary
+        .as_deref()
+        .map(crate::security::redact_secrets);
+    let mut attrs = record.attrs.clone();
+    crate::security::redact_json_strings(&mut attrs);
+    let attrs_json = serde_json::to_string(&attrs).unwrap_or_else(|_| "{}".to_string());
     let _ = with_conn(|conn| {
         conn.execute(
             r#"
@@ -37,8 +44,8 @@ pub(crate) fn persist_record(record: &TraceRecord) {
                 record.ts_unix_ms as i64,
                 record.duration_ms.map(|v| v as i64),
                 trace_level_as_str(&record.level),
-                record.summary,
-                serde_json::to_string(&record.attrs).unwrap_or_else(|_| "{}".to_string()),
+                summary,
+                attrs_json,
             ],
         )?;
         Ok(())
@@ -153,6 +160,96 @@ pub(crate) fn get_records(run_id: &str) -> Option<Vec<TraceRecord>> {
     })
 }
 
+pub(crate) fn get_recent_records_by_name(
+    name: &str,
+    session_id: Option<&str>,
+    limit: usize,
+) -> Option<Vec<TraceRecord>> {
+    with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT record_id, trace_id, run_id, span_id, parent_span_id, session_id, task_id, turn_id,
+                   actor, kind, name, status, iteration, ts_unix_ms, duration_ms, level, summary, attrs_json
+            FROM trace_records
+            WHERE name = ?1
+              AND (?2 IS NULL
+                   OR session_id = ?2
+                   OR json_extract(attrs_json, '$.root_session_id') = ?2)
+            ORDER BY ts_unix_ms DESC, rowid DESC
+            LIMIT ?3
+            "#,
+        )?;
+        let rows = stmt.query_map(params![name, session_id, limit as i64], row_to_trace_record)?;
+        let mut records = Vec::new();
+        for row in rows.flatten() {
+            records.push(row);
+        }
+        Ok(records)
+    })
+}
+
+/// Records named `name` whose turn id starts with `turn_prefix`, oldest first.
+pub(crate) fn get_records_by_turn_prefix(
+    name: &str,
+    turn_prefix: &str,
+    session_id: Option<&str>,
+) -> Option<Vec<TraceRecord>> {
+    let pattern = format!(
+        "{}%",
+        turn_prefix
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT record_id, trace_id, run_id, span_id, parent_span_id, session_id, task_id, turn_id,
+                   actor, kind, name, status, iteration, ts_unix_ms, duration_ms, level, summary, attrs_json
+            FROM trace_records
+            WHERE name = ?1
+              AND turn_id LIKE ?2 ESCAPE '\'
+              AND (?3 IS NULL
+                   OR session_id = ?3
+                   OR json_extract(attrs_json, '$.root_session_id') = ?3)
+            ORDER BY ts_unix_ms ASC, rowid ASC
+            "#,
+        )?;
+        let rows = stmt.query_map(params![name, pattern, session_id], row_to_trace_record)?;
+        let mut records = Vec::new();
+        for row in rows.flatten() {
+            records.push(row);
+        }
+        Ok(records)
+    })
+}
+
+/// Remove a run and all of its records from the index.
+pub(crate) fn delete_run(run_id: &str) -> Option<()> {
+    with_conn(|conn| {
+        conn.execute(
+            "DELETE FROM trace_records WHERE run_id = ?1",
+            params![run_id],
+        )?;
+        conn.execute("DELETE FROM runs WHERE run_id = ?1", params![run_id])?;
+        Ok(())
+    })
+}
+
+/// `attrs_json` of every record named `name`; `None` when the index is
+/// unavailable.
+pub(crate) fn record_attrs_by_name(name: &str) -> Option<Vec<serde_json::Value>> {
+    with_conn(|conn| {
+        let mut stmt = conn.prepare("SELECT attrs_json FROM trace_records WHERE name = ?1")?;
+        let rows = stmt.query_map(params![name], |row| row.get::<_, String>(0))?;
+        let mut attrs = Vec::new();
+        for row in rows {
+            attrs.push(serde_json::from_str(&row?).unwrap_or_default());
+        }
+        Ok(attrs)
+    })
+}
+
 fn with_conn<T, F>(f: F) -> Option<T>
 where
     F: FnOnce(&Connection) -> rusqlite::Result<T>,